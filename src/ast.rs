@@ -1,37 +1,163 @@
-use crate::environment::Environment;
-use crate::interpreter::eval_binary_expr;
-use crate::values::{NullVal, NumberVal, RuntimeVal, Value, ValueType};
+use std::fmt;
+use crate::values::Value;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     //statements
     Program,
     VarDeclaration,
+    FunctionDeclaration,
+    ClassDeclaration,
+    EnumDeclaration,
+    ImportDeclaration,
+    ExportDeclaration,
+    ReturnStmt,
+    ThrowStmt,
+    TryStmt,
+    ForStmt,
+    YieldStmt,
+    Comment,
 
     //expressions
     AssignmentExpr,
+    MemberExpr,
+    IndexExpr,
+    CallExpr,
+    NewExpr,
+    FunctionExpr,
+    PropagateExpr,
+    MatchExpr,
+    ListExpr,
+    CaptureExpr,
     NumericLiteral,
+    FloatLiteral,
     NullLiteral,
+    StringLiteral,
     Identifier,
     BinaryExpr,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub kind: NodeType,
     pub body: Vec<Stmt>,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VarDeclaration {
     pub kind: NodeType,
     pub constant: bool,
     pub identifier: String,
     pub value: Option<Expr>,
+    pub doc: Option<String>, // text of the `///` comments right above the declaration
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDeclaration {
+    pub kind: NodeType,
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Vec<Stmt>,
+    pub generator: bool, // the body yields, so calling it returns a suspended generator
+    pub doc: Option<String>,
+    pub comments: Vec<String>, // plain comments above a method, kept for the formatter
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDeclaration {
+    pub kind: NodeType,
+    pub name: String,
+    pub superclass: Option<String>,
+    pub methods: Vec<FunctionDeclaration>,
+    pub static_methods: Vec<FunctionDeclaration>,
+    pub doc: Option<String>,
+    pub closing_comments: Vec<String>, // comments after the last method
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDeclaration {
+    pub kind: NodeType,
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+    pub doc: Option<String>,
+    pub closing_comments: Vec<String>, // comments after the last variant
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub comments: Vec<String>, // plain comments above the variant, kept for the formatter
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDeclaration {
+    pub kind: NodeType,
+    pub specifiers: Vec<(String, String)>, // imported name and local name
+    pub namespace: Option<String>,
+    pub source: String,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDeclaration {
+    pub kind: NodeType,
+    pub declaration: Box<Stmt>,
+}
+impl ExportDeclaration {
+    pub fn exported_name(&self) -> String {
+        match self.declaration.as_ref() {
+            Stmt::VarDeclaration(declaration) => return declaration.identifier.clone(),
+            Stmt::FunctionDeclaration(declaration) => return declaration.name.clone(),
+            Stmt::ClassDeclaration(declaration) => return declaration.name.clone(),
+            Stmt::EnumDeclaration(declaration) => return declaration.name.clone(),
+            _ => panic!("Only declarations can be exported."),
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnStmt {
+    pub kind: NodeType,
+    pub argument: Option<Expr>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrowStmt {
+    pub kind: NodeType,
+    pub argument: Expr,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryStmt {
+    pub kind: NodeType,
+    pub block: Vec<Stmt>,
+    pub param: Option<String>,
+    pub handler: Option<Vec<Stmt>>,
+    pub finalizer: Option<Vec<Stmt>>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForStmt {
+    pub kind: NodeType,
+    pub variable: String,
+    pub iterable: Expr,
+    pub body: Vec<Stmt>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct YieldStmt {
+    pub kind: NodeType,
+    pub argument: Expr,
+}
+// only produced when the parser is asked to keep comments, which the engines never do
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub kind: NodeType,
+    pub text: String,
+}
+#[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     Program(Program),
     VarDeclaration(VarDeclaration),
+    FunctionDeclaration(FunctionDeclaration),
+    ClassDeclaration(ClassDeclaration),
+    EnumDeclaration(EnumDeclaration),
+    ImportDeclaration(ImportDeclaration),
+    ExportDeclaration(ExportDeclaration),
+    ReturnStmt(ReturnStmt),
+    ThrowStmt(ThrowStmt),
+    TryStmt(TryStmt),
+    ForStmt(ForStmt),
+    YieldStmt(YieldStmt),
+    Comment(Comment),
     Expr(Expr),
 }
 impl Stmt {
@@ -43,18 +169,27 @@ impl Stmt {
                         return Expr::NumericLiteral(NumericLiteral {
                             kind: NodeType::NumericLiteral,
                             value: numeric_literal.value,
+                            raw: numeric_literal.raw.clone(),
                         })
                     },
+                    Expr::FloatLiteral(float_literal) => return Expr::FloatLiteral(float_literal.clone()),
                     Expr::NullLiteral(_) => {
                         return Expr::NullLiteral(NullLiteral {
                             kind: NodeType::NullLiteral,
                             value: Value::Null
                         })
                     },
+                    Expr::StringLiteral(string_literal) => {
+                        return Expr::StringLiteral(StringLiteral {
+                            kind: NodeType::StringLiteral,
+                            value: string_literal.value.clone(),
+                        })
+                    },
                     Expr::Identifier(identifier) => {
                         return Expr::Identifier(Identifier {
                             kind: NodeType::Identifier,
-                            symbol: identifier.symbol.clone()
+                            symbol: identifier.symbol.clone(),
+                            slot: identifier.slot,
                         })
                     },
                     Expr::BinaryExpr(binary_expr) => {
@@ -62,7 +197,7 @@ impl Stmt {
                             kind: NodeType::BinaryExpr,
                             left: binary_expr.left.clone(),
                             right: binary_expr.right.clone(),
-                            operator: binary_expr.operator.clone(),
+                            operator: binary_expr.operator,
                         }))
                     },
                     Expr::AssignmentExpr(assignment_expr) => {
@@ -72,17 +207,73 @@ impl Stmt {
                             value: assignment_expr.value.clone(),
                         }))
                     },
+                    Expr::MemberExpr(member_expr) => {
+                        return Expr::MemberExpr(Box::new(MemberExpr {
+                            kind: NodeType::MemberExpr,
+                            object: member_expr.object.clone(),
+                            property: member_expr.property.clone(),
+                        }))
+                    },
+                    Expr::IndexExpr(index_expr) => return Expr::IndexExpr(index_expr.clone()),
+                    Expr::ListExpr(list_expr) => return Expr::ListExpr(list_expr.clone()),
+                    Expr::CaptureExpr(capture_expr) => return Expr::CaptureExpr(capture_expr.clone()),
+                    Expr::CallExpr(call_expr) => {
+                        return Expr::CallExpr(Box::new(CallExpr {
+                            kind: NodeType::CallExpr,
+                            callee: call_expr.callee.clone(),
+                            arguments: call_expr.arguments.clone(),
+                        }))
+                    },
+                    Expr::NewExpr(new_expr) => {
+                        return Expr::NewExpr(Box::new(NewExpr {
+                            kind: NodeType::NewExpr,
+                            callee: new_expr.callee.clone(),
+                            arguments: new_expr.arguments.clone(),
+                        }))
+                    },
+                    Expr::FunctionExpr(function_expr) => {
+                        return Expr::FunctionExpr(FunctionExpr {
+                            kind: NodeType::FunctionExpr,
+                            parameters: function_expr.parameters.clone(),
+                            body: function_expr.body.clone(),
+                            generator: function_expr.generator,
+                        })
+                    },
+                    Expr::PropagateExpr(propagate_expr) => {
+                        return Expr::PropagateExpr(Box::new(PropagateExpr {
+                            kind: NodeType::PropagateExpr,
+                            argument: propagate_expr.argument.clone(),
+                        }))
+                    },
+                    Expr::MatchExpr(match_expr) => {
+                        return Expr::MatchExpr(Box::new(MatchExpr {
+                            kind: NodeType::MatchExpr,
+                            subject: match_expr.subject.clone(),
+                            arms: match_expr.arms.clone(),
+                        }))
+                    },
                 }
             },
             _ => panic!("This statement is not an expression.")
         }
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     AssignmentExpr(Box<AssignmentExpr>),
+    MemberExpr(Box<MemberExpr>),
+    IndexExpr(Box<IndexExpr>),
+    CallExpr(Box<CallExpr>),
+    NewExpr(Box<NewExpr>),
+    FunctionExpr(FunctionExpr),
+    PropagateExpr(Box<PropagateExpr>),
+    MatchExpr(Box<MatchExpr>),
+    ListExpr(ListExpr),
+    CaptureExpr(CaptureExpr),
     NumericLiteral(NumericLiteral),
+    FloatLiteral(FloatLiteral),
     NullLiteral(NullLiteral),
+    StringLiteral(StringLiteral),
     Identifier(Identifier),
     BinaryExpr(Box<BinaryExpr>),
 }
@@ -94,53 +285,183 @@ impl Expr {
             _ => panic!("Expression is not a binary expression"),
         }
     }
-    pub fn expr_to_runtime_val(&self, env: &mut Environment) -> RuntimeVal {
-        match self {
-            Expr::NumericLiteral(numeric_literal) => RuntimeVal::NumberVal(NumberVal {
-                value_type: ValueType::Number,
-                value: numeric_literal.value,
-            }),
-            Expr::NullLiteral(null_literal) => RuntimeVal::NullVal(NullVal {
-                value_type: ValueType::Null,
-                value: null_literal.value.clone(),
-            }),
-            Expr::Identifier(identifier) => {
-                return env.lookup_var(identifier.symbol.clone())
-            },
-            Expr::BinaryExpr(binary_expr) => RuntimeVal::NumberVal(NumberVal {
-                value_type: ValueType::Number,
-                value: eval_binary_expr(binary_expr.as_ref().clone(), env).to_number_val().value,
-            }),
-            Expr::AssignmentExpr(_assignment_expr) => panic!("huh"),//fix this too
-        }
-    }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssignmentExpr {
     pub kind: NodeType,
     pub assigne: Expr,
     pub value: Expr,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberExpr {
+    pub kind: NodeType,
+    pub object: Expr,
+    pub property: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexExpr {
+    pub kind: NodeType,
+    pub object: Expr,
+    pub index: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallExpr {
+    pub kind: NodeType,
+    pub callee: Expr,
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewExpr {
+    pub kind: NodeType,
+    pub callee: Expr,
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionExpr {
+    pub kind: NodeType,
+    pub parameters: Vec<String>,
+    pub body: Vec<Stmt>,
+    pub generator: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropagateExpr {
+    pub kind: NodeType,
+    pub argument: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchExpr {
+    pub kind: NodeType,
+    pub subject: Expr,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListExpr {
+    pub kind: NodeType,
+    pub elements: Vec<Expr>,
+}
+
+// `$(ls -la)`, which runs the command and evaluates to what it printed
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureExpr {
+    pub kind: NodeType,
+    pub command: String, // the text between the parentheses, exactly as written
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Literal(Expr),
+    Variant {
+        enum_name: String,
+        variant: String,
+        fields: Vec<Pattern>,
+    },
+}
+
+impl Pattern {
+    // true when the pattern matches every value, so it needs no further coverage
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Binding(_) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinaryExpr {
     pub kind: NodeType,
     pub left: Expr,
     pub right: Expr,
-    pub operator: String,
+    pub operator: BinaryOperator,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    InstanceOf,
+    Pipe,
 }
-#[derive(Debug, Clone)]
+impl BinaryOperator {
+    pub fn from_symbol(symbol: &str) -> Option<BinaryOperator> {
+        match symbol {
+            "+" => return Some(BinaryOperator::Add),
+            "-" => return Some(BinaryOperator::Subtract),
+            "*" => return Some(BinaryOperator::Multiply),
+            "/" => return Some(BinaryOperator::Divide),
+            "%" => return Some(BinaryOperator::Remainder),
+            "==" => return Some(BinaryOperator::Equal),
+            "!=" => return Some(BinaryOperator::NotEqual),
+            "instanceof" => return Some(BinaryOperator::InstanceOf),
+            "|" => return Some(BinaryOperator::Pipe),
+            _ => return None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => return "+",
+            BinaryOperator::Subtract => return "-",
+            BinaryOperator::Multiply => return "*",
+            BinaryOperator::Divide => return "/",
+            BinaryOperator::Remainder => return "%",
+            BinaryOperator::Equal => return "==",
+            BinaryOperator::NotEqual => return "!=",
+            BinaryOperator::InstanceOf => return "instanceof",
+            BinaryOperator::Pipe => return "|",
+        }
+    }
+}
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.symbol())
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
 pub struct Identifier {
     pub kind: NodeType,
     pub symbol: String,
+    pub slot: Option<(usize, usize)>, // scope depth and index, filled in by the resolver
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NumericLiteral {
     pub kind: NodeType,
     pub value: i64,
+    pub raw: Option<String>, // source spelling such as 0xFF or 1_000, none for computed literals
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatLiteral {
+    pub kind: NodeType,
+    pub value: f64,
+    pub raw: String,
+}
+#[derive(Debug, Clone, PartialEq)]
 pub struct NullLiteral {
     pub kind: NodeType,
     pub value: Value,
 }
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral {
+    pub kind: NodeType,
+    pub value: String,
+}
\ No newline at end of file
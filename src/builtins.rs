@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::environment::Environment;
+use crate::fs::fs_module;
+use crate::interpreter::{call_function, EvalResult};
+use crate::json::{json_module, object_class};
+use crate::iterators::{iter_enum, native_iter, native_range};
+use crate::limits::require_io;
+use crate::math::math_module;
+use crate::path::path_module;
+use crate::process::{native_exec, native_exit, native_getenv, native_run, native_setenv, process_enum};
+use crate::random::random_module;
+use crate::time::{native_sleep, time_module};
+use crate::values::{BooleanVal, ErrorVal, NativeFnVal, NullVal, ResultVal, RuntimeVal};
+
+// fs and path are only declared for hosts that allow I/O, and always last, so switching them
+// on or off leaves the slots of every other builtin where the resolver expects them
+pub fn declare_builtins(env: &Rc<RefCell<Environment>>, allow_io: bool) {
+    let mut scope = env.borrow_mut();
+    scope.declare_var(String::from("true"), BooleanVal::make_bool(true).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("false"), BooleanVal::make_bool(false).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("Ok"), NativeFnVal::make_native_fn("Ok", native_ok).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("Err"), NativeFnVal::make_native_fn("Err", native_err).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("print"), NativeFnVal::make_native_fn("print", native_print).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("math"), math_module().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("Iter"), iter_enum().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("iter"), NativeFnVal::make_native_fn("iter", native_iter).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("range"), NativeFnVal::make_native_fn("range", native_range).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("json"), json_module().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("Object"), object_class().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("Process"), process_enum().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("exec"), NativeFnVal::make_native_fn("exec", native_exec).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("run"), NativeFnVal::make_native_fn("run", native_run).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("getenv"), NativeFnVal::make_native_fn("getenv", native_getenv).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("setenv"), NativeFnVal::make_native_fn("setenv", native_setenv).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("exit"), NativeFnVal::make_native_fn("exit", native_exit).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("time"), time_module().to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("sleep"), NativeFnVal::make_native_fn("sleep", native_sleep).to_runtime_val(), true).unwrap();
+    scope.declare_var(String::from("random"), random_module().to_runtime_val(), true).unwrap();
+    if allow_io {
+        scope.declare_var(String::from("fs"), fs_module().to_runtime_val(), true).unwrap();
+        scope.declare_var(String::from("path"), path_module().to_runtime_val(), true).unwrap();
+    }
+}
+
+pub fn expect_args(name: &str, arguments: &[RuntimeVal], count: usize) -> Result<(), ErrorVal> {
+    if arguments.len() != count {
+        return Err(ErrorVal::make_error(
+            "TypeError",
+            format!("{} expects {} argument(s) but received {}", name, count, arguments.len())
+        ))
+    }
+    return Ok(())
+}
+
+fn native_ok(mut arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("Ok", &arguments, 1)?;
+    return Ok(ResultVal::make_ok(arguments.remove(0)).to_runtime_val())
+}
+
+fn native_err(mut arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("Err", &arguments, 1)?;
+    return Ok(ResultVal::make_err(arguments.remove(0)).to_runtime_val())
+}
+
+fn native_print(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    require_io("print")?;
+    let parts: Vec<String> = arguments.iter().map(RuntimeVal::to_string).collect();
+    println!("{}", parts.join(" "));
+    return Ok(NullVal::make_null().to_runtime_val())
+}
+
+pub fn call_result_method(result: ResultVal, method: &str, mut arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    if method == "is_ok" {
+        expect_args(method, &arguments, 0)?;
+        return Ok(BooleanVal::make_bool(result.is_ok).to_runtime_val())
+    } else if method == "is_err" {
+        expect_args(method, &arguments, 0)?;
+        return Ok(BooleanVal::make_bool(!result.is_ok).to_runtime_val())
+    } else if method == "unwrap" {
+        expect_args(method, &arguments, 0)?;
+        if result.is_ok {
+            return Ok(*result.value)
+        }
+        return Err(ErrorVal::make_error(
+            "UnwrapError",
+            format!("Called unwrap on an Err value: {}", result.value.repr())
+        ).into())
+    } else if method == "unwrap_or" {
+        expect_args(method, &arguments, 1)?;
+        if result.is_ok {
+            return Ok(*result.value)
+        }
+        return Ok(arguments.remove(0))
+    } else if method == "map" {
+        expect_args(method, &arguments, 1)?;
+        if result.is_ok {
+            let mapped = call_function(arguments.remove(0), vec![*result.value], env)?;
+            return Ok(ResultVal::make_ok(mapped).to_runtime_val())
+        }
+        return Ok(result.to_runtime_val())
+    } else if method == "map_err" {
+        expect_args(method, &arguments, 1)?;
+        if !result.is_ok {
+            let mapped = call_function(arguments.remove(0), vec![*result.value], env)?;
+            return Ok(ResultVal::make_err(mapped).to_runtime_val())
+        }
+        return Ok(result.to_runtime_val())
+    }
+    return Err(ErrorVal::make_error(
+        "TypeError",
+        format!("Result has no method '{}'", method)
+    ).into())
+}
@@ -0,0 +1,494 @@
+use std::rc::Rc;
+use crate::ast::{AssignmentExpr, BinaryOperator, CallExpr, ClassDeclaration, Expr, ForStmt, FunctionDeclaration, ImportDeclaration, MatchExpr, Pattern, Program, Stmt, TryStmt};
+use crate::limits::grow_stack;
+use crate::values::{EnumVal, ErrorVal, FloatVal, NumberVal, RuntimeVal, StringVal, ValueType};
+
+// jump targets are absolute instruction indices within the chunk
+#[derive(Debug, Clone)]
+pub enum OpCode {
+    Constant(usize),
+    Null,
+    Pop,
+    DefineVar(usize, bool), // declares the value on top of the stack, leaving it there
+    LoadVar(usize),
+    StoreVar(usize),
+    LoadSlot(usize, usize, usize), // scope depth, slot and name of a resolved variable
+    StoreSlot(usize, usize, usize),
+    PushScope,
+    PopScope,
+    Binary(BinaryOperator),
+    GetProperty(usize),
+    SetProperty(usize),
+    GetIndex,
+    SetIndex,
+    MakeList(usize),
+    Call(usize),
+    CallMethod(usize, usize),
+    SuperCall(Option<usize>, usize),
+    New(usize),
+    MakeFunction(usize),
+    MakeClass(usize),
+    Import(usize),
+    Throw,
+    SetupTry(usize),
+    PopTry,
+    Return,
+    JumpIfOkUnwrap(usize),
+    MatchPattern(usize, usize), // on success pops the subject and pushes a scope holding the bindings
+    MatchFail,
+    Jump(usize),
+    GetIter, // replaces the value on top of the stack with an iterator over it
+    IterNext(usize), // pushes the iterator's next item, or pops the iterator and jumps once it is done
+    Yield, // suspends a generator, handing out the value on top of the stack
+    Capture(usize), // runs the named command line and pushes what it printed
+}
+
+#[derive(Debug)]
+pub struct FunctionProto {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub generator: bool,
+    pub chunk: Rc<Chunk>,
+}
+
+#[derive(Debug)]
+pub struct ClassProto {
+    pub name: String,
+    pub superclass: Option<String>,
+    pub methods: Vec<usize>,
+    pub static_methods: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    pub constants: Vec<RuntimeVal>,
+    pub names: Vec<String>,
+    pub functions: Vec<FunctionProto>,
+    pub classes: Vec<ClassProto>,
+    pub patterns: Vec<Pattern>,
+    pub imports: Vec<ImportDeclaration>,
+}
+
+// a try block or catch body whose finalizer has to run before a return leaves it
+#[derive(Debug, Clone)]
+struct TryRegion {
+    scope_depth: usize,
+    finalizer: Option<Vec<Stmt>>,
+}
+
+struct Compiler {
+    chunk: Chunk,
+    scope_depth: usize,
+    regions: Vec<TryRegion>,
+}
+
+pub fn compile_program(program: Program) -> Chunk {
+    let mut compiler = Compiler::new();
+    compiler.compile_body(program.body);
+    compiler.emit(OpCode::Return);
+    return compiler.chunk
+}
+
+// also used by the tree walker, which hands generator bodies to the VM so they can be suspended
+pub fn compile_function_body(body: Vec<Stmt>) -> Chunk {
+    let mut compiler = Compiler::new();
+    compiler.compile_body(body);
+    compiler.emit(OpCode::Return);
+    return compiler.chunk
+}
+
+fn compile_function(name: String, parameters: Vec<String>, body: Vec<Stmt>, generator: bool) -> FunctionProto {
+    return FunctionProto {
+        name,
+        parameters,
+        generator,
+        chunk: Rc::new(compile_function_body(body)),
+    }
+}
+
+impl Compiler {
+    fn new() -> Self {
+        return Compiler {
+            chunk: Chunk::default(),
+            scope_depth: 0,
+            regions: Vec::new(),
+        }
+    }
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.chunk.code.push(op);
+        return self.chunk.code.len() - 1
+    }
+
+    // points the jump at `index` to the next instruction to be emitted
+    fn patch(&mut self, index: usize) {
+        let here = self.chunk.code.len();
+        match &mut self.chunk.code[index] {
+            OpCode::Jump(target) | OpCode::SetupTry(target) | OpCode::JumpIfOkUnwrap(target) | OpCode::MatchPattern(_, target) | OpCode::IterNext(target) => *target = here,
+            op => panic!("Cannot patch a non-jump instruction: {:?}", op),
+        }
+    }
+
+    fn name(&mut self, name: String) -> usize {
+        if let Some(index) = self.chunk.names.iter().position(|existing| *existing == name) {
+            return index
+        }
+        self.chunk.names.push(name);
+        return self.chunk.names.len() - 1
+    }
+
+    fn constant(&mut self, value: RuntimeVal) {
+        self.chunk.constants.push(value);
+        let index = self.chunk.constants.len() - 1;
+        self.emit(OpCode::Constant(index));
+    }
+
+    fn function(&mut self, declaration: FunctionDeclaration) -> usize {
+        self.chunk.functions.push(compile_function(declaration.name, declaration.parameters, declaration.body, declaration.generator));
+        return self.chunk.functions.len() - 1
+    }
+
+    // every statement leaves exactly one value on the stack, so a body leaves its last one
+    fn compile_body(&mut self, body: Vec<Stmt>) {
+        if body.is_empty() {
+            self.emit(OpCode::Null);
+            return
+        }
+        for (index, statement) in body.into_iter().enumerate() {
+            if index > 0 {
+                self.emit(OpCode::Pop);
+            }
+            self.compile_stmt(statement);
+        }
+    }
+
+    fn compile_scoped_body(&mut self, body: Vec<Stmt>) {
+        self.emit(OpCode::PushScope);
+        self.scope_depth += 1;
+        self.compile_body(body);
+        self.emit(OpCode::PopScope);
+        self.scope_depth -= 1;
+    }
+
+    fn compile_stmt(&mut self, statement: Stmt) {
+        grow_stack(|| self.compile_stmt_node(statement))
+    }
+
+    fn compile_stmt_node(&mut self, statement: Stmt) {
+        match statement {
+            Stmt::Program(program) => self.compile_body(program.body),
+            Stmt::VarDeclaration(declaration) => {
+                match declaration.value {
+                    Some(value) => self.compile_expr(value),
+                    None => { self.emit(OpCode::Null); },
+                }
+                let name = self.name(declaration.identifier);
+                self.emit(OpCode::DefineVar(name, declaration.constant));
+            },
+            Stmt::FunctionDeclaration(declaration) => {
+                let name = self.name(declaration.name.clone());
+                let function = self.function(declaration);
+                self.emit(OpCode::MakeFunction(function));
+                self.emit(OpCode::DefineVar(name, false));
+            },
+            Stmt::ClassDeclaration(declaration) => self.compile_class(declaration),
+            Stmt::EnumDeclaration(declaration) => {
+                let name = self.name(declaration.name.clone());
+                self.constant(EnumVal {
+                    value_type: ValueType::Enum,
+                    name: declaration.name,
+                    variants: declaration.variants.into_iter().map(|variant| (variant.name, variant.fields)).collect(),
+                }.to_runtime_val());
+                self.emit(OpCode::DefineVar(name, true));
+            },
+            Stmt::ImportDeclaration(declaration) => {
+                self.chunk.imports.push(declaration);
+                self.emit(OpCode::Import(self.chunk.imports.len() - 1));
+            },
+            Stmt::ExportDeclaration(declaration) => self.compile_stmt(*declaration.declaration),
+            Stmt::ReturnStmt(stmt) => {
+                match stmt.argument {
+                    Some(argument) => self.compile_expr(argument),
+                    None => { self.emit(OpCode::Null); },
+                }
+                self.emit_return();
+            },
+            Stmt::ThrowStmt(stmt) => {
+                self.compile_expr(stmt.argument);
+                self.emit(OpCode::Throw);
+            },
+            Stmt::TryStmt(stmt) => self.compile_try(stmt),
+            Stmt::ForStmt(stmt) => self.compile_for(stmt),
+            Stmt::YieldStmt(stmt) => {
+                self.compile_expr(stmt.argument);
+                self.emit(OpCode::Yield);
+            },
+            Stmt::Comment(_) => unreachable!("comments are only kept for the formatter"),
+            Stmt::Expr(expr) => self.compile_expr(expr),
+        }
+    }
+
+    fn compile_class(&mut self, declaration: ClassDeclaration) {
+        let name = self.name(declaration.name.clone());
+        let methods = declaration.methods.into_iter().map(|method| self.function(method)).collect();
+        let static_methods = declaration.static_methods.into_iter().map(|method| self.function(method)).collect();
+        self.chunk.classes.push(ClassProto {
+            name: declaration.name,
+            superclass: declaration.superclass,
+            methods,
+            static_methods,
+        });
+        self.emit(OpCode::MakeClass(self.chunk.classes.len() - 1));
+        self.emit(OpCode::DefineVar(name, false));
+    }
+
+    // leaves every enclosing try region innermost first, running its finalizer on the way out
+    fn emit_return(&mut self) {
+        let regions = std::mem::take(&mut self.regions);
+        let scope_depth = self.scope_depth;
+        for (index, region) in regions.iter().enumerate().rev() {
+            for _ in region.scope_depth..self.scope_depth {
+                self.emit(OpCode::PopScope);
+            }
+            self.scope_depth = region.scope_depth;
+            self.emit(OpCode::PopTry);
+            if let Some(ref finalizer) = region.finalizer {
+                self.regions = regions[..index].to_vec();
+                self.compile_scoped_body(finalizer.clone());
+                self.emit(OpCode::Pop);
+            }
+        }
+        self.regions = regions;
+        self.scope_depth = scope_depth;
+        self.emit(OpCode::Return);
+    }
+
+    fn compile_try(&mut self, stmt: TryStmt) {
+        let scope_depth = self.scope_depth;
+        let setup = self.emit(OpCode::SetupTry(0));
+        self.regions.push(TryRegion { scope_depth, finalizer: stmt.finalizer.clone() });
+        self.compile_scoped_body(stmt.block);
+        self.regions.pop();
+        self.emit(OpCode::PopTry);
+        let mut exits = vec![self.emit(OpCode::Jump(0))];
+        // the handler lands here with the thrown error on top of the stack
+        self.patch(setup);
+        match stmt.handler {
+            Some(handler) => {
+                self.emit(OpCode::PushScope);
+                self.scope_depth += 1;
+                if let Some(param) = stmt.param {
+                    let name = self.name(param);
+                    self.emit(OpCode::DefineVar(name, false));
+                }
+                self.emit(OpCode::Pop);
+                let handler_setup = stmt.finalizer.as_ref().map(|_| self.emit(OpCode::SetupTry(0)));
+                if handler_setup.is_some() {
+                    self.regions.push(TryRegion { scope_depth, finalizer: stmt.finalizer.clone() });
+                }
+                self.compile_body(handler);
+                if handler_setup.is_some() {
+                    self.regions.pop();
+                    self.emit(OpCode::PopTry);
+                }
+                self.emit(OpCode::PopScope);
+                self.scope_depth -= 1;
+                if let Some(handler_setup) = handler_setup {
+                    exits.push(self.emit(OpCode::Jump(0)));
+                    // errors thrown by the catch body land inside its scope
+                    self.patch(handler_setup);
+                    self.emit(OpCode::PopScope);
+                    self.compile_rethrow(stmt.finalizer.clone());
+                }
+            },
+            None => self.compile_rethrow(stmt.finalizer.clone()),
+        }
+        for exit in exits {
+            self.patch(exit);
+        }
+        if let Some(finalizer) = stmt.finalizer {
+            self.compile_scoped_body(finalizer);
+            self.emit(OpCode::Pop);
+        }
+    }
+
+    // the iterator stays on the stack for the whole loop; each pass binds the item in a fresh scope
+    fn compile_for(&mut self, stmt: ForStmt) {
+        self.compile_expr(stmt.iterable);
+        self.emit(OpCode::GetIter);
+        let next = self.emit(OpCode::IterNext(0));
+        self.emit(OpCode::PushScope);
+        self.scope_depth += 1;
+        let name = self.name(stmt.variable);
+        self.emit(OpCode::DefineVar(name, false));
+        self.emit(OpCode::Pop);
+        self.compile_body(stmt.body);
+        self.emit(OpCode::Pop);
+        self.emit(OpCode::PopScope);
+        self.scope_depth -= 1;
+        self.emit(OpCode::Jump(next));
+        self.patch(next);
+        self.emit(OpCode::Null);
+    }
+
+    fn compile_rethrow(&mut self, finalizer: Option<Vec<Stmt>>) {
+        if let Some(finalizer) = finalizer {
+            self.compile_scoped_body(finalizer);
+            self.emit(OpCode::Pop);
+        }
+        self.emit(OpCode::Throw);
+    }
+
+    fn compile_expr(&mut self, expr: Expr) {
+        grow_stack(|| self.compile_expr_node(expr))
+    }
+
+    fn compile_expr_node(&mut self, expr: Expr) {
+        match expr {
+            Expr::NumericLiteral(literal) => self.constant(NumberVal {
+                value_type: ValueType::Number,
+                value: literal.value,
+            }.to_runtime_val()),
+            Expr::FloatLiteral(literal) => self.constant(FloatVal::make_float(literal.value).to_runtime_val()),
+            Expr::NullLiteral(_) => { self.emit(OpCode::Null); },
+            Expr::StringLiteral(literal) => self.constant(StringVal::make_string(literal.value).to_runtime_val()),
+            Expr::CaptureExpr(capture_expr) => {
+                let command = self.name(capture_expr.command);
+                self.emit(OpCode::Capture(command));
+            },
+            Expr::Identifier(identifier) => {
+                let name = self.name(identifier.symbol);
+                match identifier.slot {
+                    Some((depth, slot)) => self.emit(OpCode::LoadSlot(depth, slot, name)),
+                    None => self.emit(OpCode::LoadVar(name)),
+                };
+            },
+            Expr::BinaryExpr(binary_expr) => {
+                self.compile_expr(binary_expr.left);
+                self.compile_expr(binary_expr.right);
+                self.emit(OpCode::Binary(binary_expr.operator));
+            },
+            Expr::AssignmentExpr(assignment_expr) => self.compile_assignment(*assignment_expr),
+            Expr::MemberExpr(member_expr) => {
+                self.compile_expr(member_expr.object);
+                let name = self.name(member_expr.property);
+                self.emit(OpCode::GetProperty(name));
+            },
+            Expr::IndexExpr(index_expr) => {
+                self.compile_expr(index_expr.object);
+                self.compile_expr(index_expr.index);
+                self.emit(OpCode::GetIndex);
+            },
+            Expr::ListExpr(list_expr) => {
+                let count = list_expr.elements.len();
+                self.compile_arguments(list_expr.elements);
+                self.emit(OpCode::MakeList(count));
+            },
+            Expr::CallExpr(call_expr) => self.compile_call(*call_expr),
+            Expr::NewExpr(new_expr) => {
+                self.compile_expr(new_expr.callee);
+                let argc = new_expr.arguments.len();
+                self.compile_arguments(new_expr.arguments);
+                self.emit(OpCode::New(argc));
+            },
+            Expr::FunctionExpr(function_expr) => {
+                self.chunk.functions.push(compile_function(String::from("<anonymous>"), function_expr.parameters, function_expr.body, function_expr.generator));
+                self.emit(OpCode::MakeFunction(self.chunk.functions.len() - 1));
+            },
+            Expr::PropagateExpr(propagate_expr) => {
+                self.compile_expr(propagate_expr.argument);
+                let check = self.emit(OpCode::JumpIfOkUnwrap(0));
+                self.emit_return();
+                self.patch(check);
+            },
+            Expr::MatchExpr(match_expr) => self.compile_match(*match_expr),
+        }
+    }
+
+    fn compile_arguments(&mut self, arguments: Vec<Expr>) {
+        for argument in arguments {
+            self.compile_expr(argument);
+        }
+    }
+
+    fn compile_call(&mut self, call: CallExpr) {
+        let argc = call.arguments.len();
+        if let Expr::Identifier(ref identifier) = call.callee {
+            if identifier.symbol == "super" {
+                self.compile_arguments(call.arguments);
+                self.emit(OpCode::SuperCall(None, argc));
+                return
+            }
+        }
+        if let Expr::MemberExpr(member) = call.callee {
+            let name = self.name(member.property);
+            if let Expr::Identifier(ref identifier) = member.object {
+                if identifier.symbol == "super" {
+                    self.compile_arguments(call.arguments);
+                    self.emit(OpCode::SuperCall(Some(name), argc));
+                    return
+                }
+            }
+            self.compile_expr(member.object);
+            self.compile_arguments(call.arguments);
+            self.emit(OpCode::CallMethod(name, argc));
+            return
+        }
+        self.compile_expr(call.callee);
+        self.compile_arguments(call.arguments);
+        self.emit(OpCode::Call(argc));
+    }
+
+    fn compile_assignment(&mut self, assignment: AssignmentExpr) {
+        match assignment.assigne {
+            Expr::Identifier(identifier) => {
+                self.compile_expr(assignment.value);
+                let name = self.name(identifier.symbol);
+                match identifier.slot {
+                    Some((depth, slot)) => self.emit(OpCode::StoreSlot(depth, slot, name)),
+                    None => self.emit(OpCode::StoreVar(name)),
+                };
+            },
+            Expr::MemberExpr(member) => {
+                self.compile_expr(member.object);
+                self.compile_expr(assignment.value);
+                let name = self.name(member.property);
+                self.emit(OpCode::SetProperty(name));
+            },
+            Expr::IndexExpr(index_expr) => {
+                self.compile_expr(index_expr.object);
+                self.compile_expr(index_expr.index);
+                self.compile_expr(assignment.value);
+                self.emit(OpCode::SetIndex);
+            },
+            assigne => {
+                // the stack trace is filled in when the error is thrown
+                self.constant(ErrorVal::make_error(
+                    "TypeError",
+                    format!("Invalid left-hand side in assignment expression: {:?}", assigne)
+                ).to_runtime_val());
+                self.emit(OpCode::Throw);
+            },
+        }
+    }
+
+    fn compile_match(&mut self, match_expr: MatchExpr) {
+        self.compile_expr(match_expr.subject);
+        let mut ends = Vec::new();
+        for arm in match_expr.arms {
+            self.chunk.patterns.push(arm.pattern);
+            let test = self.emit(OpCode::MatchPattern(self.chunk.patterns.len() - 1, 0));
+            self.scope_depth += 1;
+            self.compile_body(arm.body);
+            self.emit(OpCode::PopScope);
+            self.scope_depth -= 1;
+            ends.push(self.emit(OpCode::Jump(0)));
+            self.patch(test);
+        }
+        self.emit(OpCode::MatchFail);
+        for end in ends {
+            self.patch(end);
+        }
+    }
+}
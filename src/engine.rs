@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::ast::Program;
+use crate::builtins::declare_builtins;
+use crate::environment::Environment;
+use crate::interpreter::{EvalResult, Interrupt};
+use crate::limits::{self, Limits};
+use crate::modules::{self, run_file, run_program, Session};
+use crate::parser::{parse_source, SyntaxError};
+use crate::values::{ErrorVal, NativeFnVal, RuntimeVal};
+
+#[derive(Debug)]
+pub enum EngineError {
+    Syntax(SyntaxError),
+    Runtime(ErrorVal), // an uncaught throw, with the stack it was thrown from
+    Io(std::io::Error),
+    Exit(i32), // the script called exit() with this status
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Syntax(error) => return write!(f, "{}", error),
+            EngineError::Runtime(error) => return write!(f, "Uncaught {}: {}", error.kind, error.message),
+            EngineError::Io(error) => return write!(f, "{}", error),
+            EngineError::Exit(status) => return write!(f, "Exited with status {}", status),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+// one interpreter instance for embedding: the builtins plus a global scope that keeps its
+// bindings, and the modules it has imported, from one evaluation to the next
+pub struct Engine {
+    globals: Rc<RefCell<Environment>>,
+    session: Rc<RefCell<Session>>,
+    warnings: Vec<String>,
+    limits: Limits,
+    interrupt: Arc<AtomicBool>,
+}
+
+fn builtins(limits: &Limits) -> Rc<RefCell<Environment>> {
+    let builtins = Rc::new(RefCell::new(Environment::global(None)));
+    declare_builtins(&builtins, limits.allow_io);
+    return builtins
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        return Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let limits = Limits::default();
+        let globals = Rc::new(RefCell::new(Environment::global(Some(builtins(&limits)))));
+        return Engine {
+            globals,
+            session: Rc::new(RefCell::new(Session::default())),
+            warnings: Vec::new(),
+            limits,
+            interrupt: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // applies to every evaluation from now on, each of which gets the full budget; turning I/O
+    // on or off also adds or removes the fs and path globals
+    pub fn set_limits(&mut self, limits: Limits) {
+        if limits.allow_io != self.limits.allow_io {
+            self.globals.borrow_mut().set_parent(Some(builtins(&limits)));
+        }
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        return &self.limits
+    }
+
+    // runs programs, including the modules they import, on the bytecode VM instead of the tree walker
+    pub fn set_use_vm(&mut self, enabled: bool) {
+        self.session.borrow_mut().use_vm = enabled;
+    }
+
+    // 0 runs programs as written; 1 folds constants and 2 also propagates them and drops dead code
+    pub fn set_opt_level(&mut self, level: u8) {
+        self.session.borrow_mut().opt_level = level;
+    }
+
+    // setting the flag from any thread stops the evaluation that is running, or the next one to
+    // start, with an InterruptError; the flag is cleared once that evaluation returns
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        return Arc::clone(&self.interrupt)
+    }
+
+    fn finish(&mut self, run: impl FnOnce(&Rc<RefCell<Environment>>) -> EvalResult) -> Result<RuntimeVal, EngineError> {
+        let previous = limits::install(self.limits.clone(), Some(Arc::clone(&self.interrupt)));
+        let previous_session = modules::install(Rc::clone(&self.session));
+        let result = run(&self.globals);
+        modules::restore(previous_session);
+        limits::restore(previous);
+        self.interrupt.store(false, Ordering::Relaxed);
+        let warnings = std::mem::take(&mut self.session.borrow_mut().warnings);
+        self.warnings.extend(warnings);
+        match result {
+            Ok(value) | Err(Interrupt::Return(value)) => return Ok(value),
+            Err(Interrupt::Throw(error)) => return Err(EngineError::Runtime(error)),
+            Err(Interrupt::Exit(status)) => return Err(EngineError::Exit(status)),
+        }
+    }
+
+    // runs an already parsed program in the global scope
+    pub fn run(&mut self, program: Program) -> Result<RuntimeVal, EngineError> {
+        return self.finish(|globals| run_program(program, globals))
+    }
+
+    pub fn eval(&mut self, source: &str) -> Result<RuntimeVal, EngineError> {
+        let (program, warnings) = parse_source(source).map_err(EngineError::Syntax)?;
+        self.warnings.extend(warnings);
+        return self.run(program)
+    }
+
+    // like eval, but imports in the file resolve relative to it
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<RuntimeVal, EngineError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(EngineError::Io)?;
+        let (program, warnings) = parse_source(&source).map_err(EngineError::Syntax)?;
+        self.warnings.extend(warnings.into_iter().map(|warning| format!("{}: {}", path.display(), warning)));
+        return self.finish(|globals| run_file(path, program, globals))
+    }
+
+    // parser warnings gathered since the last call, such as non-exhaustive matches, including
+    // those from imported modules
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        return std::mem::take(&mut self.warnings)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<RuntimeVal>) {
+        self.globals.borrow_mut().define(String::from(name), value.into());
+    }
+
+    // looks through the globals and then the builtins
+    pub fn get_global(&self, name: &str) -> Option<RuntimeVal> {
+        return self.globals.borrow().lookup_var(String::from(name)).ok()
+    }
+
+    pub fn register_fn(&mut self, name: &str, function: impl Fn(Vec<RuntimeVal>) -> Result<RuntimeVal, ErrorVal> + 'static) {
+        let native_fn = NativeFnVal::make_native_fn(name, move |arguments, _env| {
+            return function(arguments).map_err(Interrupt::from)
+        });
+        self.set_global(name, native_fn.to_runtime_val());
+    }
+
+    // the globals defined so far, with their values and constness, in declaration order
+    pub fn bindings(&self) -> Vec<(String, RuntimeVal, bool)> {
+        let globals = self.globals.borrow();
+        return globals.declared().into_iter().flatten().filter_map(|(name, constant)| {
+            let value = globals.lookup_var(name.clone()).ok()?;
+            return Some((name, value, constant))
+        }).collect()
+    }
+}
@@ -1,56 +1,178 @@
-use crate::values::RuntimeVal;
+use crate::values::{ErrorVal, RuntimeVal};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
-#[derive(Debug, Clone)]
+// variables live in slots numbered in declaration order, which the resolver predicts statically,
+// so resolved accesses are plain indexes; a slot holds none until its declaration is reached
+#[derive(Debug)]
 pub struct Environment {
-    parent: Option<Box<Environment>>,
-    variables: HashMap<String, RuntimeVal>,
-    constants: Vec<String>,
+    parent: Option<Rc<RefCell<Environment>>>,
+    names: Vec<String>,
+    values: Vec<Option<RuntimeVal>>,
+    constants: Vec<bool>,
+    by_name: Option<HashMap<String, usize>>, // only for globals, which the host and the REPL look up by name
 }
 impl Environment {
-    pub fn new(parent_env: Option<Box<Environment>>) -> Self {
+    pub fn new(parent_env: Option<Rc<RefCell<Environment>>>) -> Self {
         return Environment {
             parent: parent_env,
-            variables: HashMap::new(),
+            names: Vec::new(),
+            values: Vec::new(),
             constants: Vec::new(),
+            by_name: None,
         }
     }
 
-    pub fn declare_var(&mut self, varname: String, value: RuntimeVal, isconstant: bool) -> RuntimeVal {
-        if self.variables.contains_key(&varname) {
-            panic!("Cannot declare variable {}. It has alreeady been initialized.", varname)
+    // a scope that outlives single evaluations, such as an Engine's globals or its builtins
+    pub fn global(parent_env: Option<Rc<RefCell<Environment>>>) -> Self {
+        return Environment { by_name: Some(HashMap::new()), ..Environment::new(parent_env) }
+    }
+
+    pub fn parent(&self) -> Option<Rc<RefCell<Environment>>> {
+        return self.parent.clone()
+    }
+
+    pub fn set_parent(&mut self, parent_env: Option<Rc<RefCell<Environment>>>) {
+        self.parent = parent_env;
+    }
+
+    // the names declared so far with their constness, in slot order; a slot whose declaration
+    // was never reached keeps its number but can no longer be named, so it comes back as none
+    pub fn declared(&self) -> Vec<Option<(String, bool)>> {
+        return self.names.iter().zip(self.values.iter()).zip(self.constants.iter()).map(|((name, value), &constant)| {
+            return value.as_ref().map(|_| (name.clone(), constant))
+        }).collect()
+    }
+
+    // the resolver has already refused a second declaration of a name in a resolved scope, so
+    // only a scope kept by name checks again
+    pub fn declare_var(&mut self, varname: String, value: RuntimeVal, isconstant: bool) -> Result<RuntimeVal, ErrorVal> {
+        if let Some(ref mut by_name) = self.by_name {
+            if by_name.contains_key(&varname) {
+                return Err(ErrorVal::make_error(
+                    "NameError",
+                    format!("Cannot declare variable {}. It has already been initialized.", varname)
+                ))
+            }
+            by_name.insert(varname.clone(), self.values.len());
         }
-        self.variables.insert(varname.clone(), value.clone());
-        if isconstant {
-            self.constants.push(varname)
+        self.names.push(varname);
+        self.values.push(Some(value.clone()));
+        self.constants.push(isconstant);
+        return Ok(value)
+    }
+
+    // a program that stopped part way leaves the rest of its declarations unreached; reserving
+    // their slots keeps the numbers the resolver handed out valid for closures that escaped and
+    // for whatever runs in this scope next
+    pub fn reserve(&mut self, declarations: Vec<(String, bool)>) {
+        for (name, constant) in declarations.into_iter().skip(self.values.len()) {
+            self.names.push(name);
+            self.values.push(None);
+            self.constants.push(constant);
         }
-        return value
     }
-    pub fn assign_var(&mut self, varname: String, value: RuntimeVal) -> RuntimeVal {
-        let env = self.resolve(varname.clone());
-        if env.constants.contains(&varname) {
-            panic!("Cannot reasign to variable {} as it is a constant.", varname)
+
+    pub fn assign_var(&mut self, varname: String, value: RuntimeVal) -> Result<RuntimeVal, ErrorVal> {
+        match self.slot_named(&varname) {
+            Some(slot) => return self.assign_slot(slot, value),
+            None => match self.parent {
+                Some(ref parent) => return parent.borrow_mut().assign_var(varname, value),
+                None => return Err(Environment::unresolved(&varname)),
+            },
         }
-        env.variables.remove(&varname);
-        env.variables.insert(varname, value.clone());
-        return value
     }
 
-    pub fn lookup_var(&mut self, varname: String) -> RuntimeVal {
-        let env = self.resolve(varname.clone());
-        return env.variables.get(&varname).expect("literally cant be none and if this does end up being none then shist").clone()
+    // declares `varname`, or overwrites it in place so slots the resolver handed out stay valid
+    pub fn define(&mut self, varname: String, value: RuntimeVal) {
+        match self.slot_named(&varname) {
+            Some(slot) => self.values[slot] = Some(value),
+            None => {
+                self.declare_var(varname, value, false).expect("the name is not declared yet");
+            },
+        }
     }
-    pub fn resolve(&mut self, varname: String) -> &mut Environment {
-        if self.variables.contains_key(&varname) {
-            return self
+
+    pub fn lookup_var(&self, varname: String) -> Result<RuntimeVal, ErrorVal> {
+        if let Some(slot) = self.slot_named(&varname) {
+            return self.read_slot(slot, &varname)
         }
         match self.parent {
-            Some(_) => {
-                return self.parent.as_mut().expect("environment should exist")
-            },
-            None => {
-                panic!("cannot resolve '{}', as it does not exist", varname)
-            },
+            Some(ref parent) => return parent.borrow().lookup_var(varname),
+            None => return Err(Environment::unresolved(&varname)),
         }
     }
-}
\ No newline at end of file
+
+    // `varname` is only for the error message
+    pub fn assign_at(&mut self, depth: usize, slot: usize, varname: &str, value: RuntimeVal) -> Result<RuntimeVal, ErrorVal> {
+        if depth > 0 {
+            return self.ancestor(depth)?.borrow_mut().assign_at(0, slot, varname, value)
+        }
+        if !matches!(self.values.get(slot), Some(Some(_))) {
+            return Err(Environment::undeclared(varname))
+        }
+        return self.assign_slot(slot, value)
+    }
+
+    pub fn lookup_at(&self, depth: usize, slot: usize, varname: &str) -> Result<RuntimeVal, ErrorVal> {
+        if depth > 0 {
+            return self.ancestor(depth)?.borrow().lookup_at(0, slot, varname)
+        }
+        return self.read_slot(slot, varname)
+    }
+
+    // a closure can run before a variable it captured is declared, which finds its slot empty
+    fn read_slot(&self, slot: usize, varname: &str) -> Result<RuntimeVal, ErrorVal> {
+        match self.values.get(slot) {
+            Some(Some(value)) => return Ok(value.clone()),
+            _ => return Err(Environment::undeclared(varname)),
+        }
+    }
+
+    // scopes without a name map are small and short-lived, so a scan from the newest slot will do
+    fn slot_named(&self, varname: &str) -> Option<usize> {
+        match self.by_name {
+            Some(ref by_name) => return by_name.get(varname).copied(),
+            None => return self.names.iter().rposition(|name| name == varname).filter(|&slot| self.values[slot].is_some()),
+        }
+    }
+
+    fn ancestor(&self, depth: usize) -> Result<Rc<RefCell<Environment>>, ErrorVal> {
+        let mut env = self.parent.clone();
+        for _ in 1..depth {
+            env = match env {
+                Some(env) => env.borrow().parent.clone(),
+                None => None,
+            };
+        }
+        return env.ok_or_else(|| {
+            ErrorVal::make_error("InternalError", format!("A resolved scope depth of {} exceeds the environment chain", depth))
+        })
+    }
+
+    fn assign_slot(&mut self, slot: usize, value: RuntimeVal) -> Result<RuntimeVal, ErrorVal> {
+        if self.constants[slot] {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot reassign to variable {} as it is a constant.", self.names[slot])
+            ))
+        }
+        self.values[slot] = Some(value.clone());
+        return Ok(value)
+    }
+
+    fn undeclared(varname: &str) -> ErrorVal {
+        return ErrorVal::make_error(
+            "NameError",
+            format!("Cannot access '{}' before its declaration.", varname)
+        )
+    }
+
+    fn unresolved(varname: &str) -> ErrorVal {
+        return ErrorVal::make_error(
+            "NameError",
+            format!("Cannot resolve '{}', as it does not exist.", varname)
+        )
+    }
+}
@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::iterators::{make_iterator, Source};
+use crate::limits::require_io;
+use crate::lists::list_result;
+use crate::values::{ErrorVal, ModuleVal, NativeFnVal, NullVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+// paths are strings, relative to the working directory; every failure is an IOError naming the
+// path, and each call checks the I/O capability again in case the module outlived it
+pub fn fs_module() -> ModuleVal {
+    let functions: [(&str, Native); 8] = [
+        ("read_to_string", native_read_to_string),
+        ("write", native_write),
+        ("append", native_append),
+        ("exists", native_exists),
+        ("list_dir", native_list_dir),
+        ("mkdir", native_mkdir),
+        ("remove", native_remove),
+        ("lines", native_lines),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("fs"),
+        exports: Rc::new(exports),
+    }
+}
+
+pub fn io_error(action: &str, path: &str, error: io::Error) -> ErrorVal {
+    return ErrorVal::make_error("IOError", format!("Cannot {} '{}': {}", action, path, error))
+}
+
+// checks the argument count and the capability, returning the path argument
+fn target(name: &str, action: &str, arguments: &[RuntimeVal], count: usize) -> Result<String, ErrorVal> {
+    expect_args(name, arguments, count)?;
+    let path = arguments[0].get_string_value()?;
+    require_io(&format!("{} '{}'", action, path))?;
+    return Ok(path)
+}
+
+fn null() -> EvalResult {
+    return Ok(NullVal::make_null().to_runtime_val())
+}
+
+fn native_read_to_string(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.read_to_string", "read", &arguments, 1)?;
+    let contents = fs::read_to_string(&path).map_err(|error| io_error("read", &path, error))?;
+    return Ok(RuntimeVal::from(contents))
+}
+
+fn native_write(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.write", "write", &arguments, 2)?;
+    let contents = arguments[1].get_string_value()?;
+    fs::write(&path, contents).map_err(|error| io_error("write", &path, error))?;
+    return null()
+}
+
+fn native_append(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.append", "append to", &arguments, 2)?;
+    let contents = arguments[1].get_string_value()?;
+    OpenOptions::new().create(true).append(true).open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|error| io_error("append to", &path, error))?;
+    return null()
+}
+
+fn native_exists(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.exists", "check", &arguments, 1)?;
+    let exists = fs::exists(&path).map_err(|error| io_error("check", &path, error))?;
+    return Ok(RuntimeVal::from(exists))
+}
+
+// entry names only, sorted so scripts behave the same on every platform
+fn native_list_dir(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.list_dir", "list", &arguments, 1)?;
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&path).map_err(|error| io_error("list", &path, error))? {
+        let entry = entry.map_err(|error| io_error("list", &path, error))?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    return list_result(names.into_iter().map(RuntimeVal::from).collect())
+}
+
+// creates missing parents too, and succeeds when the directory is already there
+fn native_mkdir(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.mkdir", "create", &arguments, 1)?;
+    fs::create_dir_all(&path).map_err(|error| io_error("create", &path, error))?;
+    return null()
+}
+
+// removes a file or an empty directory; anything bigger has to be emptied first
+fn native_remove(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.remove", "remove", &arguments, 1)?;
+    let removed = match fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir(&path),
+        _ => fs::remove_file(&path),
+    };
+    removed.map_err(|error| io_error("remove", &path, error))?;
+    return null()
+}
+
+// an iterator that reads one line at a time, without the line ending
+fn native_lines(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = target("fs.lines", "read", &arguments, 1)?;
+    let file = File::open(&path).map_err(|error| io_error("read", &path, error))?;
+    let lines = BufReader::new(file).lines();
+    return Ok(make_iterator(String::from("iterator"), Source::Lines { path, lines })?.to_runtime_val())
+}
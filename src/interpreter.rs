@@ -1,101 +1,839 @@
-use crate::values::{NullVal, NumberVal, RuntimeVal, ValueType, Value};
-use crate::ast::{AssignmentExpr, BinaryExpr, Expr, Identifier, Program, Stmt, VarDeclaration};
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::values::{BooleanVal, ClassVal, EnumVal, ErrorVal, FloatVal, FunctionBody, FunctionVal, InstanceVal, ListVal, NullVal, NumberVal, RuntimeVal, StringVal, ValueType, Value, VariantCtorVal, VariantVal};
+use crate::ast::{AssignmentExpr, BinaryExpr, BinaryOperator, CallExpr, ClassDeclaration, EnumDeclaration, Expr, ForStmt, FunctionDeclaration, FunctionExpr, Identifier, ImportDeclaration, IndexExpr, ListExpr, MatchExpr, MemberExpr, NewExpr, Pattern, Program, PropagateExpr, ReturnStmt, Stmt, ThrowStmt, TryStmt, VarDeclaration};
+use crate::builtins::call_result_method;
 use crate::environment::Environment;
+use crate::limits;
+use crate::compiler::compile_function_body;
+use crate::iterators::{call_iterator_method, iterate, make_generator, next_item};
+use crate::lists::call_list_method;
+use crate::modules::load_module;
+use crate::process::{capture, pipe};
+use crate::strings::call_string_method;
+use crate::time::{call_time_method, time_arithmetic};
+use crate::vm::call_compiled;
 
-pub fn eval_binary_expr(binop: BinaryExpr, env: &mut Environment) -> RuntimeVal {
-    let lhs = eval_expr(binop.left, env);
-    let rhs = eval_expr(binop.right, env);
-    if lhs.get_value_type() == ValueType::Number && rhs.get_value_type() == ValueType::Number {
-        return eval_numeric_binary_expr(lhs.to_number_val(), rhs.to_number_val(), binop.operator).to_runtime_val()
+#[derive(Debug, Clone)]
+pub enum Interrupt {
+    Throw(ErrorVal),
+    Return(RuntimeVal),
+    Exit(i32), // exit() was called; nothing catches it, not even finally
+}
+
+impl From<ErrorVal> for Interrupt {
+    fn from(error: ErrorVal) -> Self {
+        return Interrupt::Throw(with_stack(error))
+    }
+}
+
+pub type EvalResult = Result<RuntimeVal, Interrupt>;
+
+thread_local! {
+    static CALL_STACK: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+// refuses the call when it would go deeper than the limits allow
+pub fn push_frame(name: String) -> Result<(), ErrorVal> {
+    let depth = CALL_STACK.with(|stack| stack.borrow().len()) + 1;
+    limits::enter_call(depth)?;
+    CALL_STACK.with(|stack| stack.borrow_mut().push(name));
+    return Ok(())
+}
+
+pub fn pop_frame() {
+    CALL_STACK.with(|stack| stack.borrow_mut().pop());
+}
+
+fn with_stack(mut error: ErrorVal) -> ErrorVal {
+    if error.stack.is_empty() {
+        error.stack = CALL_STACK.with(|stack| stack.borrow().iter().rev().cloned().collect());
+    }
+    return error
+}
+
+pub fn eval_binary_expr(binop: &BinaryExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let lhs = eval_expr(&binop.left, env)?;
+    let rhs = eval_expr(&binop.right, env)?;
+    return apply_binary_operator(lhs, rhs, binop.operator)
+}
+
+pub fn apply_binary_operator(lhs: RuntimeVal, rhs: RuntimeVal, operator: BinaryOperator) -> EvalResult {
+    if operator == BinaryOperator::InstanceOf {
+        return eval_instanceof(lhs, rhs)
+    } else if operator == BinaryOperator::Equal {
+        return Ok(BooleanVal::make_bool(values_equal(&lhs, &rhs)).to_runtime_val())
+    } else if operator == BinaryOperator::NotEqual {
+        return Ok(BooleanVal::make_bool(!values_equal(&lhs, &rhs)).to_runtime_val())
+    } else if operator == BinaryOperator::Pipe {
+        return pipe(lhs, rhs)
+    }
+    match (lhs.get_value_type(), rhs.get_value_type()) {
+        (ValueType::Number, ValueType::Number) => {
+            return Ok(eval_numeric_binary_expr(lhs.to_number_val()?, rhs.to_number_val()?, operator)?.to_runtime_val())
+        },
+        // an integer meeting a float is widened, so 1 + 0.5 is 1.5
+        (ValueType::Number | ValueType::Float, ValueType::Number | ValueType::Float) => {
+            return Ok(eval_float_binary_expr(lhs.get_float_value()?, rhs.get_float_value()?, operator)?.to_runtime_val())
+        },
+        (ValueType::Duration | ValueType::DateTime | ValueType::Instant, _) | (_, ValueType::Duration) => {
+            return time_arithmetic(lhs, rhs, operator)
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Unsupported operand types for {}: {:?} and {:?}", operator, lhs.get_value_type(), rhs.get_value_type())
+            ).into())
+        },
+    }
+}
+
+// integers and floats compare by value, everything else structurally
+pub fn values_equal(lhs: &RuntimeVal, rhs: &RuntimeVal) -> bool {
+    match (lhs, rhs) {
+        (RuntimeVal::NumberVal(int), RuntimeVal::FloatVal(float)) | (RuntimeVal::FloatVal(float), RuntimeVal::NumberVal(int)) => {
+            return int.value as f64 == float.value
+        },
+        _ => return lhs == rhs,
+    }
+}
+
+fn eval_instanceof(lhs: RuntimeVal, rhs: RuntimeVal) -> EvalResult {
+    match (lhs, rhs) {
+        (RuntimeVal::InstanceVal(instance), RuntimeVal::ClassVal(class)) => {
+            return Ok(BooleanVal::make_bool(instance.class.is_subclass_of(&class)).to_runtime_val())
+        },
+        (_, RuntimeVal::ClassVal(_)) => return Ok(BooleanVal::make_bool(false).to_runtime_val()),
+        (_, rhs) => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Right-hand side of instanceof must be a Class, found {:?}", rhs.get_value_type())
+            ).into())
+        },
+    }
+}
+
+fn eval_numeric_binary_expr(lhs: NumberVal, rhs: NumberVal, operator: BinaryOperator) -> Result<NumberVal, ErrorVal> {
+    if (operator == BinaryOperator::Divide || operator == BinaryOperator::Remainder) && rhs.value == 0 {
+        return Err(ErrorVal::make_error("ZeroDivisionError", String::from("Division by zero.")))
+    }
+    let result = if operator == BinaryOperator::Add {
+        lhs.value.checked_add(rhs.value)
+    } else if operator == BinaryOperator::Subtract {
+        lhs.value.checked_sub(rhs.value)
+    } else if operator == BinaryOperator::Multiply {
+        lhs.value.checked_mul(rhs.value)
+    } else if operator == BinaryOperator::Divide {
+        lhs.value.checked_div(rhs.value)
+    } else if operator == BinaryOperator::Remainder {
+        lhs.value.checked_rem(rhs.value)
     } else {
-        return RuntimeVal::NullVal(NullVal {
-            value_type: ValueType::Null,
-            value: Value::Null
-        })
+        Some(0)
+    };
+    match result {
+        Some(value) => return Ok(NumberVal {
+            value_type: ValueType::Number,
+            value,
+        }),
+        None => return Err(ErrorVal::make_error(
+            "OverflowError",
+            format!("Integer overflow in {} {} {}", lhs.value, operator, rhs.value)
+        )),
+    }
+}
+
+// dividing by zero is an error as it is for integers; everything else follows IEEE 754
+fn eval_float_binary_expr(lhs: f64, rhs: f64, operator: BinaryOperator) -> Result<FloatVal, ErrorVal> {
+    if (operator == BinaryOperator::Divide || operator == BinaryOperator::Remainder) && rhs == 0.0 {
+        return Err(ErrorVal::make_error("ZeroDivisionError", String::from("Division by zero.")))
+    }
+    let value = if operator == BinaryOperator::Add {
+        lhs + rhs
+    } else if operator == BinaryOperator::Subtract {
+        lhs - rhs
+    } else if operator == BinaryOperator::Multiply {
+        lhs * rhs
+    } else if operator == BinaryOperator::Divide {
+        lhs / rhs
+    } else if operator == BinaryOperator::Remainder {
+        lhs % rhs
+    } else {
+        0.0
+    };
+    return Ok(FloatVal::make_float(value))
+}
+fn eval_identifier(ident: &Identifier, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let val = match ident.slot {
+        Some((depth, slot)) => env.borrow().lookup_at(depth, slot, &ident.symbol)?,
+        None => env.borrow().lookup_var(ident.symbol.clone())?,
+    };
+    return Ok(val)
+}
+
+pub fn get_property(object: RuntimeVal, property: &str) -> EvalResult {
+    if let RuntimeVal::InstanceVal(instance) = &object {
+        if let Some(value) = instance.fields.borrow().get(property) {
+            return Ok(value.clone())
+        }
+        if let Some((method, owner)) = instance.class.find_method(property) {
+            return Ok(bind_method(method, object.clone(), &owner)?.to_runtime_val())
+        }
+    }
+    if let RuntimeVal::ClassVal(class) = &object {
+        if let Some((method, owner)) = class.find_static_method(property) {
+            return Ok(bind_method(method, object.clone(), &owner)?.to_runtime_val())
+        }
+    }
+    if let RuntimeVal::EnumVal(enum_val) = &object {
+        if let Some((variant, field_names)) = enum_val.variants.iter().find(|(name, _)| name == property) {
+            if field_names.is_empty() {
+                return Ok(VariantVal {
+                    value_type: ValueType::Variant,
+                    enum_name: enum_val.name.clone(),
+                    variant: variant.clone(),
+                    field_names: Vec::new(),
+                    fields: Vec::new(),
+                }.to_runtime_val())
+            }
+            return Ok(VariantCtorVal {
+                value_type: ValueType::Function,
+                enum_name: enum_val.name.clone(),
+                variant: variant.clone(),
+                field_names: field_names.clone(),
+            }.to_runtime_val())
+        }
+    }
+    if let RuntimeVal::ModuleVal(module) = &object {
+        if let Some(value) = module.exports.get(property) {
+            return Ok(value.clone())
+        }
+        return Err(ErrorVal::make_error(
+            "ImportError",
+            format!("Module '{}' has no export named '{}'", module.path, property)
+        ).into())
+    }
+    if let RuntimeVal::VariantVal(variant) = &object {
+        if let Some(index) = variant.field_names.iter().position(|name| name == property) {
+            return Ok(variant.fields[index].clone())
+        }
+    }
+    if let RuntimeVal::ErrorVal(error) = &object {
+        if property == "kind" {
+            return Ok(StringVal::make_string(error.kind.clone()).to_runtime_val())
+        } else if property == "message" {
+            return Ok(StringVal::make_string(error.message.clone()).to_runtime_val())
+        } else if property == "stack" {
+            let frames: Vec<String> = error.stack.iter().map(|frame| format!("at {}", frame)).collect();
+            return Ok(StringVal::make_string(frames.join("\n")).to_runtime_val())
+        }
+    }
+    return Err(ErrorVal::make_error(
+        "TypeError",
+        format!("Cannot read property '{}' of {:?}", property, object.get_value_type())
+    ).into())
+}
+
+pub fn eval_member_expr(member: &MemberExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let object = eval_expr(&member.object, env)?;
+    return get_property(object, &member.property)
+}
+
+fn eval_index_expr(index_expr: &IndexExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let object = eval_expr(&index_expr.object, env)?;
+    let index = eval_expr(&index_expr.index, env)?;
+    return get_index(object, index)
+}
+
+fn eval_list_expr(list_expr: &ListExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let mut items = Vec::new();
+    for element in &list_expr.elements {
+        items.push(eval_expr(element, env)?);
+    }
+    limits::allocate_list(items.len())?;
+    return Ok(ListVal::make_list(items).to_runtime_val())
+}
+
+// positions count from 0 and must fall inside the value; negative ones are never valid
+pub fn checked_position(index: i64, length: usize) -> Result<usize, ErrorVal> {
+    match usize::try_from(index) {
+        Ok(position) if position < length => return Ok(position),
+        _ => return Err(ErrorVal::make_error("IndexError", format!("Index {} is out of range for length {}", index, length))),
     }
 }
 
-fn eval_numeric_binary_expr(lhs: NumberVal, rhs: NumberVal, operator: String) -> NumberVal {
-    let mut result = 0;
-    if operator == "+" {
-        result = lhs.value + rhs.value
-    } else if operator == "-" {
-        result = lhs.value - rhs.value
-    } else if operator == "*" {
-        result = lhs.value * rhs.value
-    } else if operator == "/" { //TODO check for divide by zero
-        result = lhs.value / rhs.value
-    } else if operator == "%" {
-        result = lhs.value % rhs.value
+// strings index by Unicode scalar value, so "héllo"[1] is "é" whatever its UTF-8 width
+// objects can also be indexed by field name, for keys that are not identifiers
+pub fn get_index(object: RuntimeVal, index: RuntimeVal) -> EvalResult {
+    if let (RuntimeVal::InstanceVal(_), RuntimeVal::StringVal(key)) = (&object, &index) {
+        return get_property(object.clone(), &key.value)
     }
-    return NumberVal {
-        value_type: ValueType::Number,
-        value: result
+    let index = index.get_number_value()?;
+    match object {
+        RuntimeVal::ListVal(list) => {
+            let items = list.items.borrow();
+            return Ok(items[checked_position(index, items.len())?].clone())
+        },
+        RuntimeVal::StringVal(string) => {
+            let position = checked_position(index, string.value.chars().count())?;
+            let character = string.value.chars().nth(position).unwrap();
+            return Ok(StringVal::make_string(character.to_string()).to_runtime_val())
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot index a value of type {:?}", object.get_value_type())
+            ).into())
+        },
     }
 }
-fn eval_identifier(ident: Identifier, env: &mut Environment) -> RuntimeVal {
-    let val = env.lookup_var(ident.symbol);
-    return val
+
+pub fn set_index(object: RuntimeVal, index: RuntimeVal, value: RuntimeVal) -> EvalResult {
+    if let (RuntimeVal::InstanceVal(_), RuntimeVal::StringVal(key)) = (&object, &index) {
+        return set_property(object.clone(), key.value.clone(), value)
+    }
+    let index = index.get_number_value()?;
+    match object {
+        RuntimeVal::ListVal(list) => {
+            let mut items = list.items.borrow_mut();
+            let position = checked_position(index, items.len())?;
+            items[position] = value.clone();
+            return Ok(value)
+        },
+        RuntimeVal::StringVal(_) => {
+            return Err(ErrorVal::make_error("TypeError", String::from("Strings are immutable, so their characters cannot be assigned")).into())
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot index a value of type {:?}", object.get_value_type())
+            ).into())
+        },
+    }
 }
 
-fn eval_program(program: Program, env: &mut Environment) -> RuntimeVal {
+// closes `method` over `this` and, when the defining class has one, its superclass as `super`
+fn bind_method(method: FunctionVal, this: RuntimeVal, owner: &ClassVal) -> Result<FunctionVal, ErrorVal> {
+    let mut scope = Environment::new(Some(Rc::clone(&method.declaration_env)));
+    scope.declare_var(String::from("this"), this, true)?;
+    if let Some(ref superclass) = owner.superclass {
+        scope.declare_var(String::from("super"), superclass.as_ref().clone().to_runtime_val(), true)?;
+    }
+    return Ok(FunctionVal {
+        name: format!("{}.{}", owner.name, method.name),
+        declaration_env: Rc::new(RefCell::new(scope)),
+        ..method
+    })
+}
+
+pub fn eval_super_call(method: Option<String>, arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let superclass = match env.borrow().lookup_var(String::from("super"))? {
+        RuntimeVal::ClassVal(class) => class,
+        _ => return Err(ErrorVal::make_error("TypeError", String::from("'super' is not a class.")).into()),
+    };
+    let this = env.borrow().lookup_var(String::from("this"))?;
+    let found = match (&method, &this) {
+        (None, _) => superclass.find_method("constructor"),
+        (Some(name), RuntimeVal::ClassVal(_)) => superclass.find_static_method(name),
+        (Some(name), _) => superclass.find_method(name),
+    };
+    match found {
+        Some((function, owner)) => {
+            return call_function(bind_method(function, this, &owner)?.to_runtime_val(), arguments, env)
+        },
+        None if method.is_none() && arguments.is_empty() => return Ok(NullVal::make_null().to_runtime_val()),
+        None => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("{} has no method '{}'", superclass.name, method.unwrap_or(String::from("constructor")))
+            ).into())
+        },
+    }
+}
+
+fn eval_call_expr(call: &CallExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let mut arguments = Vec::new();
+    if let Expr::Identifier(ref identifier) = call.callee {
+        if identifier.symbol == "super" {
+            for argument in &call.arguments {
+                arguments.push(eval_expr(argument, env)?);
+            }
+            return eval_super_call(None, arguments, env)
+        }
+    }
+    if let Expr::MemberExpr(ref member) = call.callee {
+        if let Expr::Identifier(ref identifier) = member.object {
+            if identifier.symbol == "super" {
+                for argument in &call.arguments {
+                    arguments.push(eval_expr(argument, env)?);
+                }
+                return eval_super_call(Some(member.property.clone()), arguments, env)
+            }
+        }
+        let object = eval_expr(&member.object, env)?;
+        for argument in &call.arguments {
+            arguments.push(eval_expr(argument, env)?);
+        }
+        return call_method(object, &member.property, arguments, env)
+    }
+    let callee = eval_expr(&call.callee, env)?;
+    for argument in &call.arguments {
+        arguments.push(eval_expr(argument, env)?);
+    }
+    return call_function(callee, arguments, env)
+}
+
+pub fn call_method(object: RuntimeVal, property: &str, arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    if let RuntimeVal::ResultVal(result) = object {
+        return call_result_method(result, property, arguments, env)
+    }
+    if let RuntimeVal::StringVal(string) = object {
+        return call_string_method(string.value, property, arguments)
+    }
+    if let RuntimeVal::ListVal(list) = object {
+        return call_list_method(list, property, arguments, env)
+    }
+    if let RuntimeVal::IteratorVal(iterator) = object {
+        return call_iterator_method(iterator, property, arguments, env)
+    }
+    if let RuntimeVal::DurationVal(_) | RuntimeVal::DateTimeVal(_) | RuntimeVal::InstantVal(_) = object {
+        return call_time_method(object, property, arguments)
+    }
+    return call_function(get_property(object, property)?, arguments, env)
+}
+
+// checks arity and declares the parameters in a fresh scope under the function's closure
+pub fn bind_arguments(function: &FunctionVal, arguments: Vec<RuntimeVal>) -> Result<Rc<RefCell<Environment>>, ErrorVal> {
+    if arguments.len() != function.parameters.len() {
+        return Err(ErrorVal::make_error(
+            "TypeError",
+            format!("{} expects {} argument(s) but received {}", function.name, function.parameters.len(), arguments.len())
+        ))
+    }
+    let scope = Rc::new(RefCell::new(Environment::new(Some(Rc::clone(&function.declaration_env)))));
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        scope.borrow_mut().declare_var(parameter.clone(), argument, false)?;
+    }
+    return Ok(scope)
+}
+
+pub fn call_function(callee: RuntimeVal, arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    match callee {
+        RuntimeVal::NativeFnVal(native_fn) => {
+            return (native_fn.call)(arguments, env)
+        },
+        RuntimeVal::FunctionVal(function) => {
+            let scope = bind_arguments(&function, arguments)?;
+            let body = match function.body {
+                FunctionBody::Ast(body) => body,
+                FunctionBody::Bytecode(chunk) => return call_compiled(function.name, chunk, scope),
+                FunctionBody::Generator(chunk) => return make_generator(function.name, chunk, scope),
+            };
+            push_frame(function.name)?;
+            let result = limits::grow_stack(|| eval_body(&body, &scope));
+            pop_frame();
+            match result {
+                Err(Interrupt::Return(value)) => return Ok(value),
+                _ => return result,
+            }
+        },
+        RuntimeVal::VariantCtorVal(constructor) => {
+            if arguments.len() != constructor.field_names.len() {
+                return Err(ErrorVal::make_error(
+                    "TypeError",
+                    format!("{}.{} expects {} argument(s) but received {}", constructor.enum_name, constructor.variant, constructor.field_names.len(), arguments.len())
+                ).into())
+            }
+            limits::allocate()?;
+            return Ok(VariantVal {
+                value_type: ValueType::Variant,
+                enum_name: constructor.enum_name,
+                variant: constructor.variant,
+                field_names: constructor.field_names,
+                fields: arguments,
+            }.to_runtime_val())
+        },
+        RuntimeVal::ClassVal(class) => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Class {} cannot be invoked without 'new'", class.name)
+            ).into())
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot call a value of type {:?}", callee.get_value_type())
+            ).into())
+        },
+    }
+}
+
+fn eval_new_expr(new_expr: &NewExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let callee = eval_expr(&new_expr.callee, env)?;
+    let mut arguments = Vec::new();
+    for argument in &new_expr.arguments {
+        arguments.push(eval_expr(argument, env)?);
+    }
+    return construct(callee, arguments, env)
+}
+
+pub fn construct(callee: RuntimeVal, arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let class = match callee {
+        RuntimeVal::ClassVal(class) => class,
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot instantiate a value of type {:?}", callee.get_value_type())
+            ).into())
+        },
+    };
+    limits::allocate()?;
+    let instance = InstanceVal::make_instance(class.clone()).to_runtime_val();
+    match class.find_method("constructor") {
+        Some((constructor, owner)) => {
+            call_function(bind_method(constructor, instance.clone(), &owner)?.to_runtime_val(), arguments, env)?;
+        },
+        None => {
+            if !arguments.is_empty() {
+                return Err(ErrorVal::make_error(
+                    "TypeError",
+                    format!("{} has no constructor but received {} argument(s)", class.name, arguments.len())
+                ).into())
+            }
+        },
+    }
+    return Ok(instance)
+}
+
+fn eval_propagate_expr(propagate: &PropagateExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = eval_expr(&propagate.argument, env)?;
+    match value {
+        RuntimeVal::ResultVal(result) => {
+            if result.is_ok {
+                return Ok(*result.value)
+            }
+            return Err(Interrupt::Return(result.to_runtime_val()))
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("The '?' operator expects a Result, found {:?}", value.get_value_type())
+            ).into())
+        },
+    }
+}
+
+pub fn match_pattern(pattern: &Pattern, value: &RuntimeVal, bindings: &mut Vec<(String, RuntimeVal)>, env: &Rc<RefCell<Environment>>) -> Result<bool, Interrupt> {
+    match pattern {
+        Pattern::Wildcard => return Ok(true),
+        Pattern::Binding(name) => {
+            bindings.push((name.clone(), value.clone()));
+            return Ok(true)
+        },
+        Pattern::Literal(literal) => {
+            return Ok(values_equal(&eval_expr(literal, env)?, value))
+        },
+        Pattern::Variant { enum_name, variant, fields } => {
+            let variant_val = match value {
+                RuntimeVal::VariantVal(variant_val) => variant_val,
+                _ => return Ok(false),
+            };
+            if variant_val.enum_name != *enum_name || variant_val.variant != *variant {
+                return Ok(false)
+            }
+            if fields.is_empty() {
+                return Ok(true)
+            }
+            if fields.len() != variant_val.fields.len() {
+                return Err(ErrorVal::make_error(
+                    "TypeError",
+                    format!("Pattern for {}.{} expects {} field(s) but the variant has {}", enum_name, variant, fields.len(), variant_val.fields.len())
+                ).into())
+            }
+            for (field_pattern, field) in fields.iter().zip(variant_val.fields.iter()) {
+                if !match_pattern(field_pattern, field, bindings, env)? {
+                    return Ok(false)
+                }
+            }
+            return Ok(true)
+        },
+    }
+}
+
+fn eval_match_expr(match_expr: &MatchExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let subject = eval_expr(&match_expr.subject, env)?;
+    for arm in &match_expr.arms {
+        let mut bindings = Vec::new();
+        if match_pattern(&arm.pattern, &subject, &mut bindings, env)? {
+            return eval_scoped_body(&arm.body, env, bindings)
+        }
+    }
+    return Err(ErrorVal::make_error(
+        "MatchError",
+        format!("No match arm matched a value of type {:?}", subject.get_value_type())
+    ).into())
+}
+
+fn eval_program(program: &Program, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    push_frame(String::from("<program>"))?;
+    let result = eval_body(&program.body, env);
+    pop_frame();
+    match result {
+        Err(Interrupt::Return(value)) => return Ok(value),
+        _ => return result,
+    }
+}
+
+fn eval_body(body: &[Stmt], env: &Rc<RefCell<Environment>>) -> EvalResult {
     let mut last_evaluated: RuntimeVal = RuntimeVal::NullVal(NullVal {
         value_type: ValueType::Null,
         value: Value::Null
     });
-    for statement in program.body {
-        last_evaluated = evaluate(statement, env);
+    for statement in body {
+        last_evaluated = evaluate(statement, env)?;
     }
-    return  last_evaluated
+    return Ok(last_evaluated)
 }
 
-fn eval_expr(ast_node: Expr, env: &mut Environment) -> RuntimeVal {
+fn eval_scoped_body(body: &[Stmt], env: &Rc<RefCell<Environment>>, bindings: Vec<(String, RuntimeVal)>) -> EvalResult {
+    let scope = Rc::new(RefCell::new(Environment::new(Some(Rc::clone(env)))));
+    for (name, value) in bindings {
+        scope.borrow_mut().declare_var(name, value, false)?;
+    }
+    return eval_body(body, &scope)
+}
+
+// the parser bounds how deeply the tree nests, but not how much stack each level takes, so
+// both walks grow the stack as they go
+pub fn eval_expr(ast_node: &Expr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    limits::tick()?;
+    return limits::grow_stack(|| eval_expr_node(ast_node, env))
+}
+
+fn eval_expr_node(ast_node: &Expr, env: &Rc<RefCell<Environment>>) -> EvalResult {
     if let Expr::NumericLiteral(value) = ast_node {
-        return RuntimeVal::NumberVal(NumberVal {
+        return Ok(RuntimeVal::NumberVal(NumberVal {
             value_type: ValueType::Number,
             value: value.value,
-        }) 
+        }))
+    } else if let Expr::FloatLiteral(literal) = ast_node {
+        return Ok(FloatVal::make_float(literal.value).to_runtime_val())
     } else if let Expr::NullLiteral(_null_literal) = ast_node {
-        return RuntimeVal::NullVal(NullVal {
+        return Ok(RuntimeVal::NullVal(NullVal {
             value_type: ValueType::Null,
             value: Value::Null
-        })
+        }))
+    } else if let Expr::StringLiteral(string_literal) = ast_node {
+        return Ok(StringVal::make_string(string_literal.value.clone()).to_runtime_val())
     } else if let Expr::BinaryExpr(binary_expr) = ast_node {
-        return eval_binary_expr(*binary_expr, env)
+        return eval_binary_expr(binary_expr, env)
     } else if let Expr::Identifier(identifier) = ast_node {
         return eval_identifier(identifier, env)
     } else if let Expr::AssignmentExpr(assignment_expr) = ast_node {
-        return eval_assignment(*assignment_expr, env)
+        return eval_assignment(assignment_expr, env)
+    } else if let Expr::MemberExpr(member_expr) = ast_node {
+        return eval_member_expr(member_expr, env)
+    } else if let Expr::IndexExpr(index_expr) = ast_node {
+        return eval_index_expr(index_expr, env)
+    } else if let Expr::ListExpr(list_expr) = ast_node {
+        return eval_list_expr(list_expr, env)
+    } else if let Expr::CallExpr(call_expr) = ast_node {
+        return eval_call_expr(call_expr, env)
+    } else if let Expr::NewExpr(new_expr) = ast_node {
+        return eval_new_expr(new_expr, env)
+    } else if let Expr::FunctionExpr(function_expr) = ast_node {
+        return eval_function_expr(function_expr, env)
+    } else if let Expr::PropagateExpr(propagate_expr) = ast_node {
+        return eval_propagate_expr(propagate_expr, env)
+    } else if let Expr::MatchExpr(match_expr) = ast_node {
+        return eval_match_expr(match_expr, env)
+    } else if let Expr::CaptureExpr(capture_expr) = ast_node {
+        return capture(&capture_expr.command)
     } else {
         panic!("this ast node has not been implemented yet:\n{:#?}", ast_node)
     }
 }
 
-fn eval_var_declaration(declaration: VarDeclaration, env: &mut Environment) -> RuntimeVal {
+fn eval_var_declaration(declaration: &VarDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
     match declaration.value {
-        Some(_) => {
-            return env.declare_var(declaration.identifier, declaration.value.expect("huh").expr_to_runtime_val(&mut env.clone()), false)
+        Some(ref value) => {
+            let value = eval_expr(value, env)?;
+            return Ok(env.borrow_mut().declare_var(declaration.identifier.clone(), value, false)?)
         },
-        None => return env.declare_var(declaration.identifier, RuntimeVal::NullVal(NullVal {
+        None => return Ok(env.borrow_mut().declare_var(declaration.identifier.clone(), RuntimeVal::NullVal(NullVal {
             value_type: ValueType::Null,
             value: Value::Null,
-        }), false),
+        }), false)?),
     }
 }
-fn eval_const_declaration(declaration: VarDeclaration, env: &mut Environment) -> RuntimeVal {
+fn eval_const_declaration(declaration: &VarDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
     match declaration.value {
-        Some(_) => {
-            return env.declare_var(declaration.identifier, declaration.value.expect("huh").expr_to_runtime_val(&mut env.clone()), true)
+        Some(ref value) => {
+            let value = eval_expr(value, env)?;
+            return Ok(env.borrow_mut().declare_var(declaration.identifier.clone(), value, true)?)
         },
-        None => return env.declare_var(declaration.identifier, RuntimeVal::NullVal(NullVal {
+        None => return Ok(env.borrow_mut().declare_var(declaration.identifier.clone(), RuntimeVal::NullVal(NullVal {
             value_type: ValueType::Null,
             value: Value::Null,
-        }), true),
+        }), true)?),
+    }
+}
+
+// a generator has to stop halfway through its body and carry on later, which this recursive
+// walker cannot do, so generator bodies are compiled and run on the VM instead
+fn function_body(body: &[Stmt], generator: bool) -> FunctionBody {
+    if generator {
+        return FunctionBody::Generator(Rc::new(compile_function_body(body.to_vec())))
+    }
+    return FunctionBody::Ast(Rc::new(body.to_vec()))
+}
+
+fn make_function(declaration: &FunctionDeclaration, env: &Rc<RefCell<Environment>>) -> FunctionVal {
+    return FunctionVal {
+        value_type: ValueType::Function,
+        name: declaration.name.clone(),
+        parameters: declaration.parameters.clone(),
+        body: function_body(&declaration.body, declaration.generator),
+        declaration_env: Rc::clone(env),
+    }
+}
+
+fn eval_fn_declaration(declaration: &FunctionDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let name = declaration.name.clone();
+    limits::allocate()?;
+    let function = make_function(declaration, env);
+    return Ok(env.borrow_mut().declare_var(name, function.to_runtime_val(), false)?)
+}
+
+fn eval_class_declaration(declaration: &ClassDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let methods = declaration.methods.iter().map(|method| make_function(method, env)).collect();
+    let static_methods = declaration.static_methods.iter().map(|method| make_function(method, env)).collect();
+    let class = make_class(declaration.name.clone(), declaration.superclass.clone(), methods, static_methods, env)?;
+    return Ok(env.borrow_mut().declare_var(declaration.name.clone(), class.to_runtime_val(), false)?)
+}
+
+pub fn make_class(name: String, superclass: Option<String>, methods: Vec<FunctionVal>, static_methods: Vec<FunctionVal>, env: &Rc<RefCell<Environment>>) -> Result<ClassVal, Interrupt> {
+    let superclass = match superclass {
+        Some(superclass_name) => {
+            match env.borrow().lookup_var(superclass_name.clone())? {
+                RuntimeVal::ClassVal(class) => Some(Box::new(class)),
+                _ => {
+                    return Err(ErrorVal::make_error(
+                        "TypeError",
+                        format!("Class {} cannot extend {} as it is not a class.", name, superclass_name)
+                    ).into())
+                },
+            }
+        },
+        None => None,
+    };
+    return Ok(ClassVal {
+        value_type: ValueType::Class,
+        name,
+        superclass,
+        methods: Rc::new(methods.into_iter().map(|method| (method.name.clone(), method)).collect()),
+        static_methods: Rc::new(static_methods.into_iter().map(|method| (method.name.clone(), method)).collect()),
+    })
+}
+
+fn eval_function_expr(function_expr: &FunctionExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    limits::allocate()?;
+    return Ok(FunctionVal {
+        value_type: ValueType::Function,
+        name: String::from("<anonymous>"),
+        parameters: function_expr.parameters.clone(),
+        body: function_body(&function_expr.body, function_expr.generator),
+        declaration_env: Rc::clone(env),
+    }.to_runtime_val())
+}
+
+fn eval_enum_declaration(declaration: &EnumDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let enum_val = EnumVal {
+        value_type: ValueType::Enum,
+        name: declaration.name.clone(),
+        variants: declaration.variants.iter().map(|variant| (variant.name.clone(), variant.fields.clone())).collect(),
+    };
+    return Ok(env.borrow_mut().declare_var(declaration.name.clone(), enum_val.to_runtime_val(), true)?)
+}
+
+pub fn eval_import_declaration(declaration: &ImportDeclaration, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let module = load_module(&declaration.source)?;
+    for (imported, local) in &declaration.specifiers {
+        let value = match module.exports.get(imported) {
+            Some(value) => value.clone(),
+            None => {
+                return Err(ErrorVal::make_error(
+                    "ImportError",
+                    format!("Module '{}' has no export named '{}'", module.path, imported)
+                ).into())
+            },
+        };
+        env.borrow_mut().declare_var(local.clone(), value, true)?;
+    }
+    if let Some(ref namespace) = declaration.namespace {
+        env.borrow_mut().declare_var(namespace.clone(), module.clone().to_runtime_val(), true)?;
     }
+    return Ok(module.to_runtime_val())
+}
+
+fn eval_return_stmt(stmt: &ReturnStmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = match stmt.argument {
+        Some(ref argument) => eval_expr(argument, env)?,
+        None => NullVal::make_null().to_runtime_val(),
+    };
+    return Err(Interrupt::Return(value))
+}
+
+fn eval_throw_stmt(stmt: &ThrowStmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = eval_expr(&stmt.argument, env)?;
+    return Err(throw_value(value))
 }
 
+pub fn throw_value(value: RuntimeVal) -> Interrupt {
+    match value {
+        RuntimeVal::ErrorVal(error) => return error.into(),
+        RuntimeVal::StringVal(string_val) => return ErrorVal::make_error("Error", string_val.value).into(),
+        _ => return ErrorVal::make_error(
+            "TypeError",
+            format!("Can only throw a String or an Error, found {:?}", value.get_value_type())
+        ).into(),
+    }
+}
+
+fn eval_try_stmt(stmt: &TryStmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let mut result = eval_scoped_body(&stmt.block, env, Vec::new());
+    if let Some(ref handler) = stmt.handler {
+        if let Err(Interrupt::Throw(error)) = result {
+            let bindings = match stmt.param {
+                Some(ref param) => vec![(param.clone(), error.to_runtime_val())],
+                None => Vec::new(),
+            };
+            result = eval_scoped_body(handler, env, bindings);
+        }
+    }
+    if let Some(ref finalizer) = stmt.finalizer {
+        if let Err(Interrupt::Exit(_)) = result {
+            return result
+        }
+        eval_scoped_body(finalizer, env, Vec::new())?;
+    }
+    return result
+}
 
-pub fn evaluate(ast_node: Stmt, env: &mut Environment) -> RuntimeVal {
+// each pass binds the item in a fresh scope, so closures made in the body keep their own item
+fn eval_for_stmt(stmt: &ForStmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let iterator = iterate(eval_expr(&stmt.iterable, env)?)?;
+    while let Some(item) = next_item(&iterator, env)? {
+        eval_scoped_body(&stmt.body, env, vec![(stmt.variable.clone(), item)])?;
+    }
+    return Ok(NullVal::make_null().to_runtime_val())
+}
+
+pub fn evaluate(ast_node: &Stmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    limits::tick()?;
+    return limits::grow_stack(|| evaluate_node(ast_node, env))
+}
+
+fn evaluate_node(ast_node: &Stmt, env: &Rc<RefCell<Environment>>) -> EvalResult {
     match ast_node {
         Stmt::Program(program) => return eval_program(program, env),
         Stmt::VarDeclaration(var_declaration) => {
@@ -104,19 +842,57 @@ pub fn evaluate(ast_node: Stmt, env: &mut Environment) -> RuntimeVal {
                 false => return eval_var_declaration(var_declaration, env),
             }
         },
-        Stmt::Expr(_) => return eval_expr(ast_node.get_stmt_expr(), env),
+        Stmt::FunctionDeclaration(fn_declaration) => return eval_fn_declaration(fn_declaration, env),
+        Stmt::ClassDeclaration(class_declaration) => return eval_class_declaration(class_declaration, env),
+        Stmt::EnumDeclaration(enum_declaration) => return eval_enum_declaration(enum_declaration, env),
+        Stmt::ImportDeclaration(import_declaration) => return eval_import_declaration(import_declaration, env),
+        Stmt::ExportDeclaration(export_declaration) => return evaluate(&export_declaration.declaration, env),
+        Stmt::ReturnStmt(return_stmt) => return eval_return_stmt(return_stmt, env),
+        Stmt::ThrowStmt(throw_stmt) => return eval_throw_stmt(throw_stmt, env),
+        Stmt::TryStmt(try_stmt) => return eval_try_stmt(try_stmt, env),
+        Stmt::ForStmt(for_stmt) => return eval_for_stmt(for_stmt, env),
+        Stmt::YieldStmt(_) => unreachable!("generator bodies always run on the VM"),
+        Stmt::Comment(_) => unreachable!("comments are only kept for the formatter"),
+        Stmt::Expr(expr) => return eval_expr(expr, env),
     };
 }
 
-fn eval_assignment(node: AssignmentExpr, env: &mut Environment) -> RuntimeVal {
+fn eval_assignment(node: &AssignmentExpr, env: &Rc<RefCell<Environment>>) -> EvalResult {
     match node.assigne {
-        Expr::Identifier(identifier) => {
-            let varname = identifier.symbol.clone();
-            let value = evaluate(Stmt::Expr(node.value), env);
-            return env.assign_var(varname, value)
+        Expr::Identifier(ref identifier) => {
+            let value = eval_expr(&node.value, env)?;
+            match identifier.slot {
+                Some((depth, slot)) => return Ok(env.borrow_mut().assign_at(depth, slot, &identifier.symbol, value)?),
+                None => return Ok(env.borrow_mut().assign_var(identifier.symbol.clone(), value)?),
+            }
+        },
+        Expr::MemberExpr(ref member) => {
+            let object = eval_expr(&member.object, env)?;
+            let value = eval_expr(&node.value, env)?;
+            return set_property(object, member.property.clone(), value)
+        },
+        Expr::IndexExpr(ref index_expr) => {
+            let object = eval_expr(&index_expr.object, env)?;
+            let index = eval_expr(&index_expr.index, env)?;
+            let value = eval_expr(&node.value, env)?;
+            return set_index(object, index, value)
         },
         _ => {
-            panic!("Invalid LHS inside assignment expression:\n{:#?}", node.assigne)
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Invalid left-hand side in assignment expression: {:?}", node.assigne)
+            ).into())
         },
     }
-}
\ No newline at end of file
+}
+
+pub fn set_property(object: RuntimeVal, property: String, value: RuntimeVal) -> EvalResult {
+    if let RuntimeVal::InstanceVal(instance) = object {
+        instance.fields.borrow_mut().insert(property, value.clone());
+        return Ok(value)
+    }
+    return Err(ErrorVal::make_error(
+        "TypeError",
+        format!("Cannot set property '{}' on {:?}", property, object.get_value_type())
+    ).into())
+}
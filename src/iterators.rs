@@ -0,0 +1,276 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, Lines};
+use std::rc::Rc;
+use crate::builtins::expect_args;
+use crate::compiler::Chunk;
+use crate::environment::Environment;
+use crate::fs::io_error;
+use crate::interpreter::{call_function, call_method, pop_frame, push_frame, EvalResult, Interrupt};
+use crate::limits;
+use crate::lists::predicate_holds;
+use crate::values::{EnumVal, ErrorVal, IteratorVal, ListVal, RuntimeVal, ValueType, VariantVal};
+use crate::vm::{Exit, Frame};
+
+// where an iterator's items come from; adapters wrap the iterator they draw on
+pub enum Source {
+    List { items: Rc<RefCell<Vec<RuntimeVal>>>, index: usize }, // live, so items pushed mid-loop are visited
+    Chars(std::vec::IntoIter<char>),
+    Lines { path: String, lines: Lines<BufReader<File>> },
+    Range { next: Option<i64>, end: Option<i64>, step: i64 }, // `next` is none once stepping overflowed
+    Object(RuntimeVal), // a script object with its own next() method
+    Generator { name: String, frame: Frame },
+    Map { source: IteratorVal, function: RuntimeVal },
+    Filter { source: IteratorVal, predicate: RuntimeVal },
+    Take { source: IteratorVal, remaining: usize },
+    Skip { source: IteratorVal, remaining: usize },
+    Chain { first: IteratorVal, second: IteratorVal },
+    Running, // stands in while the real source is out being advanced
+    Done,
+}
+
+// `next()` hands back Iter.Done once a sequence runs out, so scripts can match on it
+pub fn iter_enum() -> EnumVal {
+    return EnumVal {
+        value_type: ValueType::Enum,
+        name: String::from("Iter"),
+        variants: vec![(String::from("Done"), Vec::new())],
+    }
+}
+
+pub fn done_marker() -> RuntimeVal {
+    return VariantVal {
+        value_type: ValueType::Variant,
+        enum_name: String::from("Iter"),
+        variant: String::from("Done"),
+        field_names: Vec::new(),
+        fields: Vec::new(),
+    }.to_runtime_val()
+}
+
+fn is_done(value: &RuntimeVal) -> bool {
+    return matches!(value, RuntimeVal::VariantVal(variant) if variant.enum_name == "Iter" && variant.variant == "Done")
+}
+
+pub fn make_iterator(label: String, source: Source) -> Result<IteratorVal, ErrorVal> {
+    limits::allocate()?;
+    return Ok(IteratorVal::make_iterator(label, source))
+}
+
+fn adapter(source: Source) -> EvalResult {
+    return Ok(make_iterator(String::from("iterator"), source)?.to_runtime_val())
+}
+
+// what `for ... in` and iter() accept: iterators as they are, lists, strings by character,
+// and instances whose class defines next()
+pub fn iterate(value: RuntimeVal) -> Result<IteratorVal, ErrorVal> {
+    let source = match value {
+        RuntimeVal::IteratorVal(iterator) => return Ok(iterator),
+        RuntimeVal::ListVal(list) => Source::List { items: list.items, index: 0 },
+        RuntimeVal::StringVal(string) => Source::Chars(string.value.chars().collect::<Vec<char>>().into_iter()),
+        RuntimeVal::InstanceVal(ref instance) if instance.class.find_method("next").is_some() => Source::Object(value),
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot iterate over a value of type {:?}", value.get_value_type())
+            ))
+        },
+    };
+    return make_iterator(String::from("iterator"), source)
+}
+
+// calling a generator function runs none of its body; the frame waits for the first next()
+pub fn make_generator(name: String, chunk: Rc<Chunk>, scope: Rc<RefCell<Environment>>) -> EvalResult {
+    let frame = Frame::new(chunk, scope);
+    return Ok(make_iterator(format!("generator {}", name), Source::Generator { name, frame })?.to_runtime_val())
+}
+
+// the next item, or none once the iterator is exhausted; an iterator that has finished or
+// failed stays finished. each item drawn is a step, so draining an endless native source
+// still runs out of fuel or time
+pub fn next_item(iterator: &IteratorVal, env: &Rc<RefCell<Environment>>) -> Result<Option<RuntimeVal>, Interrupt> {
+    limits::tick()?;
+    let mut source = iterator.source.replace(Source::Running);
+    if let Source::Running = source {
+        return Err(ErrorVal::make_error(
+            "ValueError",
+            format!("Cannot advance the {} from inside its own next step", iterator.label)
+        ).into())
+    }
+    let result = advance(&mut source, env);
+    if !matches!(result, Ok(Some(_))) {
+        source = Source::Done;
+    }
+    iterator.source.replace(source);
+    return result
+}
+
+fn advance(source: &mut Source, env: &Rc<RefCell<Environment>>) -> Result<Option<RuntimeVal>, Interrupt> {
+    match source {
+        Source::List { items, index } => {
+            let item = items.borrow().get(*index).cloned();
+            *index += 1;
+            return Ok(item)
+        },
+        Source::Chars(chars) => return Ok(chars.next().map(|c| RuntimeVal::from(String::from(c)))),
+        Source::Lines { path, lines } => return Ok(lines.next().transpose().map_err(|error| io_error("read", path, error))?.map(RuntimeVal::from)),
+        Source::Range { next, end, step } => {
+            let value = match (*next, *end) {
+                (Some(value), _) => value,
+                (None, Some(_)) => return Ok(None),
+                (None, None) => {
+                    return Err(ErrorVal::make_error(
+                        "OverflowError",
+                        String::from("An endless range ran past the largest integer")
+                    ).into())
+                },
+            };
+            if let Some(end) = *end {
+                if (*step > 0 && value >= end) || (*step < 0 && value <= end) {
+                    return Ok(None)
+                }
+            }
+            *next = value.checked_add(*step);
+            return Ok(Some(RuntimeVal::from(value)))
+        },
+        Source::Object(object) => {
+            let value = call_method(object.clone(), "next", Vec::new(), env)?;
+            if is_done(&value) {
+                return Ok(None)
+            }
+            return Ok(Some(value))
+        },
+        Source::Generator { name, frame } => {
+            push_frame(name.clone())?;
+            let result = limits::grow_stack(|| frame.resume());
+            pop_frame();
+            match result? {
+                Exit::Yielded(value) => return Ok(Some(value)),
+                Exit::Returned(_) => return Ok(None),
+            }
+        },
+        Source::Map { source, function } => {
+            match next_item(source, env)? {
+                Some(item) => return Ok(Some(call_function(function.clone(), vec![item], env)?)),
+                None => return Ok(None),
+            }
+        },
+        Source::Filter { source, predicate } => {
+            while let Some(item) = next_item(source, env)? {
+                if predicate_holds("filter", predicate, item.clone(), env)? {
+                    return Ok(Some(item))
+                }
+            }
+            return Ok(None)
+        },
+        // stops without drawing another item, so taking from an endless source finishes
+        Source::Take { source, remaining } => {
+            if *remaining == 0 {
+                return Ok(None)
+            }
+            *remaining -= 1;
+            return next_item(source, env)
+        },
+        Source::Skip { source, remaining } => {
+            while *remaining > 0 {
+                *remaining -= 1;
+                if next_item(source, env)?.is_none() {
+                    return Ok(None)
+                }
+            }
+            return next_item(source, env)
+        },
+        Source::Chain { first, second } => {
+            match next_item(first, env)? {
+                Some(item) => return Ok(Some(item)),
+                None => return next_item(second, env),
+            }
+        },
+        Source::Running => unreachable!("a running source is never advanced"),
+        Source::Done => return Ok(None),
+    }
+}
+
+fn count(method: &str, argument: &RuntimeVal) -> Result<usize, ErrorVal> {
+    let count = argument.get_number_value()?;
+    return usize::try_from(count).map_err(|_| {
+        ErrorVal::make_error("ValueError", format!("{} needs a count of at least 0, not {}", method, count))
+    })
+}
+
+// adapters are lazy: nothing is drawn from the source until the result is advanced
+pub fn call_iterator_method(iterator: IteratorVal, method: &str, arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    match method {
+        "next" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(next_item(&iterator, env)?.unwrap_or_else(done_marker))
+        },
+        "collect" => {
+            expect_args(method, &arguments, 0)?;
+            let mut items = Vec::new();
+            while let Some(item) = next_item(&iterator, env)? {
+                // charged as the list grows, so collecting an endless iterator stops at the memory limit
+                limits::allocate_items(1)?;
+                items.push(item);
+            }
+            limits::allocate()?;
+            return Ok(ListVal::make_list(items).to_runtime_val())
+        },
+        "map" => {
+            expect_args(method, &arguments, 1)?;
+            return adapter(Source::Map { source: iterator, function: arguments[0].clone() })
+        },
+        "filter" => {
+            expect_args(method, &arguments, 1)?;
+            return adapter(Source::Filter { source: iterator, predicate: arguments[0].clone() })
+        },
+        "take" => {
+            expect_args(method, &arguments, 1)?;
+            return adapter(Source::Take { source: iterator, remaining: count(method, &arguments[0])? })
+        },
+        "skip" => {
+            expect_args(method, &arguments, 1)?;
+            return adapter(Source::Skip { source: iterator, remaining: count(method, &arguments[0])? })
+        },
+        "chain" => {
+            expect_args(method, &arguments, 1)?;
+            return adapter(Source::Chain { first: iterator, second: iterate(arguments[0].clone())? })
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Iterator has no method '{}'", method)
+            ).into())
+        },
+    }
+}
+
+pub fn native_iter(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("iter", &arguments, 1)?;
+    return Ok(iterate(arguments[0].clone())?.to_runtime_val())
+}
+
+// range(end), range(start, end) or range(start, end, step); an end of null never stops
+pub fn native_range(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let bound = |argument: &RuntimeVal| -> Result<Option<i64>, ErrorVal> {
+        match argument {
+            RuntimeVal::NullVal(_) => return Ok(None),
+            _ => return Ok(Some(argument.get_number_value()?)),
+        }
+    };
+    let (start, end, step) = match arguments.as_slice() {
+        [end] => (0, bound(end)?, 1),
+        [start, end] => (start.get_number_value()?, bound(end)?, 1),
+        [start, end, step] => (start.get_number_value()?, bound(end)?, step.get_number_value()?),
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("range expects 1 to 3 argument(s) but received {}", arguments.len())
+            ).into())
+        },
+    };
+    if step == 0 {
+        return Err(ErrorVal::make_error("ValueError", String::from("range step cannot be 0")).into())
+    }
+    return adapter(Source::Range { next: Some(start), end, step })
+}
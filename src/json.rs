@@ -0,0 +1,427 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+use crate::environment::Environment;
+use crate::builtins::expect_args;
+use crate::interpreter::EvalResult;
+use crate::limits;
+use crate::values::{ClassVal, ErrorVal, InstanceVal, ModuleVal, NativeFnVal, NullVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+// deeper documents are refused rather than risking the native stack
+const MAX_DEPTH: usize = 512;
+
+thread_local! {
+    static OBJECT: ClassVal = ClassVal {
+        value_type: ValueType::Class,
+        name: String::from("Object"),
+        superclass: None,
+        methods: Rc::new(HashMap::new()),
+        static_methods: Rc::new(HashMap::new()),
+    };
+}
+
+// JSON objects become instances of this method-less class, so `value instanceof Object` holds
+// for them and `new Object()` builds one to stringify
+pub fn object_class() -> ClassVal {
+    return OBJECT.with(ClassVal::clone)
+}
+
+pub fn json_module() -> ModuleVal {
+    let functions: [(&str, Native); 2] = [
+        ("parse", native_parse),
+        ("stringify", native_stringify),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("json"),
+        exports: Rc::new(exports),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ErrorVal {
+        return ErrorVal::make_error("ValueError", format!("Invalid JSON at line {} column {}: {}", self.line, self.column, message))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        return Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: RuntimeVal) -> Result<RuntimeVal, ErrorVal> {
+        for expected in word.chars() {
+            if self.chars.peek() != Some(&expected) {
+                return Err(self.error(&format!("expected '{}'", word)))
+            }
+            self.next();
+        }
+        return Ok(value)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<RuntimeVal, ErrorVal> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(&format!("nested more than {} levels deep", MAX_DEPTH)))
+        }
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('{') => return self.object(depth),
+            Some('[') => return self.array(depth),
+            Some('"') => return Ok(RuntimeVal::from(self.string()?)),
+            Some('t') => return self.expect_word("true", RuntimeVal::from(true)),
+            Some('f') => return self.expect_word("false", RuntimeVal::from(false)),
+            Some('n') => return self.expect_word("null", NullVal::make_null().to_runtime_val()),
+            Some('-' | '0'..='9') => return self.number(),
+            Some(c) => return Err(self.error(&format!("unexpected character '{}'", c))),
+            None => return Err(self.error("unexpected end of input")),
+        }
+    }
+
+    // a separator or the closing bracket after each member; trailing commas are not JSON
+    fn after_member(&mut self, close: char) -> Result<bool, ErrorVal> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(',') => {
+                self.next();
+                return Ok(false)
+            },
+            Some(&c) if c == close => {
+                self.next();
+                return Ok(true)
+            },
+            _ => return Err(self.error(&format!("expected ',' or '{}'", close))),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<RuntimeVal, ErrorVal> {
+        self.next();
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+        } else {
+            loop {
+                items.push(self.value(depth + 1)?);
+                if self.after_member(']')? {
+                    break;
+                }
+            }
+        }
+        limits::allocate_list(items.len())?;
+        return Ok(RuntimeVal::from(items))
+    }
+
+    // a repeated key keeps its last value, as most parsers do
+    fn object(&mut self, depth: usize) -> Result<RuntimeVal, ErrorVal> {
+        self.next();
+        let mut fields = HashMap::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+        } else {
+            loop {
+                self.skip_whitespace();
+                if self.chars.peek() != Some(&'"') {
+                    return Err(self.error("expected a string key"))
+                }
+                let key = self.string()?;
+                self.skip_whitespace();
+                if self.chars.peek() != Some(&':') {
+                    return Err(self.error("expected ':' after an object key"))
+                }
+                self.next();
+                fields.insert(key, self.value(depth + 1)?);
+                if self.after_member('}')? {
+                    break;
+                }
+            }
+        }
+        limits::allocate()?;
+        let object = InstanceVal::make_instance(object_class());
+        *object.fields.borrow_mut() = fields;
+        return Ok(object.to_runtime_val())
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, ErrorVal> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.error("expected four hex digits after \\u")),
+            }
+        }
+        return Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, ErrorVal> {
+        self.next();
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.hex_escape()?;
+                            // characters outside the basic plane arrive as a surrogate pair
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.next() != Some('\\') || self.next() != Some('u') {
+                                    return Err(self.error("expected a low surrogate after a high one"))
+                                }
+                                let low = self.hex_escape()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("expected a low surrogate after a high one"))
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            match char::from_u32(code) {
+                                Some(c) => c,
+                                None => return Err(self.error("unpaired surrogate in \\u escape")),
+                            }
+                        },
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    string.push(escaped);
+                },
+                Some(c) if c < ' ' => return Err(self.error("control characters must be escaped in strings")),
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn digits(&mut self, text: &mut String) -> usize {
+        let mut count = 0;
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            text.push(c);
+            self.next();
+            count += 1;
+        }
+        return count
+    }
+
+    // integers that fit in 64 bits stay Numbers; a fraction, an exponent or a larger magnitude
+    // makes a Float
+    fn number(&mut self) -> Result<RuntimeVal, ErrorVal> {
+        let mut text = String::new();
+        if self.chars.peek() == Some(&'-') {
+            text.push('-');
+            self.next();
+        }
+        let whole = self.digits(&mut text);
+        if whole == 0 {
+            return Err(self.error("expected digits"))
+        }
+        if whole > 1 && text.trim_start_matches('-').starts_with('0') {
+            return Err(self.error("numbers cannot have leading zeros"))
+        }
+        let mut float = false;
+        if self.chars.peek() == Some(&'.') {
+            text.push('.');
+            self.next();
+            if self.digits(&mut text) == 0 {
+                return Err(self.error("expected digits after '.'"))
+            }
+            float = true;
+        }
+        if matches!(self.chars.peek(), Some('e' | 'E')) {
+            text.push('e');
+            self.next();
+            if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                text.push(sign);
+                self.next();
+            }
+            if self.digits(&mut text) == 0 {
+                return Err(self.error("expected digits in the exponent"))
+            }
+            float = true;
+        }
+        if !float {
+            if let Ok(value) = text.parse::<i64>() {
+                return Ok(RuntimeVal::from(value))
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => return Ok(RuntimeVal::from(value)),
+            _ => return Err(self.error(&format!("number {} is too large", text))),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<RuntimeVal, ErrorVal> {
+    let mut parser = Parser { chars: text.chars().peekable(), line: 1, column: 1 };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return Err(parser.error("unexpected text after the value"))
+    }
+    return Ok(value)
+}
+
+fn escape(value: &str, out: &mut String) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Writer {
+    indent: Option<String>,
+    seen: Vec<*const ()>, // the lists and objects currently being written, to catch cycles
+}
+
+impl Writer {
+    fn newline(&self, depth: usize, out: &mut String) {
+        if let Some(ref indent) = self.indent {
+            out.push('\n');
+            out.push_str(&indent.repeat(depth));
+        }
+    }
+
+    fn enter(&mut self, pointer: *const ()) -> Result<(), ErrorVal> {
+        if self.seen.contains(&pointer) {
+            return Err(ErrorVal::make_error("ValueError", String::from("Cannot convert a cyclic structure to JSON")))
+        }
+        self.seen.push(pointer);
+        return Ok(())
+    }
+
+    // opening bracket, members, closing bracket; empty containers stay on one line
+    fn members(&mut self, open: char, close: char, members: Vec<(Option<&str>, &RuntimeVal)>, depth: usize, out: &mut String) -> Result<(), ErrorVal> {
+        out.push(open);
+        let empty = members.is_empty();
+        for (index, (key, value)) in members.into_iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            self.newline(depth + 1, out);
+            if let Some(key) = key {
+                escape(key, out);
+                out.push_str(if self.indent.is_some() { ": " } else { ":" });
+            }
+            self.write(value, depth + 1, out)?;
+        }
+        if !empty {
+            self.newline(depth, out);
+        }
+        out.push(close);
+        return Ok(())
+    }
+
+    fn write(&mut self, value: &RuntimeVal, depth: usize, out: &mut String) -> Result<(), ErrorVal> {
+        match value {
+            RuntimeVal::NullVal(_) => out.push_str("null"),
+            RuntimeVal::BooleanVal(boolean) => out.push_str(&boolean.value.to_string()),
+            RuntimeVal::NumberVal(number) => out.push_str(&number.value.to_string()),
+            // Debug keeps the '.0' so the float comes back as a float
+            RuntimeVal::FloatVal(float) if float.value.is_finite() => out.push_str(&format!("{:?}", float.value)),
+            RuntimeVal::FloatVal(float) => {
+                return Err(ErrorVal::make_error("ValueError", format!("Cannot convert {:?} to JSON", float.value)))
+            },
+            RuntimeVal::StringVal(string) => escape(&string.value, out),
+            RuntimeVal::ListVal(list) => {
+                self.enter(Rc::as_ptr(&list.items) as *const ())?;
+                let items = list.items.borrow().clone();
+                self.members('[', ']', items.iter().map(|item| (None, item)).collect(), depth, out)?;
+                self.seen.pop();
+            },
+            // any instance's fields make an object, in key order so the output is stable
+            RuntimeVal::InstanceVal(instance) => {
+                self.enter(Rc::as_ptr(&instance.fields) as *const ())?;
+                let fields = instance.fields.borrow().clone();
+                let mut members: Vec<(Option<&str>, &RuntimeVal)> = fields.iter().map(|(key, value)| (Some(key.as_str()), value)).collect();
+                members.sort_by_key(|(key, _)| *key);
+                self.members('{', '}', members, depth, out)?;
+                self.seen.pop();
+            },
+            _ => {
+                return Err(ErrorVal::make_error(
+                    "TypeError",
+                    format!("Cannot convert a value of type {:?} to JSON", value.get_value_type())
+                ))
+            },
+        }
+        return Ok(())
+    }
+}
+
+// compact without an indent or with an indent of 0; otherwise one member per line, indented by
+// that many spaces per level
+pub fn stringify(value: &RuntimeVal, indent: Option<usize>) -> Result<String, ErrorVal> {
+    let indent = indent.filter(|spaces| *spaces > 0).map(|spaces| " ".repeat(spaces));
+    let mut writer = Writer { indent, seen: Vec::new() };
+    let mut out = String::new();
+    writer.write(value, 0, &mut out)?;
+    return Ok(out)
+}
+
+fn native_parse(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("json.parse", &arguments, 1)?;
+    return Ok(parse(&arguments[0].get_string_value()?)?)
+}
+
+// json.stringify(value) or json.stringify(value, indent), where an indent of null means compact
+fn native_stringify(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let indent = match arguments.as_slice() {
+        [_] | [_, RuntimeVal::NullVal(_)] => None,
+        [_, indent] => {
+            let spaces = indent.get_number_value()?;
+            match usize::try_from(spaces) {
+                Ok(spaces) if spaces <= 10 => Some(spaces),
+                _ => return Err(ErrorVal::make_error("ValueError", format!("json.stringify needs an indent from 0 to 10, not {}", spaces)).into()),
+            }
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("json.stringify expects 1 or 2 argument(s) but received {}", arguments.len())
+            ).into())
+        },
+    };
+    return Ok(RuntimeVal::from(stringify(&arguments[0], indent)?))
+}
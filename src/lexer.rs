@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
@@ -6,18 +8,55 @@ pub enum TokenType {
     //literal types
     Null,
     Number,
+    Float,
+    String,
     Identifier,
+    Capture, // the command line inside `$( )`
 
     //keywords
     Let,
     Const,
+    Fn,
+    Return,
+    Import,
+    Export,
+    From,
+    As,
+    Enum,
+    Match,
+    Class,
+    Extends,
+    Static,
+    New,
+    This,
+    Super,
+    InstanceOf,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    For,
+    In,
+    Yield,
 
     //grouping * operators
     Equals,
+    FatArrow,
     Semicolon,
+    Comma,
+    Dot,
+    Question,
     OpenParen,
     CloseParen,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
     BinaryOperator,
+
+    //trivia, set aside by the parser
+    Comment,
+    DocComment,
     EOF, // end of file
 }
 
@@ -31,8 +70,13 @@ pub fn token(tvalue: String, ttype: TokenType) -> Token {
     Token { tvalue, ttype }
 }
 
-fn is_alpha(c: char) -> bool {
-    c.is_alphabetic()
+// identifiers follow Unicode's XID_Start and XID_Continue, as Rust's do, plus a leading underscore
+fn is_ident_start(c: char) -> bool {
+    unicode_ident::is_xid_start(c) || c == '_'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
 }
 
 fn is_skippable(c: char) -> bool {
@@ -40,7 +84,74 @@ fn is_skippable(c: char) -> bool {
 }
 
 fn is_digit(c: char) -> bool {
-    c.is_digit(10)
+    c.is_ascii_digit()
+}
+
+fn take_word(src: &mut Peekable<Chars>, word: &mut String) {
+    while let Some(&next) = src.peek() {
+        if !is_ident_continue(next) {
+            break;
+        }
+        word.push(next);
+        src.next();
+    }
+}
+
+// turns the source spelling of a number (`42`, `0xFF`, `0o17`, `0b1010`, `1_000`) into its value
+pub fn parse_number(literal: &str) -> Result<i64, String> {
+    let (radix, name, digits) = match literal.get(..2) {
+        Some("0x") => (16, "hexadecimal", &literal[2..]),
+        Some("0o") => (8, "octal", &literal[2..]),
+        Some("0b") => (2, "binary", &literal[2..]),
+        _ => (10, "decimal", literal),
+    };
+    if digits.is_empty() {
+        return Err(format!("Missing digits in {} literal {}", name, literal))
+    }
+    if let Some(invalid) = digits.chars().find(|&c| c != '_' && !c.is_digit(radix)) {
+        return Err(format!("Invalid digit '{}' in {} literal {}", invalid, name, literal))
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(format!("Misplaced '_' in numeric literal {}: separators go between digits", literal))
+    }
+    match i64::from_str_radix(&digits.replace('_', ""), radix) {
+        Ok(value) => return Ok(value),
+        Err(_) => return Err(format!("Numeric literal {} does not fit in a 64-bit integer", literal)),
+    }
+}
+
+// decimal digits with an optional fraction and exponent, such as 1.5, 2e10 or 6.022_140e23
+pub fn parse_float(literal: &str) -> Result<f64, String> {
+    let (mantissa, exponent) = match literal.find(['e', 'E']) {
+        Some(index) => (&literal[..index], Some(&literal[index + 1..])),
+        None => (literal, None),
+    };
+    let mut parts = vec![mantissa.split_once('.').map_or(mantissa, |(whole, _)| whole)];
+    if let Some((_, fraction)) = mantissa.split_once('.') {
+        if fraction.is_empty() {
+            return Err(format!("Missing digits after '.' in float literal {}", literal))
+        }
+        parts.push(fraction);
+    }
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if digits.is_empty() {
+            return Err(format!("Missing digits in the exponent of float literal {}", literal))
+        }
+        parts.push(digits);
+    }
+    for part in parts {
+        if let Some(invalid) = part.chars().find(|&c| c != '_' && !c.is_ascii_digit()) {
+            return Err(format!("Invalid digit '{}' in float literal {}", invalid, literal))
+        }
+        if part.starts_with('_') || part.ends_with('_') || part.contains("__") {
+            return Err(format!("Misplaced '_' in numeric literal {}: separators go between digits", literal))
+        }
+    }
+    match literal.replace('_', "").parse::<f64>() {
+        Ok(value) if value.is_finite() => return Ok(value),
+        _ => return Err(format!("Float literal {} is too large", literal)),
+    }
 }
 
 fn make_hashmap() -> HashMap<String, TokenType> {
@@ -48,45 +159,227 @@ fn make_hashmap() -> HashMap<String, TokenType> {
     keywords.insert(String::from("let"), TokenType::Let);
     keywords.insert(String::from("const"), TokenType::Const);
     keywords.insert(String::from("null"), TokenType::Null);
+    keywords.insert(String::from("fn"), TokenType::Fn);
+    keywords.insert(String::from("return"), TokenType::Return);
+    keywords.insert(String::from("import"), TokenType::Import);
+    keywords.insert(String::from("export"), TokenType::Export);
+    keywords.insert(String::from("from"), TokenType::From);
+    keywords.insert(String::from("as"), TokenType::As);
+    keywords.insert(String::from("enum"), TokenType::Enum);
+    keywords.insert(String::from("match"), TokenType::Match);
+    keywords.insert(String::from("class"), TokenType::Class);
+    keywords.insert(String::from("extends"), TokenType::Extends);
+    keywords.insert(String::from("static"), TokenType::Static);
+    keywords.insert(String::from("new"), TokenType::New);
+    keywords.insert(String::from("this"), TokenType::This);
+    keywords.insert(String::from("super"), TokenType::Super);
+    keywords.insert(String::from("instanceof"), TokenType::InstanceOf);
+    keywords.insert(String::from("throw"), TokenType::Throw);
+    keywords.insert(String::from("try"), TokenType::Try);
+    keywords.insert(String::from("catch"), TokenType::Catch);
+    keywords.insert(String::from("finally"), TokenType::Finally);
+    keywords.insert(String::from("for"), TokenType::For);
+    keywords.insert(String::from("in"), TokenType::In);
+    keywords.insert(String::from("yield"), TokenType::Yield);
     return keywords
 }
 
-pub fn tokenize(source_code: String) -> Vec<Token> {
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub unterminated: bool, // the source ended inside a string or block comment
+}
+
+pub fn tokenize(source_code: String) -> Result<Vec<Token>, LexError> {
     let mut tokens = Vec::new();
     let mut src = source_code.chars().peekable();
     let keywords = make_hashmap();
 
+    // a `#!` first line lets scripts be executed directly
+    if source_code.starts_with("#!") {
+        let shebang: String = src.by_ref().take_while(|&c| c != '\n').collect();
+        tokens.push(token(shebang, TokenType::Comment));
+    }
+
     while let Some(&current) = src.peek() {
-        if current == '(' {
+        if current == '/' && src.clone().nth(1) == Some('/') {
+            let comment: String = src.by_ref().take_while(|&c| c != '\n').collect();
+            // exactly three slashes make a doc comment, four or more are an ordinary comment
+            match comment.strip_prefix("///") {
+                Some(doc) if !doc.starts_with('/') => {
+                    let doc = doc.strip_prefix(' ').unwrap_or(doc);
+                    tokens.push(token(String::from(doc.trim_end()), TokenType::DocComment));
+                },
+                _ => tokens.push(token(String::from(comment.trim_end()), TokenType::Comment)),
+            }
+        } else if current == '/' && src.clone().nth(1) == Some('*') {
+            let mut comment = String::new();
+            let mut depth = 0;
+            loop {
+                match (src.next(), src.peek()) {
+                    (Some('/'), Some(&'*')) => {
+                        comment.push_str("/*");
+                        src.next();
+                        depth += 1;
+                    },
+                    (Some('*'), Some(&'/')) => {
+                        comment.push_str("*/");
+                        src.next();
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    },
+                    (Some(next), _) => comment.push(next),
+                    (None, _) => {
+                        return Err(LexError { message: String::from("Unterminated block comment found."), unterminated: true })
+                    },
+                }
+            }
+            tokens.push(token(comment, TokenType::Comment));
+        } else if current == '(' {
             tokens.push(token(current.to_string(), TokenType::OpenParen));
             src.next();
         } else if current == ')' {
             tokens.push(token(current.to_string(), TokenType::CloseParen));
             src.next();
-        } else if "+-*/%".contains(current) {
+        } else if current == '{' {
+            tokens.push(token(current.to_string(), TokenType::OpenBrace));
+            src.next();
+        } else if current == '}' {
+            tokens.push(token(current.to_string(), TokenType::CloseBrace));
+            src.next();
+        } else if current == '[' {
+            tokens.push(token(current.to_string(), TokenType::OpenBracket));
+            src.next();
+        } else if current == ']' {
+            tokens.push(token(current.to_string(), TokenType::CloseBracket));
+            src.next();
+        } else if current == '$' && src.clone().nth(1) == Some('(') {
+            src.nth(1);
+            let mut command = String::new();
+            let mut depth = 0;
+            let mut quote = None;
+            // nested parentheses and quoted text belong to the command, so `$(echo ")")` works
+            loop {
+                match (src.next(), quote) {
+                    (Some('\\'), Some('"')) => {
+                        command.push('\\');
+                        command.extend(src.next());
+                    },
+                    (Some(c), Some(open)) => {
+                        if c == open {
+                            quote = None;
+                        }
+                        command.push(c);
+                    },
+                    (Some(')'), None) if depth == 0 => break,
+                    (Some(c), None) => {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            '"' | '\'' => quote = Some(c),
+                            _ => {},
+                        }
+                        command.push(c);
+                    },
+                    (None, _) => {
+                        return Err(LexError { message: String::from("Unterminated command capture found."), unterminated: true })
+                    },
+                }
+            }
+            tokens.push(token(command, TokenType::Capture));
+        } else if "+-*/%|".contains(current) {
             tokens.push(token(current.to_string(), TokenType::BinaryOperator));
             src.next();
         } else if current == '=' {
-            tokens.push(token(current.to_string(), TokenType::Equals));
             src.next();
+            if src.peek() == Some(&'=') {
+                src.next();
+                tokens.push(token(String::from("=="), TokenType::BinaryOperator));
+            } else if src.peek() == Some(&'>') {
+                src.next();
+                tokens.push(token(String::from("=>"), TokenType::FatArrow));
+            } else {
+                tokens.push(token(current.to_string(), TokenType::Equals));
+            }
+        } else if current == '!' {
+            src.next();
+            if src.peek() == Some(&'=') {
+                src.next();
+                tokens.push(token(String::from("!="), TokenType::BinaryOperator));
+            } else {
+                return Err(LexError { message: format!("Unrecognized character found: {}", current), unterminated: false })
+            }
         } else if current == ';' {
             tokens.push(token(current.to_string(), TokenType::Semicolon));
             src.next();
+        } else if current == ',' {
+            tokens.push(token(current.to_string(), TokenType::Comma));
+            src.next();
+        } else if current == '?' {
+            tokens.push(token(current.to_string(), TokenType::Question));
+            src.next();
+        } else if current == '.' {
+            tokens.push(token(current.to_string(), TokenType::Dot));
+            src.next();
+        } else if current == '"' {
+            src.next();
+            let mut string = String::new();
+            loop {
+                match src.next() {
+                    Some('"') => break,
+                    Some('\\') => match src.next() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some('"') => string.push('"'),
+                        Some('\\') => string.push('\\'),
+                        Some(other) => {
+                            return Err(LexError { message: format!("Unrecognized escape sequence found: \\{}", other), unterminated: false })
+                        },
+                        None => {
+                            return Err(LexError { message: String::from("Unterminated string literal found."), unterminated: true })
+                        },
+                    },
+                    Some(next) => string.push(next),
+                    None => {
+                        return Err(LexError { message: String::from("Unterminated string literal found."), unterminated: true })
+                    },
+                }
+            }
+            tokens.push(token(string, TokenType::String));
         } else if is_digit(current) {
+            // take every letter and digit attached to the number so `12ab` is reported as a whole
             let mut num = String::new();
-            while let Some(&next) = src.peek() {
-                if is_digit(next) {
-                    num.push(next);
-                    src.next();
-                } else {
-                    break;
+            take_word(&mut src, &mut num);
+            let decimal = !matches!(num.get(..2), Some("0x" | "0o" | "0b"));
+            // a '.' only continues the number when a digit follows, so `1.method` stays a member access
+            let mut ahead = src.clone();
+            ahead.next();
+            if decimal && src.peek() == Some(&'.') && ahead.peek().is_some_and(|c| is_digit(*c)) {
+                num.push('.');
+                src.next();
+                take_word(&mut src, &mut num);
+            }
+            if decimal && (num.ends_with('e') || num.ends_with('E')) && matches!(src.peek(), Some('+' | '-')) {
+                num.push(src.next().unwrap());
+                take_word(&mut src, &mut num);
+            }
+            if decimal && num.contains(['.', 'e', 'E']) {
+                if let Err(message) = parse_float(&num) {
+                    return Err(LexError { message, unterminated: false })
+                }
+                tokens.push(token(num, TokenType::Float));
+            } else {
+                if let Err(message) = parse_number(&num) {
+                    return Err(LexError { message, unterminated: false })
                 }
+                tokens.push(token(num, TokenType::Number));
             }
-            tokens.push(token(num, TokenType::Number));
-        } else if is_alpha(current) {
+        } else if is_ident_start(current) {
             let mut ident = String::new();
             while let Some(&next) = src.peek() {
-                if is_alpha(next) {
+                if is_ident_continue(next) {
                     ident.push(next);
                     src.next();
                 } else {
@@ -101,10 +394,9 @@ pub fn tokenize(source_code: String) -> Vec<Token> {
         } else if is_skippable(current) {
             src.next();
         } else {
-            println!("Unrecognized character found: {}", current);
-            std::process::exit(1);
+            return Err(LexError { message: format!("Unrecognized character found: {}", current), unterminated: false })
         }
     }
     tokens.push(token(String::from("EndOfFile"), TokenType::EOF));
-    tokens
+    return Ok(tokens)
 }
@@ -0,0 +1,33 @@
+#![allow(clippy::needless_return, clippy::wrong_self_convention)]
+mod lexer;
+pub mod ast;
+mod parser;
+mod values;
+mod interpreter;
+mod environment;
+mod builtins;
+mod modules;
+mod resolver;
+mod optimizer;
+mod compiler;
+mod vm;
+mod printer;
+mod engine;
+mod limits;
+mod math;
+mod strings;
+mod lists;
+mod iterators;
+mod fs;
+mod path;
+mod process;
+mod json;
+mod time;
+mod random;
+
+pub use engine::{Engine, EngineError};
+pub use lexer::{tokenize, LexError, Token, TokenType};
+pub use limits::Limits;
+pub use parser::{parse_source, SyntaxError};
+pub use printer::format_source;
+pub use values::{ErrorVal, RuntimeVal};
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::values::ErrorVal;
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 2000;
+
+// how often the clock is read, since Instant::now is far slower than a step
+const CLOCK_INTERVAL: u64 = 1024;
+
+// the longest a sleeping script goes without checking for an interrupt or the deadline
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+// the native stack grows in segments of this size once less than the red zone is left,
+// so deep recursion is bounded by max_call_depth rather than by the thread's stack
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+// strings count against max_objects by length, one object per this many bytes, so short ones are free
+const STRING_CHUNK: usize = 64;
+
+// what a single evaluation may use; everything except the call depth is unlimited by default
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub fuel: Option<u64>, // evaluation steps: statements and expressions, or instructions in the VM
+    pub timeout: Option<Duration>,
+    pub max_call_depth: usize,
+    pub max_objects: Option<u64>, // instances, closures, lists and enum variants with fields, plus list items and string bytes
+    pub allow_io: bool, // printing, reading files and importing modules
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        return Limits {
+            fuel: None,
+            timeout: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_objects: None,
+            allow_io: true,
+        }
+    }
+}
+
+impl Limits {
+    // no I/O plus the given budgets, for code that is not trusted
+    pub fn sandboxed(fuel: u64, timeout: Duration, max_objects: u64) -> Self {
+        return Limits {
+            fuel: Some(fuel),
+            timeout: Some(timeout),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_objects: Some(max_objects),
+            allow_io: false,
+        }
+    }
+}
+
+// what is left of the limits while one evaluation runs
+pub struct Budget {
+    limits: Limits,
+    steps: u64,
+    objects: u64,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    fn new(limits: Limits, interrupt: Option<Arc<AtomicBool>>) -> Self {
+        let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+        return Budget { limits, steps: 0, objects: 0, deadline, timed_out: false, interrupt }
+    }
+
+    fn tick(&mut self) -> Result<(), ErrorVal> {
+        self.steps += 1;
+        self.check_interrupt()?;
+        if let Some(fuel) = self.limits.fuel {
+            if self.steps > fuel {
+                return Err(ErrorVal::make_error("FuelError", format!("Ran out of fuel after {} steps", fuel)))
+            }
+        }
+        return self.check_deadline(self.steps.is_multiple_of(CLOCK_INTERVAL))
+    }
+
+    fn charge(&mut self, objects: u64) -> Result<(), ErrorVal> {
+        self.objects = self.objects.saturating_add(objects);
+        if let Some(max) = self.limits.max_objects {
+            if self.objects > max {
+                return Err(ErrorVal::make_error("MemoryError", format!("Created more than {} objects", max)))
+            }
+        }
+        return Ok(())
+    }
+
+    fn check_interrupt(&self) -> Result<(), ErrorVal> {
+        if let Some(ref interrupt) = self.interrupt {
+            if interrupt.load(Ordering::Relaxed) {
+                return Err(ErrorVal::make_error("InterruptError", String::from("Evaluation was interrupted by the host")))
+            }
+        }
+        return Ok(())
+    }
+
+    fn check_deadline(&mut self, read_clock: bool) -> Result<(), ErrorVal> {
+        if let Some(deadline) = self.deadline {
+            // once the deadline passes every later step fails too, so catch blocks cannot outlast it
+            if self.timed_out || (read_clock && Instant::now() >= deadline) {
+                self.timed_out = true;
+                return Err(ErrorVal::make_error(
+                    "TimeoutError",
+                    format!("Evaluation ran longer than {:?}", self.limits.timeout.unwrap())
+                ))
+            }
+        }
+        return Ok(())
+    }
+}
+
+thread_local! {
+    static BUDGET: RefCell<Budget> = RefCell::new(Budget::new(Limits::default(), None));
+}
+
+// starts a fresh budget, returning the one it replaces so nested evaluations can restore it
+pub fn install(limits: Limits, interrupt: Option<Arc<AtomicBool>>) -> Budget {
+    return BUDGET.with(|budget| budget.replace(Budget::new(limits, interrupt)))
+}
+
+pub fn restore(previous: Budget) {
+    BUDGET.with(|budget| budget.replace(previous));
+}
+
+// called once per evaluation step by both engines
+pub fn tick() -> Result<(), ErrorVal> {
+    return BUDGET.with(|budget| budget.borrow_mut().tick())
+}
+
+// sleeps in short slices, so a host interrupt or the timeout still ends the evaluation on time
+pub fn sleep(duration: Duration) -> Result<(), ErrorVal> {
+    let end = Instant::now() + duration;
+    loop {
+        BUDGET.with(|budget| {
+            let mut budget = budget.borrow_mut();
+            budget.check_interrupt()?;
+            return budget.check_deadline(true)
+        })?;
+        let now = Instant::now();
+        if now >= end {
+            return Ok(())
+        }
+        std::thread::sleep((end - now).min(SLEEP_SLICE));
+    }
+}
+
+// called with the depth the call stack is about to reach
+pub fn enter_call(depth: usize) -> Result<(), ErrorVal> {
+    let max = BUDGET.with(|budget| budget.borrow().limits.max_call_depth);
+    if depth > max {
+        return Err(ErrorVal::make_error("RecursionError", format!("Maximum call depth of {} exceeded", max)))
+    }
+    return Ok(())
+}
+
+// runs a call, growing the native stack first when it is close to running out
+pub fn grow_stack<T>(call: impl FnOnce() -> T) -> T {
+    return stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, call)
+}
+
+pub fn allocate() -> Result<(), ErrorVal> {
+    return BUDGET.with(|budget| budget.borrow_mut().charge(1))
+}
+
+// a list is charged once for itself and once per item, before a large one is built where possible
+pub fn allocate_list(items: usize) -> Result<(), ErrorVal> {
+    return BUDGET.with(|budget| budget.borrow_mut().charge(1 + items as u64))
+}
+
+// for a list that grows one item at a time
+pub fn allocate_items(items: usize) -> Result<(), ErrorVal> {
+    return BUDGET.with(|budget| budget.borrow_mut().charge(items as u64))
+}
+
+pub fn allocate_string(bytes: usize) -> Result<(), ErrorVal> {
+    return BUDGET.with(|budget| budget.borrow_mut().charge((bytes / STRING_CHUNK) as u64))
+}
+
+pub fn io_allowed() -> bool {
+    return BUDGET.with(|budget| budget.borrow().limits.allow_io)
+}
+
+// guards every builtin that touches the outside world
+pub fn require_io(action: &str) -> Result<(), ErrorVal> {
+    if !io_allowed() {
+        return Err(ErrorVal::make_error("PermissionError", format!("Cannot {}: I/O is disabled", action)))
+    }
+    return Ok(())
+}
@@ -0,0 +1,337 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::{call_function, values_equal, EvalResult, Interrupt};
+use crate::limits;
+use crate::time::compare_times;
+use crate::values::{ErrorVal, ListVal, NullVal, RuntimeVal};
+
+pub fn list_result(items: Vec<RuntimeVal>) -> EvalResult {
+    limits::allocate_list(items.len())?;
+    return Ok(ListVal::make_list(items).to_runtime_val())
+}
+
+fn value_error(message: String) -> Interrupt {
+    return ErrorVal::make_error("ValueError", message).into()
+}
+
+// a predicate has to answer with a boolean; there is no truthiness to fall back on
+pub fn predicate_holds(method: &str, predicate: &RuntimeVal, item: RuntimeVal, env: &Rc<RefCell<Environment>>) -> Result<bool, Interrupt> {
+    let answer = call_function(predicate.clone(), vec![item], env)?;
+    return answer.get_bool_val().map_err(|_| {
+        ErrorVal::make_error(
+            "TypeError",
+            format!("The function given to {} must return a Boolean, not {:?}", method, answer.get_value_type())
+        ).into()
+    })
+}
+
+fn size(method: &str, argument: &RuntimeVal) -> Result<usize, Interrupt> {
+    let size = argument.get_number_value()?;
+    match usize::try_from(size) {
+        Ok(size) if size > 0 => return Ok(size),
+        _ => return Err(value_error(format!("{} needs a size of at least 1, not {}", method, size))),
+    }
+}
+
+// numbers compare by value, strings by code point, booleans false first, and lists element by element
+pub fn compare_values(lhs: &RuntimeVal, rhs: &RuntimeVal) -> Result<Ordering, ErrorVal> {
+    return compare_nested(lhs, rhs, &mut Vec::new())
+}
+
+// `comparing` holds the pairs of lists being compared further up; as with equality, meeting one
+// again means both sides went round a cycle together, so the remaining items decide
+fn compare_nested(lhs: &RuntimeVal, rhs: &RuntimeVal, comparing: &mut Vec<(*const (), *const ())>) -> Result<Ordering, ErrorVal> {
+    match (lhs, rhs) {
+        (RuntimeVal::NumberVal(lhs), RuntimeVal::NumberVal(rhs)) => return Ok(lhs.value.cmp(&rhs.value)),
+        (RuntimeVal::NumberVal(_) | RuntimeVal::FloatVal(_), RuntimeVal::NumberVal(_) | RuntimeVal::FloatVal(_)) => {
+            return lhs.get_float_value()?.partial_cmp(&rhs.get_float_value()?).ok_or_else(|| {
+                ErrorVal::make_error("ValueError", String::from("NaN cannot be ordered"))
+            })
+        },
+        (RuntimeVal::StringVal(lhs), RuntimeVal::StringVal(rhs)) => return Ok(lhs.value.cmp(&rhs.value)),
+        (RuntimeVal::BooleanVal(lhs), RuntimeVal::BooleanVal(rhs)) => return Ok(lhs.value.cmp(&rhs.value)),
+        (RuntimeVal::ListVal(lhs), RuntimeVal::ListVal(rhs)) => {
+            let pair = (Rc::as_ptr(&lhs.items) as *const (), Rc::as_ptr(&rhs.items) as *const ());
+            if Rc::ptr_eq(&lhs.items, &rhs.items) || comparing.contains(&pair) {
+                return Ok(Ordering::Equal)
+            }
+            comparing.push(pair);
+            let (lhs, rhs) = (lhs.items.borrow(), rhs.items.borrow());
+            for (left, right) in lhs.iter().zip(rhs.iter()) {
+                let ordering = compare_nested(left, right, comparing)?;
+                if ordering != Ordering::Equal {
+                    comparing.pop();
+                    return Ok(ordering)
+                }
+            }
+            comparing.pop();
+            return Ok(lhs.len().cmp(&rhs.len()))
+        },
+        (RuntimeVal::DurationVal(_), RuntimeVal::DurationVal(_))
+        | (RuntimeVal::DateTimeVal(_), RuntimeVal::DateTimeVal(_))
+        | (RuntimeVal::InstantVal(_), RuntimeVal::InstantVal(_)) => return Ok(compare_times(lhs, rhs).unwrap()),
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Cannot order {:?} and {:?}", lhs.get_value_type(), rhs.get_value_type())
+            ))
+        },
+    }
+}
+
+// where `wanted` first appears, ticking once per comparison since the search is quadratic overall
+fn position_of<'a>(values: impl Iterator<Item = &'a RuntimeVal>, wanted: &RuntimeVal) -> Result<Option<usize>, ErrorVal> {
+    for (index, value) in values.enumerate() {
+        limits::tick()?;
+        if values_equal(value, wanted) {
+            return Ok(Some(index))
+        }
+    }
+    return Ok(None)
+}
+
+// a stable sort of `items` by `keys`, stopping at the first pair that cannot be ordered
+fn sort_by_keys(items: Vec<RuntimeVal>, keys: Vec<RuntimeVal>) -> Result<Vec<RuntimeVal>, ErrorVal> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    merge_sort(&mut order, &keys)?;
+    return Ok(order.into_iter().map(|index| items[index].clone()).collect())
+}
+
+// slice::sort_by needs a total order, which a comparison that can fail part way through cannot
+// promise, so this merge sort returns the failure as soon as it happens instead
+fn merge_sort(order: &mut [usize], keys: &[RuntimeVal]) -> Result<(), ErrorVal> {
+    if order.len() < 2 {
+        return Ok(())
+    }
+    let middle = order.len() / 2;
+    merge_sort(&mut order[..middle], keys)?;
+    merge_sort(&mut order[middle..], keys)?;
+    let (left, right) = (order[..middle].to_vec(), order[middle..].to_vec());
+    let (mut next_left, mut next_right) = (0, 0);
+    for slot in order.iter_mut() {
+        // ties go to the left half, which keeps equal keys in their original order
+        let from_left = if next_left == left.len() {
+            false
+        } else if next_right == right.len() {
+            true
+        } else {
+            limits::tick()?;
+            compare_values(&keys[left[next_left]], &keys[right[next_right]])? != Ordering::Greater
+        };
+        if from_left {
+            *slot = left[next_left];
+            next_left += 1;
+        } else {
+            *slot = right[next_right];
+            next_right += 1;
+        }
+    }
+    return Ok(())
+}
+
+// apart from push and pop, methods leave the list alone and return a new one, so they chain;
+// callbacks see a snapshot, so changing the list from inside one does not disturb the walk;
+// every loop ticks once per item, so a long list cannot outrun the fuel or the timeout
+pub fn call_list_method(list: ListVal, method: &str, mut arguments: Vec<RuntimeVal>, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    match method {
+        "len" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(RuntimeVal::from(list.items.borrow().len() as i64))
+        },
+        "push" => {
+            expect_args(method, &arguments, 1)?;
+            limits::allocate_items(1)?;
+            list.items.borrow_mut().push(arguments.remove(0));
+            return Ok(NullVal::make_null().to_runtime_val())
+        },
+        // the removed item, or null when the list is empty
+        "pop" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(RuntimeVal::from(list.items.borrow_mut().pop()))
+        },
+        _ => {},
+    }
+    let items = list.items.borrow().clone();
+    match method {
+        "map" => {
+            expect_args(method, &arguments, 1)?;
+            let mut mapped = Vec::new();
+            for item in items {
+                limits::tick()?;
+                mapped.push(call_function(arguments[0].clone(), vec![item], env)?);
+            }
+            return list_result(mapped)
+        },
+        "filter" => {
+            expect_args(method, &arguments, 1)?;
+            let mut kept = Vec::new();
+            for item in items {
+                limits::tick()?;
+                if predicate_holds(method, &arguments[0], item.clone(), env)? {
+                    kept.push(item);
+                }
+            }
+            return list_result(kept)
+        },
+        // folds from the left, starting from the first item when no initial value is given
+        "reduce" => {
+            if arguments.is_empty() || arguments.len() > 2 {
+                return Err(ErrorVal::make_error(
+                    "TypeError",
+                    format!("reduce expects 1 or 2 argument(s) but received {}", arguments.len())
+                ).into())
+            }
+            let mut items = items.into_iter();
+            let mut accumulator = match arguments.get(1) {
+                Some(initial) => initial.clone(),
+                None => items.next().ok_or_else(|| value_error(String::from("reduce of an empty list needs an initial value")))?,
+            };
+            for item in items {
+                limits::tick()?;
+                accumulator = call_function(arguments[0].clone(), vec![accumulator, item], env)?;
+            }
+            return Ok(accumulator)
+        },
+        // the first matching item, or null
+        "find" => {
+            expect_args(method, &arguments, 1)?;
+            for item in items {
+                limits::tick()?;
+                if predicate_holds(method, &arguments[0], item.clone(), env)? {
+                    return Ok(item)
+                }
+            }
+            return Ok(NullVal::make_null().to_runtime_val())
+        },
+        "any" | "all" => {
+            expect_args(method, &arguments, 1)?;
+            // both stop at the first item that settles the answer
+            let wanted = method == "any";
+            for item in items {
+                limits::tick()?;
+                if predicate_holds(method, &arguments[0], item, env)? == wanted {
+                    return Ok(RuntimeVal::from(wanted))
+                }
+            }
+            return Ok(RuntimeVal::from(!wanted))
+        },
+        "flat_map" => {
+            expect_args(method, &arguments, 1)?;
+            let mut flattened = Vec::new();
+            for item in items {
+                limits::tick()?;
+                let mapped = call_function(arguments[0].clone(), vec![item], env)?;
+                let mapped = mapped.get_list_value().map_err(|_| {
+                    ErrorVal::make_error(
+                        "TypeError",
+                        format!("The function given to flat_map must return a List, not {:?}", mapped.get_value_type())
+                    )
+                })?;
+                flattened.extend(mapped.items.borrow().iter().cloned());
+            }
+            return list_result(flattened)
+        },
+        // pairs items up to the length of the shorter list
+        "zip" => {
+            expect_args(method, &arguments, 1)?;
+            let others = arguments[0].get_list_value()?.items.borrow().clone();
+            let mut pairs = Vec::new();
+            for (item, other) in items.into_iter().zip(others) {
+                limits::tick()?;
+                pairs.push(list_result(vec![item, other])?);
+            }
+            return list_result(pairs)
+        },
+        "enumerate" => {
+            expect_args(method, &arguments, 0)?;
+            let mut pairs = Vec::new();
+            for (index, item) in items.into_iter().enumerate() {
+                limits::tick()?;
+                pairs.push(list_result(vec![RuntimeVal::from(index as i64), item])?);
+            }
+            return list_result(pairs)
+        },
+        "sort" => {
+            expect_args(method, &arguments, 0)?;
+            let keys = items.clone();
+            return list_result(sort_by_keys(items, keys)?)
+        },
+        // orders by the key the function computes for each item, keeping ties in their original order
+        "sort_by" => {
+            expect_args(method, &arguments, 1)?;
+            let mut keys = Vec::new();
+            for item in items.iter() {
+                limits::tick()?;
+                keys.push(call_function(arguments[0].clone(), vec![item.clone()], env)?);
+            }
+            return list_result(sort_by_keys(items, keys)?)
+        },
+        "reverse" => {
+            expect_args(method, &arguments, 0)?;
+            return list_result(items.into_iter().rev().collect())
+        },
+        // keeps the first of each run of equal items, wherever they are
+        "unique" => {
+            expect_args(method, &arguments, 0)?;
+            let mut seen: Vec<RuntimeVal> = Vec::new();
+            for item in items {
+                limits::tick()?;
+                if position_of(seen.iter(), &item)?.is_none() {
+                    seen.push(item);
+                }
+            }
+            return list_result(seen)
+        },
+        // there is no map type, so groups come back as [key, items] pairs in order of first appearance
+        "group_by" => {
+            expect_args(method, &arguments, 1)?;
+            let mut groups: Vec<(RuntimeVal, Vec<RuntimeVal>)> = Vec::new();
+            for item in items {
+                limits::tick()?;
+                let key = call_function(arguments[0].clone(), vec![item.clone()], env)?;
+                match position_of(groups.iter().map(|(other, _)| other), &key)? {
+                    Some(index) => groups[index].1.push(item),
+                    None => groups.push((key, vec![item])),
+                }
+            }
+            let mut pairs = Vec::new();
+            for (key, members) in groups {
+                limits::tick()?;
+                let members = list_result(members)?;
+                pairs.push(list_result(vec![key, members])?);
+            }
+            return list_result(pairs)
+        },
+        // consecutive slices of `size` items; the last chunk may be shorter
+        "chunks" => {
+            expect_args(method, &arguments, 1)?;
+            let size = size(method, &arguments[0])?;
+            let mut chunks = Vec::new();
+            for chunk in items.chunks(size) {
+                limits::tick()?;
+                chunks.push(list_result(chunk.to_vec())?);
+            }
+            return list_result(chunks)
+        },
+        // every run of `size` neighbouring items, overlapping; none when the list is shorter
+        "windows" => {
+            expect_args(method, &arguments, 1)?;
+            let size = size(method, &arguments[0])?;
+            let mut windows = Vec::new();
+            for window in items.windows(size) {
+                limits::tick()?;
+                windows.push(list_result(window.to_vec())?);
+            }
+            return list_result(windows)
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("List has no method '{}'", method)
+            ).into())
+        },
+    }
+}
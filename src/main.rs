@@ -1,37 +1,98 @@
-use std::io::{self, Write};
-use environment::Environment;
-use values::{ValueType, RuntimeVal, BooleanVal};
-use ast::Stmt;
-use interpreter::evaluate;
-pub mod lexer;
-pub mod ast;
-pub mod parser;
-pub mod values;
-pub mod interpreter;
-pub mod environment;
-fn main() {
-    let mut env = Environment::new(None);
-    env.declare_var(String::from("true"), RuntimeVal::BooleanVal(BooleanVal {
-        value_type: ValueType::Boolean,
-        value: true,
-    }), true);
-    env.declare_var(String::from("false"), RuntimeVal::BooleanVal(BooleanVal {
-        value_type: ValueType::Boolean,
-        value: false,
-    }), true);
-    loop {
-        print!(">>>");
-        io::stdout().flush().unwrap();
-        let mut input:String = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let input: String = input.trim().to_owned();
-        if input == "exit" {
-            break;
+#![allow(clippy::needless_return)]
+use shtark::{format_source, Engine, EngineError, ErrorVal};
+mod repl;
+
+fn report_uncaught(error: ErrorVal) {
+    println!("Uncaught {}: {}", error.kind, error.message);
+    // runaway recursion repeats one frame thousands of times, so repeats are counted instead
+    let mut index = 0;
+    while index < error.stack.len() {
+        let frame = &error.stack[index];
+        let repeats = error.stack[index..].iter().take_while(|other| *other == frame).count();
+        println!("    at {}", frame);
+        if repeats > 1 {
+            println!("    ... {} more at {}", repeats - 1, frame);
         }
-        if input.is_empty() {
+        index += repeats;
+    }
+}
+
+// `shtark fmt [--check] files...` rewrites each file in canonical form, or only lists the ones that differ
+fn format_files(args: Vec<String>) {
+    let check = args.iter().any(|arg| arg == "--check");
+    let mut failed = false;
+    for path in args.iter().filter(|arg| *arg != "--check") {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                println!("Cannot read {}: {}", path, error);
+                failed = true;
+                continue;
+            },
+        };
+        let formatted = match format_source(source.clone()) {
+            Ok(formatted) => formatted,
+            Err(message) => {
+                println!("Cannot format {}: {}", path, message);
+                failed = true;
+                continue;
+            },
+        };
+        if formatted == source {
             continue;
         }
-        let mut program = parser::Parser::new(input);
-        println!("{:#?}", evaluate(Stmt::Program(program.produce_ast()), &mut env));
+        if check {
+            println!("Would reformat {}", path);
+            failed = true;
+        } else if let Err(error) = std::fs::write(path, formatted) {
+            println!("Cannot write {}: {}", path, error);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    if std::env::args().nth(1).as_deref() == Some("fmt") {
+        format_files(std::env::args().skip(2).collect());
+        return
+    }
+    let mut path = None;
+    let mut use_vm = false;
+    let mut opt_level = 0;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--vm" {
+            use_vm = true;
+        } else if arg == "--opt-level" {
+            match args.next().and_then(|level| level.parse::<u8>().ok()) {
+                Some(level) if level <= 2 => opt_level = level,
+                _ => {
+                    println!("--opt-level expects 0, 1 or 2");
+                    std::process::exit(1);
+                },
+            }
+        } else {
+            path = Some(arg);
+        }
+    }
+    if let Some(path) = path {
+        let mut engine = Engine::new();
+        engine.set_use_vm(use_vm);
+        engine.set_opt_level(opt_level);
+        let result = engine.eval_file(&path);
+        for warning in engine.take_warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+        match result {
+            Ok(_) => return,
+            Err(EngineError::Exit(status)) => std::process::exit(status),
+            Err(EngineError::Runtime(error)) => report_uncaught(error),
+            Err(error) => println!("{}", error),
+        }
+        std::process::exit(1);
     }
-}
\ No newline at end of file
+    repl::run(use_vm, opt_level);
+}
@@ -0,0 +1,289 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64::consts;
+use std::rc::Rc;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::values::{ErrorVal, ModuleVal, NativeFnVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+// integer arguments give integer results wherever the answer is exact: abs, min, max, clamp, pow
+// with a non-negative exponent, floor, ceil, round, gcd, lcm and isqrt; the rest return floats
+pub fn math_module() -> ModuleVal {
+    let functions: [(&str, Native); 23] = [
+        ("abs", native_abs),
+        ("min", native_min),
+        ("max", native_max),
+        ("clamp", native_clamp),
+        ("pow", native_pow),
+        ("sqrt", native_sqrt),
+        ("isqrt", native_isqrt),
+        ("floor", native_floor),
+        ("ceil", native_ceil),
+        ("round", native_round),
+        ("exp", native_exp),
+        ("log", native_log),
+        ("log2", native_log2),
+        ("log10", native_log10),
+        ("sin", native_sin),
+        ("cos", native_cos),
+        ("tan", native_tan),
+        ("asin", native_asin),
+        ("acos", native_acos),
+        ("atan", native_atan),
+        ("atan2", native_atan2),
+        ("gcd", native_gcd),
+        ("lcm", native_lcm),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    exports.insert(String::from("PI"), RuntimeVal::from(consts::PI));
+    exports.insert(String::from("E"), RuntimeVal::from(consts::E));
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("math"),
+        exports: Rc::new(exports),
+    }
+}
+
+fn value_error(message: String) -> ErrorVal {
+    return ErrorVal::make_error("ValueError", message)
+}
+
+fn overflow_error(message: String) -> ErrorVal {
+    return ErrorVal::make_error("OverflowError", message)
+}
+
+fn float_argument(name: &str, arguments: &[RuntimeVal]) -> Result<f64, ErrorVal> {
+    expect_args(name, arguments, 1)?;
+    return arguments[0].get_float_value()
+}
+
+// the float argument of a function only defined where `defined` holds, such as sqrt or log
+fn domain_argument(name: &str, arguments: &[RuntimeVal], defined: impl Fn(f64) -> bool) -> Result<f64, ErrorVal> {
+    let value = float_argument(name, arguments)?;
+    if !defined(value) {
+        return Err(value_error(format!("math.{} is undefined for {}", name, arguments[0])))
+    }
+    return Ok(value)
+}
+
+fn to_integer(name: &str, value: f64) -> EvalResult {
+    if value.is_nan() {
+        return Err(value_error(format!("math.{} cannot turn NaN into an integer", name)).into())
+    }
+    // i64::MAX rounds up to 2^63 as a float, so this also rejects values just past it
+    if value < i64::MIN as f64 || value >= i64::MAX as f64 {
+        return Err(overflow_error(format!("math.{} result {:?} does not fit in a 64-bit integer", name, value)).into())
+    }
+    return Ok(RuntimeVal::from(value as i64))
+}
+
+// integers compare exactly, anything involving a float compares as floats
+fn compare(name: &str, lhs: &RuntimeVal, rhs: &RuntimeVal) -> Result<Ordering, ErrorVal> {
+    if let (RuntimeVal::NumberVal(lhs), RuntimeVal::NumberVal(rhs)) = (lhs, rhs) {
+        return Ok(lhs.value.cmp(&rhs.value))
+    }
+    return lhs.get_float_value()?.partial_cmp(&rhs.get_float_value()?).ok_or_else(|| {
+        value_error(format!("math.{} cannot order NaN", name))
+    })
+}
+
+// the argument `keep` prefers, returned unchanged so integers stay integers
+fn extreme(name: &str, arguments: Vec<RuntimeVal>, keep: Ordering) -> EvalResult {
+    if arguments.is_empty() {
+        return Err(ErrorVal::make_error("TypeError", format!("math.{} expects at least 1 argument", name)).into())
+    }
+    let mut best = arguments[0].clone();
+    best.get_float_value()?;
+    for argument in arguments.into_iter().skip(1) {
+        if compare(name, &argument, &best)? == keep {
+            best = argument;
+        }
+    }
+    return Ok(best)
+}
+
+fn native_abs(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("abs", &arguments, 1)?;
+    if let RuntimeVal::NumberVal(number) = &arguments[0] {
+        return number.value.checked_abs().map(RuntimeVal::from).ok_or_else(|| {
+            overflow_error(format!("math.abs({}) does not fit in a 64-bit integer", number.value)).into()
+        })
+    }
+    return Ok(RuntimeVal::from(arguments[0].get_float_value()?.abs()))
+}
+
+fn native_min(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return extreme("min", arguments, Ordering::Less)
+}
+
+fn native_max(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return extreme("max", arguments, Ordering::Greater)
+}
+
+fn native_clamp(mut arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("clamp", &arguments, 3)?;
+    let high = arguments.pop().unwrap();
+    let low = arguments.pop().unwrap();
+    let value = arguments.pop().unwrap();
+    if compare("clamp", &low, &high)? == Ordering::Greater {
+        return Err(value_error(format!("math.clamp lower bound {} is above upper bound {}", low, high)).into())
+    }
+    if compare("clamp", &value, &low)? == Ordering::Less {
+        return Ok(low)
+    }
+    if compare("clamp", &value, &high)? == Ordering::Greater {
+        return Ok(high)
+    }
+    return Ok(value)
+}
+
+fn native_pow(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("pow", &arguments, 2)?;
+    if let (RuntimeVal::NumberVal(base), RuntimeVal::NumberVal(exponent)) = (&arguments[0], &arguments[1]) {
+        if exponent.value >= 0 {
+            let result = match u32::try_from(exponent.value) {
+                Ok(exponent) => base.value.checked_pow(exponent),
+                // only 0, 1 and -1 survive an exponent this large
+                Err(_) if base.value == 0 || base.value == 1 => Some(base.value),
+                Err(_) if base.value == -1 => Some(if exponent.value % 2 == 0 { 1 } else { -1 }),
+                Err(_) => None,
+            };
+            return result.map(RuntimeVal::from).ok_or_else(|| {
+                overflow_error(format!("math.pow({}, {}) does not fit in a 64-bit integer", base.value, exponent.value)).into()
+            })
+        }
+    }
+    return Ok(RuntimeVal::from(arguments[0].get_float_value()?.powf(arguments[1].get_float_value()?)))
+}
+
+fn native_sqrt(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(domain_argument("sqrt", &arguments, |x| x >= 0.0)?.sqrt()))
+}
+
+fn native_isqrt(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("isqrt", &arguments, 1)?;
+    let value = arguments[0].get_number_value()?;
+    if value < 0 {
+        return Err(value_error(format!("math.isqrt is undefined for {}", value)).into())
+    }
+    // the float root is close; step it onto the exact floor without overflowing near i64::MAX
+    let mut root = (value as f64).sqrt() as i64;
+    while root.checked_mul(root).is_none_or(|square| square > value) {
+        root -= 1;
+    }
+    while (root + 1).checked_mul(root + 1).is_some_and(|square| square <= value) {
+        root += 1;
+    }
+    return Ok(RuntimeVal::from(root))
+}
+
+fn native_floor(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = float_argument("floor", &arguments)?;
+    if let RuntimeVal::NumberVal(_) = arguments[0] {
+        return Ok(arguments[0].clone())
+    }
+    return to_integer("floor", value.floor())
+}
+
+fn native_ceil(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = float_argument("ceil", &arguments)?;
+    if let RuntimeVal::NumberVal(_) = arguments[0] {
+        return Ok(arguments[0].clone())
+    }
+    return to_integer("ceil", value.ceil())
+}
+
+// halves round away from zero, so round(2.5) is 3 and round(-2.5) is -3
+fn native_round(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let value = float_argument("round", &arguments)?;
+    if let RuntimeVal::NumberVal(_) = arguments[0] {
+        return Ok(arguments[0].clone())
+    }
+    return to_integer("round", value.round())
+}
+
+fn native_exp(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(float_argument("exp", &arguments)?.exp()))
+}
+
+// the natural logarithm, or the logarithm in the base given as a second argument
+fn native_log(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    if arguments.len() == 2 {
+        let base = domain_argument("log", &arguments[1..], |base| base > 0.0 && base != 1.0)?;
+        let value = domain_argument("log", &arguments[..1], |x| x > 0.0)?;
+        return Ok(RuntimeVal::from(value.log(base)))
+    }
+    return Ok(RuntimeVal::from(domain_argument("log", &arguments, |x| x > 0.0)?.ln()))
+}
+
+fn native_log2(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(domain_argument("log2", &arguments, |x| x > 0.0)?.log2()))
+}
+
+fn native_log10(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(domain_argument("log10", &arguments, |x| x > 0.0)?.log10()))
+}
+
+fn native_sin(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(float_argument("sin", &arguments)?.sin()))
+}
+
+fn native_cos(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(float_argument("cos", &arguments)?.cos()))
+}
+
+fn native_tan(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(float_argument("tan", &arguments)?.tan()))
+}
+
+fn native_asin(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(domain_argument("asin", &arguments, |x| (-1.0..=1.0).contains(&x))?.asin()))
+}
+
+fn native_acos(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(domain_argument("acos", &arguments, |x| (-1.0..=1.0).contains(&x))?.acos()))
+}
+
+fn native_atan(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return Ok(RuntimeVal::from(float_argument("atan", &arguments)?.atan()))
+}
+
+fn native_atan2(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("atan2", &arguments, 2)?;
+    return Ok(RuntimeVal::from(arguments[0].get_float_value()?.atan2(arguments[1].get_float_value()?)))
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    return a
+}
+
+// both are integer-only and never negative; gcd(0, 0) and lcm with a zero are 0
+fn native_gcd(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("gcd", &arguments, 2)?;
+    let (a, b) = (arguments[0].get_number_value()?, arguments[1].get_number_value()?);
+    return i64::try_from(gcd(a.unsigned_abs(), b.unsigned_abs())).map(RuntimeVal::from).map_err(|_| {
+        overflow_error(format!("math.gcd({}, {}) does not fit in a 64-bit integer", a, b)).into()
+    })
+}
+
+fn native_lcm(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("lcm", &arguments, 2)?;
+    let (a, b) = (arguments[0].get_number_value()?, arguments[1].get_number_value()?);
+    if a == 0 || b == 0 {
+        return Ok(RuntimeVal::from(0))
+    }
+    let lcm = (a.unsigned_abs() / gcd(a.unsigned_abs(), b.unsigned_abs())).checked_mul(b.unsigned_abs());
+    return lcm.and_then(|lcm| i64::try_from(lcm).ok()).map(RuntimeVal::from).ok_or_else(|| {
+        overflow_error(format!("math.lcm({}, {}) does not fit in a 64-bit integer", a, b)).into()
+    })
+}
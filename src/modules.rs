@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::ast::{Program, Stmt};
+use crate::builtins::declare_builtins;
+use crate::environment::Environment;
+use crate::interpreter::{evaluate, EvalResult, Interrupt};
+use crate::fs::fs_module;
+use crate::json::json_module;
+use crate::limits::{io_allowed, require_io};
+use crate::math::math_module;
+use crate::optimizer::optimize;
+use crate::path::path_module;
+use crate::parser::parse_source;
+use crate::random::{random_module, Generator};
+use crate::resolver::resolve;
+use crate::time::time_module;
+use crate::values::{ErrorVal, ModuleVal, RuntimeVal, ValueType};
+use crate::vm;
+
+#[derive(Debug, Default)]
+struct ModuleRegistry {
+    cache: HashMap<PathBuf, ModuleVal>,
+    loading: Vec<PathBuf>, // modules currently being evaluated, outermost first
+}
+
+// what an Engine keeps besides its globals: the modules it has imported, how it runs programs
+// and its random number generator
+#[derive(Debug, Default)]
+pub struct Session {
+    modules: ModuleRegistry,
+    pub use_vm: bool, // applies to imported modules too
+    pub opt_level: u8,
+    pub warnings: Vec<String>, // from imported modules, for the engine to hand on
+    pub random: Generator, // what the random module draws from
+}
+
+thread_local! {
+    static SESSION: RefCell<Rc<RefCell<Session>>> = RefCell::new(Rc::new(RefCell::new(Session::default())));
+}
+
+// makes `session` the one imports and runs go through until it is restored, like limits::install
+pub fn install(session: Rc<RefCell<Session>>) -> Rc<RefCell<Session>> {
+    return SESSION.with(|current| current.replace(session))
+}
+
+pub fn restore(previous: Rc<RefCell<Session>>) {
+    SESSION.with(|current| current.replace(previous));
+}
+
+pub fn with_session<T>(action: impl FnOnce(&mut Session) -> T) -> T {
+    let session = SESSION.with(|current| Rc::clone(&current.borrow()));
+    let result = action(&mut session.borrow_mut());
+    return result
+}
+
+pub fn run_program(mut program: Program, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let (use_vm, opt_level) = with_session(|session| (session.use_vm, session.opt_level));
+    optimize(&mut program, opt_level);
+    let declarations = resolve(&mut program, env)?;
+    let result = match use_vm {
+        true => vm::run_program(program, env),
+        false => evaluate(&Stmt::Program(program), env),
+    };
+    env.borrow_mut().reserve(declarations);
+    return result
+}
+
+// runs a program read from `path` so that its relative imports resolve against that file
+pub fn run_file(path: &Path, program: Program, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    with_session(|session| session.modules.loading.push(path));
+    let result = run_program(program, env);
+    with_session(|session| session.modules.loading.pop());
+    return result
+}
+
+fn import_error(message: String) -> Interrupt {
+    return ErrorVal::make_error("ImportError", message).into()
+}
+
+// specifiers are relative to the importing module, or the working directory at the top level
+fn resolve_path(specifier: &str) -> Result<PathBuf, Interrupt> {
+    let importer = with_session(|session| session.modules.loading.last().cloned());
+    let base = match importer {
+        Some(ref path) => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => PathBuf::from("."),
+    };
+    return base.join(specifier).canonicalize().map_err(|error| {
+        import_error(format!("Cannot find module '{}': {}", specifier, error))
+    })
+}
+
+// the standard library, importable by bare name; fs and path only exist while I/O is allowed
+fn builtin_module(specifier: &str) -> Option<ModuleVal> {
+    match specifier {
+        "math" => return Some(math_module()),
+        "json" => return Some(json_module()),
+        "time" => return Some(time_module()),
+        "random" => return Some(random_module()),
+        "fs" if io_allowed() => return Some(fs_module()),
+        "path" if io_allowed() => return Some(path_module()),
+        _ => return None,
+    }
+}
+
+pub fn load_module(specifier: &str) -> Result<ModuleVal, Interrupt> {
+    if let Some(module) = builtin_module(specifier) {
+        return Ok(module)
+    }
+    require_io(&format!("import '{}'", specifier))?;
+    let path = resolve_path(specifier)?;
+    if let Some(module) = with_session(|session| session.modules.cache.get(&path).cloned()) {
+        return Ok(module)
+    }
+    let cycle = with_session(|session| {
+        let modules = &session.modules;
+        modules.loading.iter().position(|loading| *loading == path).map(|start| {
+            let mut chain: Vec<String> = modules.loading[start..].iter().map(|loading| loading.display().to_string()).collect();
+            chain.push(path.display().to_string());
+            chain.join(" -> ")
+        })
+    });
+    if let Some(chain) = cycle {
+        return Err(import_error(format!("Cyclic import detected: {}", chain)))
+    }
+    let source = fs::read_to_string(&path).map_err(|error| {
+        import_error(format!("Cannot read module '{}': {}", path.display(), error))
+    })?;
+    with_session(|session| session.modules.loading.push(path.clone()));
+    let result = eval_module(&path, source);
+    with_session(|session| session.modules.loading.pop());
+    let module = result?;
+    with_session(|session| session.modules.cache.insert(path, module.clone()));
+    return Ok(module)
+}
+
+fn eval_module(path: &Path, source: String) -> Result<ModuleVal, Interrupt> {
+    let (program, warnings) = parse_source(&source).map_err(|error| {
+        Interrupt::from(ErrorVal::make_error("SyntaxError", format!("{}: {}", path.display(), error.message)))
+    })?;
+    with_session(|session| {
+        session.warnings.extend(warnings.into_iter().map(|warning| format!("{}: {}", path.display(), warning)));
+    });
+    let exported_names: Vec<String> = program.body.iter().filter_map(|statement| match statement {
+        Stmt::ExportDeclaration(export) => Some(export.exported_name()),
+        _ => None,
+    }).collect();
+    // builtins sit in a parent scope, as they do for an Engine's globals, so a module may shadow them
+    let builtins = Rc::new(RefCell::new(Environment::global(None)));
+    declare_builtins(&builtins, io_allowed());
+    let env = Rc::new(RefCell::new(Environment::global(Some(builtins))));
+    run_program(program, &env)?;
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for name in exported_names {
+        let value = env.borrow().lookup_var(name.clone())?;
+        exports.insert(name, value);
+    }
+    return Ok(ModuleVal {
+        value_type: ValueType::Module,
+        path: path.display().to_string(),
+        exports: Rc::new(exports),
+    })
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use crate::ast::{BinaryOperator, Expr, FloatLiteral, NodeType, NumericLiteral, Pattern, Program, Stmt};
+use crate::interpreter::apply_binary_operator;
+use crate::limits::grow_stack;
+use crate::values::RuntimeVal;
+
+// level 1 folds constant arithmetic and drops dead code, level 2 also propagates `const` literals
+pub fn optimize(program: &mut Program, level: u8) {
+    if level == 0 {
+        return
+    }
+    let mut optimizer = Optimizer {
+        propagate: level >= 2,
+        scopes: vec![HashMap::new()],
+    };
+    optimizer.optimize_body(&mut program.body);
+}
+
+struct Optimizer {
+    propagate: bool,
+    // every name declared in each open scope, with its value when it is a literal `const`
+    scopes: Vec<HashMap<String, Option<Expr>>>,
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    return matches!(expr, Expr::NumericLiteral(_) | Expr::FloatLiteral(_) | Expr::StringLiteral(_) | Expr::NullLiteral(_))
+}
+
+fn numeric_value(expr: &Expr) -> Option<RuntimeVal> {
+    match expr {
+        Expr::NumericLiteral(literal) => return Some(RuntimeVal::from(literal.value)),
+        Expr::FloatLiteral(literal) => return Some(RuntimeVal::from(literal.value)),
+        _ => return None,
+    }
+}
+
+// runs the interpreter's own arithmetic, so a folded result is exactly what evaluating the
+// expression would give; anything that would raise an error is left for run time
+fn fold_numeric(lhs: &Expr, rhs: &Expr, operator: BinaryOperator) -> Option<Expr> {
+    if !matches!(operator, BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder) {
+        return None
+    }
+    match apply_binary_operator(numeric_value(lhs)?, numeric_value(rhs)?, operator).ok()? {
+        RuntimeVal::NumberVal(number) => return Some(Expr::NumericLiteral(NumericLiteral {
+            kind: NodeType::NumericLiteral,
+            value: number.value,
+            raw: None,
+        })),
+        RuntimeVal::FloatVal(float) => return Some(Expr::FloatLiteral(FloatLiteral {
+            kind: NodeType::FloatLiteral,
+            value: float.value,
+            raw: format!("{:?}", float.value),
+        })),
+        _ => return None,
+    }
+}
+
+fn declared_names(statement: &Stmt) -> Vec<String> {
+    match statement {
+        Stmt::VarDeclaration(declaration) => return vec![declaration.identifier.clone()],
+        Stmt::FunctionDeclaration(declaration) => return vec![declaration.name.clone()],
+        Stmt::ClassDeclaration(declaration) => return vec![declaration.name.clone()],
+        Stmt::EnumDeclaration(declaration) => return vec![declaration.name.clone()],
+        Stmt::ImportDeclaration(declaration) => {
+            let mut names: Vec<String> = declaration.specifiers.iter().map(|(_, local)| local.clone()).collect();
+            names.extend(declaration.namespace.iter().cloned());
+            return names
+        },
+        Stmt::ExportDeclaration(declaration) => return declared_names(&declaration.declaration),
+        _ => return Vec::new(),
+    }
+}
+
+fn pattern_bindings(pattern: &Pattern, bindings: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) => bindings.push(name.clone()),
+        Pattern::Variant { fields, .. } => {
+            for field in fields {
+                pattern_bindings(field, bindings);
+            }
+        },
+        Pattern::Wildcard | Pattern::Literal(_) => {},
+    }
+}
+
+impl Optimizer {
+    fn lookup(&self, name: &str) -> Option<Expr> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(value) = scope.get(name) {
+                return value.clone()
+            }
+        }
+        return None
+    }
+
+    fn declare(&mut self, name: String, value: Option<Expr>) {
+        self.scopes.last_mut().unwrap().insert(name, value);
+    }
+
+    fn optimize_body(&mut self, body: &mut Vec<Stmt>) {
+        // names declared later in the scope already shadow outer constants for closures
+        for statement in body.iter() {
+            for name in declared_names(statement) {
+                self.declare(name, None);
+            }
+        }
+        if let Some(end) = body.iter().position(|statement| matches!(statement, Stmt::ReturnStmt(_) | Stmt::ThrowStmt(_))) {
+            body.truncate(end + 1);
+        }
+        for statement in body.iter_mut() {
+            self.optimize_stmt(statement);
+        }
+        // a literal whose value is discarded does nothing
+        let last = body.len().saturating_sub(1);
+        let mut index = 0;
+        body.retain(|statement| {
+            index += 1;
+            return index - 1 == last || !matches!(statement, Stmt::Expr(expr) if is_literal(expr))
+        });
+    }
+
+    fn optimize_scoped_body(&mut self, body: &mut Vec<Stmt>, bindings: Vec<String>) {
+        self.scopes.push(bindings.into_iter().map(|name| (name, None)).collect());
+        self.optimize_body(body);
+        self.scopes.pop();
+    }
+
+    fn optimize_stmt(&mut self, statement: &mut Stmt) {
+        grow_stack(|| self.optimize_stmt_node(statement))
+    }
+
+    fn optimize_stmt_node(&mut self, statement: &mut Stmt) {
+        match statement {
+            Stmt::Program(program) => self.optimize_body(&mut program.body),
+            Stmt::VarDeclaration(declaration) => {
+                if let Some(ref mut value) = declaration.value {
+                    self.optimize_expr(value);
+                    if declaration.constant && self.propagate && is_literal(value) {
+                        self.declare(declaration.identifier.clone(), Some(value.clone()));
+                    }
+                }
+            },
+            Stmt::FunctionDeclaration(declaration) => {
+                self.optimize_scoped_body(&mut declaration.body, declaration.parameters.clone());
+            },
+            Stmt::ClassDeclaration(declaration) => {
+                for method in declaration.methods.iter_mut().chain(declaration.static_methods.iter_mut()) {
+                    let mut bindings = vec![String::from("this"), String::from("super")];
+                    bindings.extend(method.parameters.iter().cloned());
+                    self.optimize_scoped_body(&mut method.body, bindings);
+                }
+            },
+            Stmt::EnumDeclaration(_) | Stmt::ImportDeclaration(_) | Stmt::Comment(_) => {},
+            Stmt::ExportDeclaration(declaration) => self.optimize_stmt(&mut declaration.declaration),
+            Stmt::ReturnStmt(stmt) => {
+                if let Some(ref mut argument) = stmt.argument {
+                    self.optimize_expr(argument);
+                }
+            },
+            Stmt::ThrowStmt(stmt) => self.optimize_expr(&mut stmt.argument),
+            Stmt::TryStmt(stmt) => {
+                self.optimize_scoped_body(&mut stmt.block, Vec::new());
+                if let Some(ref mut handler) = stmt.handler {
+                    self.optimize_scoped_body(handler, stmt.param.iter().cloned().collect());
+                }
+                if let Some(ref mut finalizer) = stmt.finalizer {
+                    self.optimize_scoped_body(finalizer, Vec::new());
+                }
+            },
+            Stmt::ForStmt(stmt) => {
+                self.optimize_expr(&mut stmt.iterable);
+                self.optimize_scoped_body(&mut stmt.body, vec![stmt.variable.clone()]);
+            },
+            Stmt::YieldStmt(stmt) => self.optimize_expr(&mut stmt.argument),
+            Stmt::Expr(expr) => self.optimize_expr(expr),
+        }
+    }
+
+    fn optimize_expr(&mut self, expr: &mut Expr) {
+        grow_stack(|| self.optimize_expr_node(expr))
+    }
+
+    fn optimize_expr_node(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::NumericLiteral(_) | Expr::FloatLiteral(_) | Expr::NullLiteral(_) | Expr::StringLiteral(_) | Expr::CaptureExpr(_) => {},
+            Expr::Identifier(identifier) => {
+                if self.propagate {
+                    if let Some(value) = self.lookup(&identifier.symbol) {
+                        *expr = value;
+                    }
+                }
+            },
+            Expr::BinaryExpr(binary_expr) => {
+                self.optimize_expr(&mut binary_expr.left);
+                self.optimize_expr(&mut binary_expr.right);
+                if let Some(folded) = fold_numeric(&binary_expr.left, &binary_expr.right, binary_expr.operator) {
+                    *expr = folded;
+                }
+            },
+            Expr::AssignmentExpr(assignment_expr) => {
+                if !matches!(assignment_expr.assigne, Expr::Identifier(_)) {
+                    self.optimize_expr(&mut assignment_expr.assigne);
+                }
+                self.optimize_expr(&mut assignment_expr.value);
+            },
+            Expr::MemberExpr(member_expr) => self.optimize_expr(&mut member_expr.object),
+            Expr::IndexExpr(index_expr) => {
+                self.optimize_expr(&mut index_expr.object);
+                self.optimize_expr(&mut index_expr.index);
+            },
+            Expr::ListExpr(list_expr) => {
+                for element in list_expr.elements.iter_mut() {
+                    self.optimize_expr(element);
+                }
+            },
+            Expr::CallExpr(call_expr) => {
+                self.optimize_expr(&mut call_expr.callee);
+                for argument in call_expr.arguments.iter_mut() {
+                    self.optimize_expr(argument);
+                }
+            },
+            Expr::NewExpr(new_expr) => {
+                self.optimize_expr(&mut new_expr.callee);
+                for argument in new_expr.arguments.iter_mut() {
+                    self.optimize_expr(argument);
+                }
+            },
+            Expr::FunctionExpr(function_expr) => {
+                self.optimize_scoped_body(&mut function_expr.body, function_expr.parameters.clone());
+            },
+            Expr::PropagateExpr(propagate_expr) => self.optimize_expr(&mut propagate_expr.argument),
+            Expr::MatchExpr(match_expr) => {
+                self.optimize_expr(&mut match_expr.subject);
+                for arm in match_expr.arms.iter_mut() {
+                    let mut bindings = Vec::new();
+                    pattern_bindings(&arm.pattern, &mut bindings);
+                    self.optimize_scoped_body(&mut arm.body, bindings);
+                }
+            },
+        }
+    }
+}
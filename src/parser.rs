@@ -1,17 +1,98 @@
-use crate::ast::{AssignmentExpr, BinaryExpr, Expr, Identifier, NodeType, NullLiteral, NumericLiteral, Program, Stmt, VarDeclaration};
-use crate::lexer::{tokenize, Token, TokenType};
+use std::collections::HashMap;
+use std::fmt;
+use crate::ast::{AssignmentExpr, BinaryExpr, BinaryOperator, CallExpr, CaptureExpr, ClassDeclaration, Comment, EnumDeclaration, EnumVariant, ExportDeclaration, Expr, FloatLiteral, ForStmt, FunctionDeclaration, FunctionExpr, Identifier, ImportDeclaration, IndexExpr, ListExpr, MatchArm, MatchExpr, MemberExpr, NewExpr, NodeType, NullLiteral, NumericLiteral, Pattern, Program, PropagateExpr, ReturnStmt, Stmt, StringLiteral, ThrowStmt, TryStmt, VarDeclaration, YieldStmt};
+use crate::limits::grow_stack;
+use crate::lexer::{parse_float, parse_number, tokenize, LexError, Token, TokenType};
 use crate::values::Value;
 
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub message: String,
+    pub incomplete: bool, // the input stopped early; more text could still make it valid
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+impl From<LexError> for SyntaxError {
+    fn from(error: LexError) -> Self {
+        return SyntaxError { message: error.message, incomplete: error.unterminated }
+    }
+}
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
+// deeper nesting is refused, since every pass after the parser walks the tree recursively
+const MAX_DEPTH: usize = 512;
+
+// parses `source`, returning the program and its warnings
+pub fn parse_source(source: &str) -> Result<(Program, Vec<String>), SyntaxError> {
+    let mut parser = Parser::new(String::from(source))?;
+    let program = parser.produce_ast()?;
+    return Ok((program, parser.warnings))
+}
+
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    enums: HashMap<String, Vec<String>>, // enums declared so far, for match exhaustiveness
+    pub warnings: Vec<String>,
+    comments: HashMap<usize, Vec<Token>>, // comment tokens, keyed by the index of the token they precede
+    keep_comments: bool,
+    doc: Option<String>, // doc comment waiting for the declaration about to be parsed
+    yields: Vec<bool>, // one entry per function being parsed, innermost last: has its body yielded yet
+    depth: usize, // how deeply the node being parsed is nested
 }
 
 impl Parser {
-    pub fn new(source_code: String) -> Self {
-        let tokens = tokenize(source_code);
-        Parser { tokens, current: 0 }
+    pub fn new(source_code: String) -> ParseResult<Self> {
+        return Ok(Parser::from_tokens(tokenize(source_code)?))
+    }
+
+    pub fn from_tokens(all_tokens: Vec<Token>) -> Self {
+        let mut tokens = Vec::new();
+        let mut comments = HashMap::new();
+        let mut pending = Vec::new();
+        for token in all_tokens {
+            match token.ttype {
+                TokenType::Comment | TokenType::DocComment => pending.push(token),
+                _ => {
+                    if !pending.is_empty() {
+                        comments.insert(tokens.len(), std::mem::take(&mut pending));
+                    }
+                    tokens.push(token);
+                },
+            }
+        }
+        Parser {
+            tokens,
+            current: 0,
+            enums: HashMap::new(),
+            warnings: Vec::new(),
+            comments,
+            keep_comments: false,
+            doc: None,
+            yields: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    // keeps comments between statements as Stmt::Comment so the formatter can print them back
+    pub fn with_comments(source_code: String) -> ParseResult<Self> {
+        let mut parser = Parser::new(source_code)?;
+        parser.keep_comments = true;
+        return Ok(parser)
+    }
+
+    // true once parsing has run into the end of the input, e.g. after a failure on unclosed brackets
+    pub fn reached_end(&self) -> bool {
+        return self.current + 1 >= self.tokens.len()
     }
 
     fn not_eof(&self) -> bool {
@@ -22,33 +103,202 @@ impl Parser {
         &self.tokens[self.current]
     }
 
+    fn peek(&self) -> &Token {
+        &self.tokens[(self.current + 1).min(self.tokens.len() - 1)]
+    }
+
     fn eat(&mut self) -> Token {
         self.current += 1;
         return self.tokens[self.current - 1].clone()
     }
 
-    fn expect(&mut self, ttype: TokenType, err: String) -> Token {
+    // eats the current token when it is one of `operators`
+    fn eat_operator(&mut self, operators: &[BinaryOperator]) -> Option<BinaryOperator> {
+        let operator = BinaryOperator::from_symbol(&self.at().tvalue).filter(|operator| operators.contains(operator))?;
+        self.eat();
+        return Some(operator)
+    }
+
+    fn expect(&mut self, ttype: TokenType, err: String) -> ParseResult<Token> {
+        // stays on the offending token when it fails, so reached_end can tell a truncated input
+        let prev = self.at().clone();
+        if prev.tvalue.is_empty() || prev.ttype != ttype {
+            return Err(self.error(format!("{} Found {}.", err, self.found())))
+        }
         self.current += 1;
-        let prev = self.tokens[self.current - 1].clone();
-        if prev.tvalue == "" || prev.ttype != ttype {
-            panic!("Parser Error:\n{}\nExpected: {:?}\nFound: {:?}", err, ttype, prev);
+        return Ok(prev)
+    }
+
+    fn error(&self, message: String) -> SyntaxError {
+        return SyntaxError { message, incomplete: self.reached_end() }
+    }
+
+    // called on the way into every nested expression, block or pattern, and each time an operator
+    // or suffix wraps what came before it; callers take their levels back off `depth` when done
+    fn enter(&mut self) -> ParseResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(format!("Nesting is deeper than {} levels.", MAX_DEPTH)))
+        }
+        return Ok(())
+    }
+
+    // the current token as error messages quote it
+    fn found(&self) -> String {
+        match self.at().ttype {
+            TokenType::EOF => return String::from("the end of the input"),
+            _ => return format!("'{}'", self.at().tvalue),
         }
-        return prev
     }
 
-    pub fn produce_ast(&mut self) -> Program {
+    pub fn produce_ast(&mut self) -> ParseResult<Program> {
         let mut program = Program {
             kind: NodeType::Program,
             body: Vec::new(),
         };
 
         while self.not_eof() {
-            program.body.push(self.parse_stmt());
+            let declaration_follows = self.starts_declaration();
+            self.take_comments(&mut program.body, declaration_follows);
+            program.body.push(self.parse_top_level_stmt()?);
         }
-        program
+        self.take_comments(&mut program.body, false);
+        Ok(program)
     }
 
-    fn parse_stmt(&mut self) -> Stmt {
+    fn starts_declaration(&self) -> bool {
+        match self.at().ttype {
+            TokenType::Let | TokenType::Const | TokenType::Class | TokenType::Enum | TokenType::Export => return true,
+            TokenType::Fn => return self.peek().ttype == TokenType::Identifier,
+            _ => return false,
+        }
+    }
+
+    // keeps the comments in front of the current token as statements of `body`
+    fn take_comments(&mut self, body: &mut Vec<Stmt>, declaration_follows: bool) {
+        for text in self.take_comment_lines(declaration_follows) {
+            body.push(Stmt::Comment(Comment {
+                kind: NodeType::Comment,
+                text,
+            }));
+        }
+    }
+
+    // deals with the comments in front of the current token: a closing run of `///` lines documents
+    // the declaration that follows, everything else is dropped unless the parser keeps comments
+    fn take_comment_lines(&mut self, declaration_follows: bool) -> Vec<String> {
+        let comments = self.comments.remove(&self.current).unwrap_or_default();
+        let mut docs = 0;
+        if declaration_follows {
+            docs = comments.iter().rev().take_while(|comment| comment.ttype == TokenType::DocComment).count();
+        }
+        let (plain, docs) = comments.split_at(comments.len() - docs);
+        let mut lines = Vec::new();
+        if self.keep_comments {
+            for comment in plain {
+                lines.push(match comment.ttype {
+                    TokenType::DocComment if comment.tvalue.is_empty() => String::from("///"),
+                    TokenType::DocComment => format!("/// {}", comment.tvalue),
+                    _ => comment.tvalue.clone(),
+                });
+            }
+        }
+        self.doc = None;
+        if !docs.is_empty() {
+            let docs: Vec<&str> = docs.iter().map(|doc| doc.tvalue.as_str()).collect();
+            self.doc = Some(docs.join("\n"));
+        }
+        return lines
+    }
+
+    fn parse_top_level_stmt(&mut self) -> ParseResult<Stmt> {
+        match self.at().ttype {
+            TokenType::Import => {
+                return self.parse_import_declaration()
+            },
+            TokenType::Export => {
+                self.eat();
+                let declaration = match self.at().ttype {
+                    TokenType::Let | TokenType::Const | TokenType::Fn | TokenType::Class | TokenType::Enum => self.parse_stmt()?,
+                    _ => return Err(self.error(format!("Expected a declaration following export keyword, found {}.", self.found()))),
+                };
+                return Ok(Stmt::ExportDeclaration(ExportDeclaration {
+                    kind: NodeType::ExportDeclaration,
+                    declaration: Box::new(declaration),
+                }))
+            },
+            _ => {
+                return self.parse_stmt()
+            }
+        }
+    }
+
+    fn parse_import_declaration(&mut self) -> ParseResult<Stmt> {
+        self.eat();
+        let mut specifiers = Vec::new();
+        let mut namespace = None;
+        if self.at().tvalue == "*" {
+            self.eat();
+            self.expect(
+                TokenType::As,
+                String::from("Expected 'as' following '*' in import declaration.")
+            )?;
+            namespace = Some(self.expect(
+                TokenType::Identifier,
+                String::from("Expected namespace name following 'as'.")
+            )?.tvalue);
+        } else {
+            self.expect(
+                TokenType::OpenBrace,
+                String::from("Expected '{' or '*' following import keyword.")
+            )?;
+            while self.not_eof() && self.at().ttype != TokenType::CloseBrace {
+                let imported = self.expect(
+                    TokenType::Identifier,
+                    String::from("Expected name inside import list.")
+                )?.tvalue;
+                let mut local = imported.clone();
+                if self.at().ttype == TokenType::As {
+                    self.eat();
+                    local = self.expect(
+                        TokenType::Identifier,
+                        String::from("Expected local name following 'as'.")
+                    )?.tvalue;
+                }
+                specifiers.push((imported, local));
+                if self.at().ttype != TokenType::CloseBrace {
+                    self.expect(
+                        TokenType::Comma,
+                        String::from("Expected ',' between imported names.")
+                    )?;
+                }
+            }
+            self.expect(
+                TokenType::CloseBrace,
+                String::from("Expected '}' to close import list.")
+            )?;
+        }
+        self.expect(
+            TokenType::From,
+            String::from("Expected 'from' in import declaration.")
+        )?;
+        let source = self.expect(
+            TokenType::String,
+            String::from("Expected module path string following 'from'.")
+        )?.tvalue;
+        self.expect(
+            TokenType::Semicolon,
+            String::from("Import declaration must end with semicolon.")
+        )?;
+        return Ok(Stmt::ImportDeclaration(ImportDeclaration {
+            kind: NodeType::ImportDeclaration,
+            specifiers,
+            namespace,
+            source,
+        }))
+    }
+
+    fn parse_stmt(&mut self) -> ParseResult<Stmt> {
         match self.at().ttype {
             TokenType::Let => {
                 return self.parse_var_declaration()
@@ -56,71 +306,401 @@ impl Parser {
             TokenType::Const => {
                 return self.parse_var_declaration()
             },
+            TokenType::Fn if self.peek().ttype == TokenType::Identifier => {
+                return self.parse_fn_declaration()
+            },
+            TokenType::Class => {
+                return self.parse_class_declaration()
+            },
+            TokenType::Enum => {
+                return self.parse_enum_declaration()
+            },
+            TokenType::Import | TokenType::Export => {
+                return Err(self.error(String::from("Import and export declarations are only allowed at the top level of a module.")))
+            },
+            TokenType::Return => {
+                return self.parse_return_stmt()
+            },
+            TokenType::Throw => {
+                return self.parse_throw_stmt()
+            },
+            TokenType::Try => {
+                return self.parse_try_stmt()
+            },
+            TokenType::For => {
+                return self.parse_for_stmt()
+            },
+            TokenType::Yield => {
+                return self.parse_yield_stmt()
+            },
             _ => {
-                return Stmt::Expr(self.parse_expr())                
+                let expr = self.parse_expr()?;
+                if self.at().ttype == TokenType::Semicolon {
+                    self.eat();
+                }
+                return Ok(Stmt::Expr(expr))
             }
         }
         
     }
-    fn parse_var_declaration(&mut self) -> Stmt {
+
+    fn parse_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        self.enter()?;
+        self.expect(
+            TokenType::OpenBrace,
+            String::from("Expected '{' to open block.")
+        )?;
+        let mut body = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseBrace {
+            let declaration_follows = self.starts_declaration();
+            self.take_comments(&mut body, declaration_follows);
+            body.push(self.parse_stmt()?);
+        }
+        self.take_comments(&mut body, false);
+        self.expect(
+            TokenType::CloseBrace,
+            String::from("Expected '}' to close block.")
+        )?;
+        self.depth -= 1;
+        return Ok(body)
+    }
+
+    // a body containing `yield` makes its function a generator
+    fn parse_function_body(&mut self) -> ParseResult<(Vec<Stmt>, bool)> {
+        self.yields.push(false);
+        let body = self.parse_block()?;
+        return Ok((body, self.yields.pop().unwrap()))
+    }
+
+    fn parse_params(&mut self) -> ParseResult<Vec<String>> {
+        self.expect(
+            TokenType::OpenParen,
+            String::from("Expected '(' to open parameter list.")
+        )?;
+        let mut parameters = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseParen {
+            parameters.push(self.expect(
+                TokenType::Identifier,
+                String::from("Expected parameter name inside parameter list.")
+            )?.tvalue);
+            if self.at().ttype != TokenType::CloseParen {
+                self.expect(
+                    TokenType::Comma,
+                    String::from("Expected ',' between parameters.")
+                )?;
+            }
+        }
+        self.expect(
+            TokenType::CloseParen,
+            String::from("Expected ')' to close parameter list.")
+        )?;
+        return Ok(parameters)
+    }
+
+    fn parse_fn_declaration(&mut self) -> ParseResult<Stmt> {
+        let doc = self.doc.take();
+        self.eat();
+        let name = self.expect(
+            TokenType::Identifier,
+            String::from("Expected function name following fn keyword.")
+        )?.tvalue;
+        let parameters = self.parse_params()?;
+        let (body, generator) = self.parse_function_body()?;
+        return Ok(Stmt::FunctionDeclaration(FunctionDeclaration {
+            kind: NodeType::FunctionDeclaration,
+            name,
+            parameters,
+            body,
+            generator,
+            doc,
+            comments: Vec::new(),
+        }))
+    }
+
+    fn parse_class_declaration(&mut self) -> ParseResult<Stmt> {
+        let doc = self.doc.take();
+        self.eat();
+        let name = self.expect(
+            TokenType::Identifier,
+            String::from("Expected class name following class keyword.")
+        )?.tvalue;
+        let mut superclass = None;
+        if self.at().ttype == TokenType::Extends {
+            self.eat();
+            superclass = Some(self.expect(
+                TokenType::Identifier,
+                String::from("Expected superclass name following extends keyword.")
+            )?.tvalue);
+        }
+        self.expect(
+            TokenType::OpenBrace,
+            String::from("Expected '{' to open class body.")
+        )?;
+        let mut methods = Vec::new();
+        let mut static_methods = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseBrace {
+            let comments = self.take_comment_lines(true);
+            let method_doc = self.doc.take();
+            let is_static = self.at().ttype == TokenType::Static;
+            if is_static {
+                self.eat();
+            }
+            let method_name = self.expect(
+                TokenType::Identifier,
+                String::from("Expected method name inside class body.")
+            )?.tvalue;
+            let parameters = self.parse_params()?;
+            let (body, generator) = self.parse_function_body()?;
+            let method = FunctionDeclaration {
+                kind: NodeType::FunctionDeclaration,
+                name: method_name,
+                parameters,
+                body,
+                generator,
+                doc: method_doc,
+                comments,
+            };
+            if is_static {
+                static_methods.push(method);
+            } else {
+                methods.push(method);
+            }
+        }
+        let closing_comments = self.take_comment_lines(false);
+        self.expect(
+            TokenType::CloseBrace,
+            String::from("Expected '}' to close class body.")
+        )?;
+        return Ok(Stmt::ClassDeclaration(ClassDeclaration {
+            kind: NodeType::ClassDeclaration,
+            name,
+            superclass,
+            methods,
+            static_methods,
+            doc,
+            closing_comments,
+        }))
+    }
+
+    fn parse_enum_declaration(&mut self) -> ParseResult<Stmt> {
+        let doc = self.doc.take();
+        self.eat();
+        let name = self.expect(
+            TokenType::Identifier,
+            String::from("Expected enum name following enum keyword.")
+        )?.tvalue;
+        self.expect(
+            TokenType::OpenBrace,
+            String::from("Expected '{' to open enum body.")
+        )?;
+        let mut variants = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseBrace {
+            let comments = self.take_comment_lines(false);
+            let variant_name = self.expect(
+                TokenType::Identifier,
+                String::from("Expected variant name inside enum body.")
+            )?.tvalue;
+            let mut fields = Vec::new();
+            if self.at().ttype == TokenType::OpenParen {
+                fields = self.parse_params()?;
+            }
+            variants.push(EnumVariant {
+                name: variant_name,
+                fields,
+                comments,
+            });
+            if self.at().ttype != TokenType::CloseBrace {
+                self.expect(
+                    TokenType::Comma,
+                    String::from("Expected ',' between enum variants.")
+                )?;
+            }
+        }
+        let closing_comments = self.take_comment_lines(false);
+        self.expect(
+            TokenType::CloseBrace,
+            String::from("Expected '}' to close enum body.")
+        )?;
+        self.enums.insert(name.clone(), variants.iter().map(|variant| variant.name.clone()).collect());
+        return Ok(Stmt::EnumDeclaration(EnumDeclaration {
+            kind: NodeType::EnumDeclaration,
+            name,
+            variants,
+            doc,
+            closing_comments,
+        }))
+    }
+
+    fn parse_return_stmt(&mut self) -> ParseResult<Stmt> {
+        self.eat();
+        let mut argument = None;
+        if self.at().ttype != TokenType::Semicolon {
+            argument = Some(self.parse_expr()?);
+        }
+        self.expect(
+            TokenType::Semicolon,
+            String::from("Return statement must end with semicolon.")
+        )?;
+        return Ok(Stmt::ReturnStmt(ReturnStmt {
+            kind: NodeType::ReturnStmt,
+            argument,
+        }))
+    }
+
+    fn parse_throw_stmt(&mut self) -> ParseResult<Stmt> {
+        self.eat();
+        let argument = self.parse_expr()?;
+        self.expect(
+            TokenType::Semicolon,
+            String::from("Throw statement must end with semicolon.")
+        )?;
+        return Ok(Stmt::ThrowStmt(ThrowStmt {
+            kind: NodeType::ThrowStmt,
+            argument,
+        }))
+    }
+
+    fn parse_try_stmt(&mut self) -> ParseResult<Stmt> {
+        self.eat();
+        let block = self.parse_block()?;
+        let mut param = None;
+        let mut handler = None;
+        let mut finalizer = None;
+        if self.at().ttype == TokenType::Catch {
+            self.eat();
+            if self.at().ttype == TokenType::OpenParen {
+                self.eat();
+                param = Some(self.expect(
+                    TokenType::Identifier,
+                    String::from("Expected identifier name inside catch clause.")
+                )?.tvalue);
+                self.expect(
+                    TokenType::CloseParen,
+                    String::from("Expected ')' following catch parameter.")
+                )?;
+            }
+            handler = Some(self.parse_block()?);
+        }
+        if self.at().ttype == TokenType::Finally {
+            self.eat();
+            finalizer = Some(self.parse_block()?);
+        }
+        if handler.is_none() && finalizer.is_none() {
+            return Err(self.error(String::from("Try statement must have a catch or finally clause.")))
+        }
+        return Ok(Stmt::TryStmt(TryStmt {
+            kind: NodeType::TryStmt,
+            block,
+            param,
+            handler,
+            finalizer,
+        }))
+    }
+
+    fn parse_for_stmt(&mut self) -> ParseResult<Stmt> {
+        self.eat();
+        let variable = self.expect(
+            TokenType::Identifier,
+            String::from("Expected loop variable name following for keyword.")
+        )?.tvalue;
+        self.expect(
+            TokenType::In,
+            String::from("Expected 'in' following the loop variable.")
+        )?;
+        let iterable = self.parse_expr()?;
+        let body = self.parse_block()?;
+        return Ok(Stmt::ForStmt(ForStmt {
+            kind: NodeType::ForStmt,
+            variable,
+            iterable,
+            body,
+        }))
+    }
+
+    fn parse_yield_stmt(&mut self) -> ParseResult<Stmt> {
+        match self.yields.last_mut() {
+            Some(yields) => *yields = true,
+            None => return Err(self.error(String::from("Yield statements are only allowed inside a function body."))),
+        }
+        self.eat();
+        let argument = self.parse_expr()?;
+        self.expect(
+            TokenType::Semicolon,
+            String::from("Yield statement must end with semicolon.")
+        )?;
+        return Ok(Stmt::YieldStmt(YieldStmt {
+            kind: NodeType::YieldStmt,
+            argument,
+        }))
+    }
+    fn parse_var_declaration(&mut self) -> ParseResult<Stmt> {
+        let doc = self.doc.take();
         let is_constant = self.eat().ttype == TokenType::Const;
         let identifier = self.expect(
             TokenType::Identifier,
             String::from("Expected identifier name following let/const keywords.")
-        ).tvalue;
+        )?.tvalue;
         if self.at().ttype == TokenType::Semicolon {
             self.eat();
             if is_constant {
-                panic!("Must assign value to constant expression. No value provided")
+                return Err(self.error(String::from("Must assign value to constant expression. No value provided")))
             }
-            return Stmt::VarDeclaration(VarDeclaration {
+            return Ok(Stmt::VarDeclaration(VarDeclaration {
                 kind: NodeType::VarDeclaration,
                 constant: false,
                 identifier,
                 value: None,
-            })
+                doc,
+            }))
         }
         let is_constant_node = is_constant;
         self.expect(
             TokenType::Equals,
-            String::from("Expected 'equals' token following identifier in var declaration")
-        );
+            String::from("Expected 'equals' token following identifier in var declaration.")
+        )?;
         let declaration = VarDeclaration {
             kind: NodeType::VarDeclaration,
             constant: is_constant_node,
             identifier,
-            value: Some(self.parse_expr()),
+            value: Some(self.parse_expr()?),
+            doc,
         };
         self.expect(
             TokenType::Semicolon,
             String::from("Variable Declaration must end with semicolon.")
-        );
-        return Stmt::VarDeclaration(declaration)
+        )?;
+        return Ok(Stmt::VarDeclaration(declaration))
     }
 
-    fn parse_expr(&mut self) -> Expr {
-        return self.parse_assignment_expr()
+    fn parse_expr(&mut self) -> ParseResult<Expr> {
+        self.enter()?;
+        let expr = grow_stack(|| self.parse_assignment_expr())?;
+        self.depth -= 1;
+        return Ok(expr)
     }
 
-    fn parse_assignment_expr(&mut self) -> Expr {
-        let left = self.parse_additive_expr();
+    fn parse_assignment_expr(&mut self) -> ParseResult<Expr> {
+        let left = self.parse_pipe_expr()?;
         if self.at().ttype ==  TokenType::Equals {
             self.eat();
-            let value = self.parse_assignment_expr();
-            return Expr::AssignmentExpr(Box::new(AssignmentExpr {
+            let value = self.parse_expr()?;
+            return Ok(Expr::AssignmentExpr(Box::new(AssignmentExpr {
                 kind: NodeType::AssignmentExpr,
                 assigne: left,
                 value,
-            }))
+            })))
         }
-        return left
+        return Ok(left)
     }
 
-    fn parse_additive_expr(&mut self) -> Expr {
-        let mut left = self.parse_multiplicative_expr();
-        while self.at().tvalue == "+" || self.at().tvalue == "-" {
-            let operator = self.eat().tvalue;
-            let right = self.parse_multiplicative_expr();
+    // binds loosest of the binary operators, so `run(a) | run(b) == other` compares the pipeline
+    fn parse_pipe_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut left = self.parse_equality_expr()?;
+        while let Some(operator) = self.eat_operator(&[BinaryOperator::Pipe]) {
+            let right = self.parse_equality_expr()?;
+            self.enter()?;
+            wrapped += 1;
             left = Expr::BinaryExpr(Box::new(BinaryExpr {
                 kind: NodeType::BinaryExpr,
                 left,
@@ -128,14 +708,17 @@ impl Parser {
                 operator,
             }));
         }
-        return left
+        self.depth -= wrapped;
+        return Ok(left)
     }
 
-    fn parse_multiplicative_expr(&mut self) -> Expr {
-        let mut left = self.parse_primary_expr();
-        while self.at().tvalue == "/" || self.at().tvalue == "*" || self.at().tvalue == "%" { // might need to do modulo
-            let operator = self.eat().tvalue;
-            let right = self.parse_primary_expr();
+    fn parse_equality_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut left = self.parse_relational_expr()?;
+        while let Some(operator) = self.eat_operator(&[BinaryOperator::Equal, BinaryOperator::NotEqual]) {
+            let right = self.parse_relational_expr()?;
+            self.enter()?;
+            wrapped += 1;
             left = Expr::BinaryExpr(Box::new(BinaryExpr {
                 kind: NodeType::BinaryExpr,
                 left,
@@ -143,8 +726,294 @@ impl Parser {
                 operator,
             }));
         }
-        return left
+        self.depth -= wrapped;
+        return Ok(left)
+    }
+
+    fn parse_relational_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut left = self.parse_additive_expr()?;
+        while let Some(operator) = self.eat_operator(&[BinaryOperator::InstanceOf]) {
+            let right = self.parse_additive_expr()?;
+            self.enter()?;
+            wrapped += 1;
+            left = Expr::BinaryExpr(Box::new(BinaryExpr {
+                kind: NodeType::BinaryExpr,
+                left,
+                right,
+                operator,
+            }));
+        }
+        self.depth -= wrapped;
+        return Ok(left)
+    }
+
+    fn parse_additive_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut left = self.parse_multiplicative_expr()?;
+        while let Some(operator) = self.eat_operator(&[BinaryOperator::Add, BinaryOperator::Subtract]) {
+            let right = self.parse_multiplicative_expr()?;
+            self.enter()?;
+            wrapped += 1;
+            left = Expr::BinaryExpr(Box::new(BinaryExpr {
+                kind: NodeType::BinaryExpr,
+                left,
+                right,
+                operator,
+            }));
+        }
+        self.depth -= wrapped;
+        return Ok(left)
+    }
+
+    fn parse_multiplicative_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut left = self.parse_call_member_expr()?;
+        while let Some(operator) = self.eat_operator(&[BinaryOperator::Divide, BinaryOperator::Multiply, BinaryOperator::Remainder]) {
+            let right = self.parse_call_member_expr()?;
+            self.enter()?;
+            wrapped += 1;
+            left = Expr::BinaryExpr(Box::new(BinaryExpr {
+                kind: NodeType::BinaryExpr,
+                left,
+                right,
+                operator,
+            }));
+        }
+        self.depth -= wrapped;
+        return Ok(left)
+    }
+
+    fn parse_new_expr(&mut self) -> ParseResult<Expr> {
+        self.eat();
+        let mut wrapped = 0;
+        let mut callee = self.parse_primary_expr()?;
+        while self.at().ttype == TokenType::Dot {
+            self.eat();
+            let property = self.expect(
+                TokenType::Identifier,
+                String::from("Expected property name following '.' operator.")
+            )?.tvalue;
+            self.enter()?;
+            wrapped += 1;
+            callee = Expr::MemberExpr(Box::new(MemberExpr {
+                kind: NodeType::MemberExpr,
+                object: callee,
+                property,
+            }));
+        }
+        let mut arguments = Vec::new();
+        if self.at().ttype == TokenType::OpenParen {
+            arguments = self.parse_args()?;
+        }
+        self.depth -= wrapped;
+        return Ok(Expr::NewExpr(Box::new(NewExpr {
+            kind: NodeType::NewExpr,
+            callee,
+            arguments,
+        })))
     }
+
+    fn parse_call_member_expr(&mut self) -> ParseResult<Expr> {
+        let mut wrapped = 0;
+        let mut object = if self.at().ttype == TokenType::New {
+            self.parse_new_expr()?
+        } else {
+            self.parse_primary_expr()?
+        };
+        loop {
+            if self.at().ttype == TokenType::Dot {
+                self.eat();
+                let property = self.expect(
+                    TokenType::Identifier,
+                    String::from("Expected property name following '.' operator.")
+                )?.tvalue;
+                self.enter()?;
+                wrapped += 1;
+                object = Expr::MemberExpr(Box::new(MemberExpr {
+                    kind: NodeType::MemberExpr,
+                    object,
+                    property,
+                }));
+            } else if self.at().ttype == TokenType::OpenBracket {
+                self.eat();
+                let index = self.parse_expr()?;
+                self.expect(
+                    TokenType::CloseBracket,
+                    String::from("Expected ']' to close index expression.")
+                )?;
+                self.enter()?;
+                wrapped += 1;
+                object = Expr::IndexExpr(Box::new(IndexExpr {
+                    kind: NodeType::IndexExpr,
+                    object,
+                    index,
+                }));
+            } else if self.at().ttype == TokenType::OpenParen {
+                let arguments = self.parse_args()?;
+                self.enter()?;
+                wrapped += 1;
+                object = Expr::CallExpr(Box::new(CallExpr {
+                    kind: NodeType::CallExpr,
+                    callee: object,
+                    arguments,
+                }));
+            } else if self.at().ttype == TokenType::Question {
+                self.eat();
+                self.enter()?;
+                wrapped += 1;
+                object = Expr::PropagateExpr(Box::new(PropagateExpr {
+                    kind: NodeType::PropagateExpr,
+                    argument: object,
+                }));
+            } else {
+                break;
+            }
+        }
+        self.depth -= wrapped;
+        return Ok(object)
+    }
+
+    fn parse_args(&mut self) -> ParseResult<Vec<Expr>> {
+        self.expect(
+            TokenType::OpenParen,
+            String::from("Expected '(' to open argument list.")
+        )?;
+        let mut arguments = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseParen {
+            arguments.push(self.parse_expr()?);
+            if self.at().ttype != TokenType::CloseParen {
+                self.expect(
+                    TokenType::Comma,
+                    String::from("Expected ',' between arguments.")
+                )?;
+            }
+        }
+        self.expect(
+            TokenType::CloseParen,
+            String::from("Expected ')' to close argument list.")
+        )?;
+        return Ok(arguments)
+    }
+    fn parse_match_expr(&mut self) -> ParseResult<Expr> {
+        self.eat();
+        let subject = self.parse_expr()?;
+        self.expect(
+            TokenType::OpenBrace,
+            String::from("Expected '{' to open match body.")
+        )?;
+        let mut arms = Vec::new();
+        while self.not_eof() && self.at().ttype != TokenType::CloseBrace {
+            let pattern = self.parse_pattern()?;
+            self.expect(
+                TokenType::FatArrow,
+                String::from("Expected '=>' following match pattern.")
+            )?;
+            let body = if self.at().ttype == TokenType::OpenBrace {
+                self.parse_block()?
+            } else {
+                vec![Stmt::Expr(self.parse_expr()?)]
+            };
+            arms.push(MatchArm { pattern, body });
+            if self.at().ttype == TokenType::Comma {
+                self.eat();
+            }
+        }
+        self.expect(
+            TokenType::CloseBrace,
+            String::from("Expected '}' to close match body.")
+        )?;
+        self.check_exhaustive(&arms);
+        return Ok(Expr::MatchExpr(Box::new(MatchExpr {
+            kind: NodeType::MatchExpr,
+            subject,
+            arms,
+        })))
+    }
+
+    fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        self.enter()?;
+        let pattern = grow_stack(|| self.parse_bare_pattern())?;
+        self.depth -= 1;
+        return Ok(pattern)
+    }
+
+    fn parse_bare_pattern(&mut self) -> ParseResult<Pattern> {
+        match self.at().ttype {
+            TokenType::Number | TokenType::Float | TokenType::String | TokenType::Null => {
+                return Ok(Pattern::Literal(self.parse_primary_expr()?))
+            },
+            TokenType::Identifier => {
+                let name = self.eat().tvalue;
+                if self.at().ttype != TokenType::Dot {
+                    if name == "_" {
+                        return Ok(Pattern::Wildcard)
+                    }
+                    return Ok(Pattern::Binding(name))
+                }
+                self.eat();
+                let variant = self.expect(
+                    TokenType::Identifier,
+                    String::from("Expected variant name following '.' in pattern.")
+                )?.tvalue;
+                let mut fields = Vec::new();
+                if self.at().ttype == TokenType::OpenParen {
+                    self.eat();
+                    while self.not_eof() && self.at().ttype != TokenType::CloseParen {
+                        fields.push(self.parse_pattern()?);
+                        if self.at().ttype != TokenType::CloseParen {
+                            self.expect(
+                                TokenType::Comma,
+                                String::from("Expected ',' between patterns.")
+                            )?;
+                        }
+                    }
+                    self.expect(
+                        TokenType::CloseParen,
+                        String::from("Expected ')' to close variant pattern.")
+                    )?;
+                }
+                return Ok(Pattern::Variant {
+                    enum_name: name,
+                    variant,
+                    fields,
+                })
+            },
+            _ => {
+                return Err(self.error(format!("Unexpected token inside match pattern: {}.", self.found())))
+            }
+        }
+    }
+
+    // warns when every arm matches variants of one known enum but some variant has no arm
+    fn check_exhaustive(&mut self, arms: &[MatchArm]) {
+        let mut matched_enum: Option<String> = None;
+        let mut covered = Vec::new();
+        for arm in arms {
+            match &arm.pattern {
+                Pattern::Variant { enum_name, variant, fields } => {
+                    if matched_enum.is_some() && matched_enum.as_ref() != Some(enum_name) {
+                        return
+                    }
+                    matched_enum = Some(enum_name.clone());
+                    if fields.iter().all(|field| field.is_irrefutable()) {
+                        covered.push(variant.clone());
+                    }
+                },
+                Pattern::Wildcard | Pattern::Binding(_) => return,
+                Pattern::Literal(_) => return,
+            }
+        }
+        if let Some(enum_name) = matched_enum {
+            if let Some(variants) = self.enums.get(&enum_name) {
+                let missing: Vec<String> = variants.iter().filter(|variant| !covered.contains(variant)).cloned().collect();
+                if !missing.is_empty() {
+                    self.warnings.push(format!("Non-exhaustive match over enum {}, missing: {}", enum_name, missing.join(", ")));
+                }
+            }
+        }
+    }
+
         /* `Expr` value */
 /*order of precidence
     assignmentExpr
@@ -157,42 +1026,108 @@ impl Parser {
     unaryExpr
     primaryExpr */
 
-    fn parse_primary_expr(&mut self) -> Expr {
+    fn parse_primary_expr(&mut self) -> ParseResult<Expr> {
         match self.at().ttype {
             TokenType::Number => {
-                let value = self.at().tvalue.clone();
-                self.eat();
-                Expr::NumericLiteral(NumericLiteral {
+                let raw = self.eat().tvalue;
+                Ok(Expr::NumericLiteral(NumericLiteral {
                     kind: NodeType::NumericLiteral,
-                    value: value.parse::<i64>().unwrap(),
-                })
+                    value: parse_number(&raw).expect("numeric literals are validated by the lexer"),
+                    raw: Some(raw),
+                }))
+            }
+            TokenType::Float => {
+                let raw = self.eat().tvalue;
+                Ok(Expr::FloatLiteral(FloatLiteral {
+                    kind: NodeType::FloatLiteral,
+                    value: parse_float(&raw).expect("float literals are validated by the lexer"),
+                    raw,
+                }))
             }
             TokenType::Null => {
                 self.eat();
-                Expr::NullLiteral(NullLiteral {
+                Ok(Expr::NullLiteral(NullLiteral {
                     kind: NodeType::NullLiteral,
                     value: Value::Null,
-                })
+                }))
+            }
+            TokenType::String => {
+                let value = self.eat().tvalue;
+                Ok(Expr::StringLiteral(StringLiteral {
+                    kind: NodeType::StringLiteral,
+                    value,
+                }))
+            }
+            TokenType::Capture => {
+                let command = self.eat().tvalue;
+                Ok(Expr::CaptureExpr(CaptureExpr {
+                    kind: NodeType::CaptureExpr,
+                    command,
+                }))
+            }
+            TokenType::This | TokenType::Super => {
+                let symbol = self.eat().tvalue;
+                Ok(Expr::Identifier(Identifier {
+                    kind: NodeType::Identifier,
+                    symbol,
+                    slot: None,
+                }))
             }
             TokenType::Identifier => {
                 let symbol = self.at().tvalue.clone();
                 self.eat();
-                Expr::Identifier(Identifier {
+                Ok(Expr::Identifier(Identifier {
                     kind: NodeType::Identifier,
                     symbol,
-                })
+                    slot: None,
+                }))
+            }
+            TokenType::Match => {
+                return self.parse_match_expr()
+            }
+            TokenType::Fn => {
+                self.eat();
+                let parameters = self.parse_params()?;
+                let (body, generator) = self.parse_function_body()?;
+                Ok(Expr::FunctionExpr(FunctionExpr {
+                    kind: NodeType::FunctionExpr,
+                    parameters,
+                    body,
+                    generator,
+                }))
+            }
+            TokenType::OpenBracket => {
+                self.eat();
+                let mut elements = Vec::new();
+                while self.not_eof() && self.at().ttype != TokenType::CloseBracket {
+                    elements.push(self.parse_expr()?);
+                    if self.at().ttype != TokenType::CloseBracket {
+                        self.expect(
+                            TokenType::Comma,
+                            String::from("Expected ',' between list elements.")
+                        )?;
+                    }
+                }
+                self.expect(
+                    TokenType::CloseBracket,
+                    String::from("Expected ']' to close list literal.")
+                )?;
+                Ok(Expr::ListExpr(ListExpr {
+                    kind: NodeType::ListExpr,
+                    elements,
+                }))
             }
             TokenType::OpenParen => {
                 self.eat();
-                let value = self.parse_expr();
+                let value = self.parse_expr()?;
                 self.expect(
                     TokenType::CloseParen,
                     String::from("Unexpected token found inside parenthesized expression.")
-                );
-                return value
+                )?;
+                return Ok(value)
             }
             _ => {
-                panic!("Unexpected token type: {:?}", self.at());
+                return Err(self.error(format!("Unexpected token {}.", self.found())))
             }
         }
     }
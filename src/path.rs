@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::values::{ErrorVal, ModuleVal, NativeFnVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+// pure string work on paths using the host's separator; nothing here touches the disk
+pub fn path_module() -> ModuleVal {
+    let functions: [(&str, Native); 3] = [
+        ("join", native_join),
+        ("basename", native_basename),
+        ("extension", native_extension),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("path"),
+        exports: Rc::new(exports),
+    }
+}
+
+fn text(path: &Path) -> RuntimeVal {
+    return RuntimeVal::from(path.to_string_lossy().into_owned())
+}
+
+// an absolute part replaces everything before it, as it does for the host's own paths
+fn native_join(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    if arguments.is_empty() {
+        return Err(ErrorVal::make_error("TypeError", String::from("path.join expects at least 1 argument")).into())
+    }
+    let mut joined = PathBuf::new();
+    for part in &arguments {
+        joined.push(part.get_string_value()?);
+    }
+    return Ok(text(&joined))
+}
+
+// the last component, or "" when there is none, as for "/" or ".."
+fn native_basename(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("path.basename", &arguments, 1)?;
+    let path = arguments[0].get_string_value()?;
+    return Ok(text(Path::new(Path::new(&path).file_name().unwrap_or_default())))
+}
+
+// the text after the last '.' of the last component, without the dot; "" when there is none
+fn native_extension(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("path.extension", &arguments, 1)?;
+    let path = arguments[0].get_string_value()?;
+    return Ok(text(Path::new(Path::new(&path).extension().unwrap_or_default())))
+}
@@ -0,0 +1,368 @@
+use crate::ast::{BinaryOperator, ClassDeclaration, Expr, FunctionDeclaration, MatchExpr, NewExpr, Pattern, Program, Stmt, TryStmt};
+use crate::lexer::{tokenize, TokenType};
+use crate::limits::grow_stack;
+use crate::parser::Parser;
+
+const INDENT: &str = "    ";
+
+// binding strength of each expression form, mirroring the parser's precedence climb
+const ASSIGNMENT: u8 = 1;
+const PIPE: u8 = 2;
+const EQUALITY: u8 = 3;
+const RELATIONAL: u8 = 4;
+const ADDITIVE: u8 = 5;
+const MULTIPLICATIVE: u8 = 6;
+const POSTFIX: u8 = 7;
+const PRIMARY: u8 = 8;
+
+pub fn print_program(program: &Program) -> String {
+    let mut printer = Printer { out: String::new(), depth: 0 };
+    for (index, statement) in program.body.iter().enumerate() {
+        if index == 0 || matches!(program.body[index - 1], Stmt::Comment(_)) {
+            printer.print_stmt(statement);
+            continue;
+        }
+        // comments stay glued to the statement below them
+        let next = program.body[index..].iter().find(|statement| !matches!(statement, Stmt::Comment(_)));
+        if is_item(&program.body[index - 1]) || next.is_some_and(is_item) {
+            printer.out.push('\n');
+        }
+        printer.print_stmt(statement);
+    }
+    return printer.out
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+fn doc_of(statement: &Stmt) -> &Option<String> {
+    match statement {
+        Stmt::VarDeclaration(declaration) => return &declaration.doc,
+        Stmt::FunctionDeclaration(declaration) => return &declaration.doc,
+        Stmt::ClassDeclaration(declaration) => return &declaration.doc,
+        Stmt::EnumDeclaration(declaration) => return &declaration.doc,
+        Stmt::ExportDeclaration(declaration) => return doc_of(&declaration.declaration),
+        _ => return &None,
+    }
+}
+
+// declarations that get a blank line around them at the top level
+fn is_item(statement: &Stmt) -> bool {
+    match statement {
+        Stmt::FunctionDeclaration(_) | Stmt::ClassDeclaration(_) | Stmt::EnumDeclaration(_) => return true,
+        Stmt::ExportDeclaration(declaration) => return is_item(&declaration.declaration),
+        _ => return false,
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::AssignmentExpr(_) => return ASSIGNMENT,
+        Expr::BinaryExpr(binary_expr) => return operator_precedence(binary_expr.operator),
+        Expr::MemberExpr(_) | Expr::IndexExpr(_) | Expr::CallExpr(_) | Expr::PropagateExpr(_) | Expr::NewExpr(_) => return POSTFIX,
+        Expr::NumericLiteral(literal) if literal.value < 0 => return ADDITIVE,
+        _ => return PRIMARY,
+    }
+}
+
+fn operator_precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Pipe => return PIPE,
+        BinaryOperator::Equal | BinaryOperator::NotEqual => return EQUALITY,
+        BinaryOperator::InstanceOf => return RELATIONAL,
+        BinaryOperator::Add | BinaryOperator::Subtract => return ADDITIVE,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => return MULTIPLICATIVE,
+    }
+}
+
+pub fn escape_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    return escaped
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    // prints `{`, the indented statements and the closing `}` without a trailing newline
+    fn block(&mut self, body: &[Stmt]) -> String {
+        if body.is_empty() {
+            return String::from("{}")
+        }
+        let mut inner = Printer { out: String::new(), depth: self.depth + 1 };
+        for statement in body {
+            inner.print_stmt(statement);
+        }
+        return format!("{{\n{}{}}}", inner.out, INDENT.repeat(self.depth))
+    }
+
+    fn function(&mut self, prefix: &str, declaration: &FunctionDeclaration) -> String {
+        return format!("{}{}({}) {}", prefix, declaration.name, declaration.parameters.join(", "), self.block(&declaration.body))
+    }
+
+    fn print_doc(&mut self, doc: &Option<String>) {
+        for line in doc.iter().flat_map(|doc| doc.split('\n')) {
+            match line {
+                "" => self.line("///"),
+                _ => self.line(&format!("/// {}", line)),
+            }
+        }
+    }
+
+    fn print_stmt(&mut self, statement: &Stmt) {
+        self.print_doc(doc_of(statement));
+        let text = self.stmt(statement);
+        self.line(&text);
+    }
+
+    fn stmt(&mut self, statement: &Stmt) -> String {
+        return grow_stack(|| self.stmt_node(statement))
+    }
+
+    fn stmt_node(&mut self, statement: &Stmt) -> String {
+        match statement {
+            Stmt::Program(program) => return print_program(program),
+            Stmt::VarDeclaration(declaration) => {
+                let keyword = if declaration.constant { "const" } else { "let" };
+                match declaration.value {
+                    Some(ref value) => return format!("{} {} = {};", keyword, declaration.identifier, self.expr(value, ASSIGNMENT)),
+                    None => return format!("{} {};", keyword, declaration.identifier),
+                }
+            },
+            Stmt::FunctionDeclaration(declaration) => return self.function("fn ", declaration),
+            Stmt::ClassDeclaration(declaration) => return self.class(declaration),
+            Stmt::EnumDeclaration(declaration) => {
+                let mut text = format!("enum {} {{\n", declaration.name);
+                for variant in &declaration.variants {
+                    text.push_str(&comment_lines(&variant.comments, self.depth + 1));
+                    text.push_str(&INDENT.repeat(self.depth + 1));
+                    text.push_str(&variant.name);
+                    if !variant.fields.is_empty() {
+                        text.push_str(&format!("({})", variant.fields.join(", ")));
+                    }
+                    text.push_str(",\n");
+                }
+                text.push_str(&comment_lines(&declaration.closing_comments, self.depth + 1));
+                text.push_str(&INDENT.repeat(self.depth));
+                text.push('}');
+                return text
+            },
+            Stmt::ImportDeclaration(declaration) => {
+                if let Some(ref namespace) = declaration.namespace {
+                    return format!("import * as {} from {};", namespace, escape_string(&declaration.source))
+                }
+                let specifiers: Vec<String> = declaration.specifiers.iter().map(|(imported, local)| {
+                    if imported == local { imported.clone() } else { format!("{} as {}", imported, local) }
+                }).collect();
+                return format!("import {{ {} }} from {};", specifiers.join(", "), escape_string(&declaration.source))
+            },
+            Stmt::ExportDeclaration(declaration) => return format!("export {}", self.stmt(&declaration.declaration)),
+            Stmt::ReturnStmt(stmt) => {
+                match stmt.argument {
+                    Some(ref argument) => return format!("return {};", self.expr(argument, ASSIGNMENT)),
+                    None => return String::from("return;"),
+                }
+            },
+            Stmt::ThrowStmt(stmt) => return format!("throw {};", self.expr(&stmt.argument, ASSIGNMENT)),
+            Stmt::TryStmt(stmt) => return self.try_stmt(stmt),
+            Stmt::ForStmt(stmt) => {
+                return format!("for {} in {} {}", stmt.variable, self.expr(&stmt.iterable, ASSIGNMENT), self.block(&stmt.body))
+            },
+            Stmt::YieldStmt(stmt) => return format!("yield {};", self.expr(&stmt.argument, ASSIGNMENT)),
+            Stmt::Comment(comment) => return comment.text.clone(),
+            Stmt::Expr(expr) => return format!("{};", self.expr(expr, ASSIGNMENT)),
+        }
+    }
+
+    fn class(&mut self, declaration: &ClassDeclaration) -> String {
+        let mut text = format!("class {}", declaration.name);
+        if let Some(ref superclass) = declaration.superclass {
+            text.push_str(&format!(" extends {}", superclass));
+        }
+        if declaration.methods.is_empty() && declaration.static_methods.is_empty() && declaration.closing_comments.is_empty() {
+            return text + " {}"
+        }
+        text.push_str(" {\n");
+        self.depth += 1;
+        let class_text = std::mem::take(&mut self.out);
+        let methods = declaration.methods.iter().map(|method| ("", method));
+        let static_methods = declaration.static_methods.iter().map(|method| ("static ", method));
+        for (index, (prefix, method)) in methods.chain(static_methods).enumerate() {
+            if index > 0 {
+                self.out.push('\n');
+            }
+            for comment in &method.comments {
+                self.line(comment);
+            }
+            self.print_doc(&method.doc);
+            let method_text = self.function(prefix, method);
+            self.line(&method_text);
+        }
+        for comment in &declaration.closing_comments {
+            self.line(comment);
+        }
+        text.push_str(&std::mem::replace(&mut self.out, class_text));
+        self.depth -= 1;
+        text.push_str(&INDENT.repeat(self.depth));
+        text.push('}');
+        return text
+    }
+
+    fn try_stmt(&mut self, stmt: &TryStmt) -> String {
+        let mut text = format!("try {}", self.block(&stmt.block));
+        if let Some(ref handler) = stmt.handler {
+            match stmt.param {
+                Some(ref param) => text.push_str(&format!(" catch ({}) ", param)),
+                None => text.push_str(" catch "),
+            }
+            text.push_str(&self.block(handler));
+        }
+        if let Some(ref finalizer) = stmt.finalizer {
+            text.push_str(&format!(" finally {}", self.block(finalizer)));
+        }
+        return text
+    }
+
+    // renders `expr`, parenthesised when it binds more loosely than `min` requires
+    fn expr(&mut self, expr: &Expr, min: u8) -> String {
+        return grow_stack(|| self.expr_node(expr, min))
+    }
+
+    fn expr_node(&mut self, expr: &Expr, min: u8) -> String {
+        let text = match expr {
+            Expr::NumericLiteral(literal) => {
+                // only the optimizer produces negative literals; the language has no unary minus
+                if let Some(ref raw) = literal.raw {
+                    raw.clone()
+                } else if literal.value == i64::MIN {
+                    format!("0 - {} - 1", i64::MAX)
+                } else if literal.value < 0 {
+                    format!("0 - {}", -literal.value)
+                } else {
+                    literal.value.to_string()
+                }
+            },
+            Expr::FloatLiteral(literal) => literal.raw.clone(),
+            Expr::NullLiteral(_) => String::from("null"),
+            Expr::StringLiteral(literal) => escape_string(&literal.value),
+            Expr::CaptureExpr(capture_expr) => format!("$({})", capture_expr.command),
+            Expr::Identifier(identifier) => identifier.symbol.clone(),
+            Expr::BinaryExpr(binary_expr) => {
+                let level = operator_precedence(binary_expr.operator);
+                format!("{} {} {}", self.expr(&binary_expr.left, level), binary_expr.operator, self.expr(&binary_expr.right, level + 1))
+            },
+            Expr::AssignmentExpr(assignment_expr) => {
+                format!("{} = {}", self.expr(&assignment_expr.assigne, EQUALITY), self.expr(&assignment_expr.value, ASSIGNMENT))
+            },
+            Expr::MemberExpr(member_expr) => format!("{}.{}", self.expr(&member_expr.object, POSTFIX), member_expr.property),
+            Expr::IndexExpr(index_expr) => format!("{}[{}]", self.expr(&index_expr.object, POSTFIX), self.expr(&index_expr.index, ASSIGNMENT)),
+            Expr::ListExpr(list_expr) => format!("[{}]", self.arguments(&list_expr.elements)),
+            Expr::CallExpr(call_expr) => format!("{}({})", self.expr(&call_expr.callee, POSTFIX), self.arguments(&call_expr.arguments)),
+            Expr::NewExpr(new_expr) => self.new_expr(new_expr),
+            Expr::FunctionExpr(function_expr) => format!("fn({}) {}", function_expr.parameters.join(", "), self.block(&function_expr.body)),
+            Expr::PropagateExpr(propagate_expr) => format!("{}?", self.expr(&propagate_expr.argument, POSTFIX)),
+            Expr::MatchExpr(match_expr) => self.match_expr(match_expr),
+        };
+        if precedence(expr) < min {
+            return format!("({})", text)
+        }
+        return text
+    }
+
+    fn arguments(&mut self, arguments: &[Expr]) -> String {
+        let arguments: Vec<String> = arguments.iter().map(|argument| self.expr(argument, ASSIGNMENT)).collect();
+        return arguments.join(", ")
+    }
+
+    // `new` only takes a primary expression followed by property accesses as its callee
+    fn new_callee(&mut self, callee: &Expr) -> String {
+        match callee {
+            Expr::MemberExpr(member_expr) => return format!("{}.{}", self.new_callee(&member_expr.object), member_expr.property),
+            _ => return self.expr(callee, PRIMARY),
+        }
+    }
+
+    fn new_expr(&mut self, new_expr: &NewExpr) -> String {
+        return format!("new {}({})", self.new_callee(&new_expr.callee), self.arguments(&new_expr.arguments))
+    }
+
+    fn match_expr(&mut self, match_expr: &MatchExpr) -> String {
+        let mut text = format!("match {} {{\n", self.expr(&match_expr.subject, ASSIGNMENT));
+        self.depth += 1;
+        for arm in &match_expr.arms {
+            let body = match arm.body.as_slice() {
+                [Stmt::Expr(expr)] => self.expr(expr, ASSIGNMENT),
+                body => self.block(body),
+            };
+            text.push_str(&INDENT.repeat(self.depth));
+            text.push_str(&format!("{} => {},\n", pattern(&arm.pattern), body));
+        }
+        self.depth -= 1;
+        text.push_str(&INDENT.repeat(self.depth));
+        text.push('}');
+        return text
+    }
+}
+
+fn pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Wildcard => return String::from("_"),
+        Pattern::Binding(name) => return name.clone(),
+        Pattern::Literal(literal) => return Printer { out: String::new(), depth: 0 }.expr(literal, PRIMARY),
+        Pattern::Variant { enum_name, variant, fields } => {
+            if fields.is_empty() {
+                return format!("{}.{}", enum_name, variant)
+            }
+            let fields: Vec<String> = fields.iter().map(self::pattern).collect();
+            return format!("{}.{}({})", enum_name, variant, fields.join(", "))
+        },
+    }
+}
+
+fn comment_lines(comments: &[String], depth: usize) -> String {
+    return comments.iter().map(|comment| format!("{}{}\n", INDENT.repeat(depth), comment)).collect()
+}
+
+fn count_comments(source: &str) -> usize {
+    // only called on sources that have already been parsed, so they lex
+    let tokens = tokenize(String::from(source)).unwrap_or_default();
+    return tokens.iter().filter(|token| matches!(token.ttype, TokenType::Comment | TokenType::DocComment)).count()
+}
+
+fn parse_with_comments(source: String) -> Result<Program, String> {
+    let mut parser = Parser::with_comments(source).map_err(|error| error.message)?;
+    return parser.produce_ast().map_err(|error| error.message)
+}
+
+// parses and reprints `source`, refusing any output that would not parse back to the same tree
+pub fn format_source(source: String) -> Result<String, String> {
+    let program = parse_with_comments(source.clone())?;
+    let formatted = print_program(&program);
+    let reparsed = parse_with_comments(formatted.clone())?;
+    if reparsed != program {
+        return Err(String::from("the formatted program does not parse back to the same syntax tree"))
+    }
+    // comments between statements, class members and enum variants have a place in the tree, as
+    // do doc comments on declarations, but those inside expressions do not
+    if count_comments(&formatted) != count_comments(&source) {
+        return Err(String::from("comments inside expressions cannot be kept"))
+    }
+    return Ok(formatted)
+}
@@ -0,0 +1,293 @@
+use std::cell::RefCell;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::fs::io_error;
+use crate::interpreter::{EvalResult, Interrupt};
+use crate::limits::{self, require_io};
+use crate::lists::list_result;
+use crate::values::{EnumVal, ErrorVal, NullVal, RuntimeVal, ValueType, VariantVal};
+
+// how often a running pipeline is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// exec() answers with Process.Output, and run() describes a command line as a Process.Command
+// holding one list of words per pipeline stage; nothing starts until the command is exec'd
+pub fn process_enum() -> EnumVal {
+    return EnumVal {
+        value_type: ValueType::Enum,
+        name: String::from("Process"),
+        variants: vec![
+            (String::from("Output"), vec![String::from("stdout"), String::from("stderr"), String::from("status")]),
+            (String::from("Command"), vec![String::from("stages")]),
+        ],
+    }
+}
+
+fn variant(name: &str, field_names: &[&str], fields: Vec<RuntimeVal>) -> RuntimeVal {
+    return VariantVal {
+        value_type: ValueType::Variant,
+        enum_name: String::from("Process"),
+        variant: String::from(name),
+        field_names: field_names.iter().map(|name| String::from(*name)).collect(),
+        fields,
+    }.to_runtime_val()
+}
+
+fn command_value(stages: Vec<Vec<String>>) -> EvalResult {
+    let mut lists = Vec::new();
+    for words in stages {
+        lists.push(list_result(words.into_iter().map(RuntimeVal::from).collect())?);
+    }
+    return Ok(variant("Command", &["stages"], vec![list_result(lists)?]))
+}
+
+// the stages of a Process.Command, or none for any other value
+fn command_stages(value: &RuntimeVal) -> Result<Option<Vec<Vec<String>>>, ErrorVal> {
+    match value {
+        RuntimeVal::VariantVal(command) if command.enum_name == "Process" && command.variant == "Command" => {
+            let mut stages = Vec::new();
+            for stage in command.fields[0].get_list_value()?.items.borrow().iter() {
+                let mut words = Vec::new();
+                for word in stage.get_list_value()?.items.borrow().iter() {
+                    words.push(word.get_string_value()?);
+                }
+                stages.push(words);
+            }
+            return Ok(Some(stages))
+        },
+        _ => return Ok(None),
+    }
+}
+
+// splits a command line into stages at each `|` and each stage into words at whitespace; quotes
+// group words the way a shell's do, but nothing is expanded
+pub fn parse_command_line(line: &str) -> Result<Vec<Vec<String>>, ErrorVal> {
+    let mut stages = Vec::new();
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some('\\') if c == '"' => word.extend(chars.next()),
+                        Some(next) => word.push(next),
+                        None => {
+                            return Err(ErrorVal::make_error("ValueError", format!("Unterminated quote in command '{}'", line)))
+                        },
+                    }
+                }
+            },
+            '|' => {
+                words.extend(word.take());
+                stages.push(std::mem::take(&mut words));
+            },
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    stages.push(words);
+    if stages.iter().any(Vec::is_empty) {
+        return Err(ErrorVal::make_error("ValueError", format!("Empty command in '{}'", line)))
+    }
+    return Ok(stages)
+}
+
+// `a | b` feeds the output of a into b; the result is a new command and neither side runs yet
+pub fn pipe(lhs: RuntimeVal, rhs: RuntimeVal) -> EvalResult {
+    match (command_stages(&lhs)?, command_stages(&rhs)?) {
+        (Some(mut first), Some(second)) => {
+            first.extend(second);
+            return command_value(first)
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Unsupported operand types for |: {:?} and {:?}", lhs.get_value_type(), rhs.get_value_type())
+            ).into())
+        },
+    }
+}
+
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: i64, // -1 when the last stage was killed by a signal
+}
+
+fn read_all(mut stream: impl Read) -> String {
+    let mut bytes = Vec::new();
+    let _ = stream.read_to_end(&mut bytes);
+    return String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// runs the stages side by side with each one's stdout wired to the next one's stdin; stderr is
+// gathered from every stage and the status is the last stage's, as in a shell
+pub fn run_pipeline(stages: &[Vec<String>]) -> Result<Output, ErrorVal> {
+    let display = stages.iter().map(|words| words.join(" ")).collect::<Vec<String>>().join(" | ");
+    require_io(&format!("run '{}'", display))?;
+    let mut children: Vec<Child> = Vec::new();
+    let mut readers = Vec::new();
+    let mut failure = None;
+    for words in stages {
+        let stdin = match children.last_mut().and_then(|child| child.stdout.take()) {
+            Some(previous) => Stdio::from(previous),
+            None => Stdio::null(),
+        };
+        let spawned = Command::new(&words[0]).args(&words[1..]).stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+        match spawned {
+            Ok(mut child) => {
+                // drained on threads, so a chatty stage cannot block the pipeline on a full buffer
+                let stderr = child.stderr.take().unwrap();
+                readers.push(thread::spawn(move || read_all(stderr)));
+                children.push(child);
+            },
+            Err(error) => {
+                failure = Some(io_error("run", &words[0], error));
+                break;
+            },
+        }
+    }
+    let stdout = match (&failure, children.last_mut().and_then(|child| child.stdout.take())) {
+        (None, Some(stdout)) => thread::spawn(move || read_all(stdout)),
+        _ => thread::spawn(String::new),
+    };
+    let status = wait_all(&mut children, &display)?;
+    if let Some(error) = failure {
+        return Err(error)
+    }
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = readers.into_iter().map(|reader| reader.join().unwrap_or_default()).collect();
+    return Ok(Output { stdout, stderr, status })
+}
+
+// polls instead of blocking in wait(), so a host interrupt or the timeout still ends a stage that
+// never exits; every stage is killed when the pipeline is cut short
+fn wait_all(children: &mut [Child], display: &str) -> Result<i64, ErrorVal> {
+    let mut status = -1;
+    for index in 0..children.len() {
+        loop {
+            match children[index].try_wait() {
+                Ok(Some(exit)) => {
+                    status = exit.code().map_or(-1, i64::from);
+                    break;
+                },
+                Ok(None) => {},
+                Err(error) => return Err(io_error("run", display, error)),
+            }
+            if let Err(error) = limits::sleep(POLL_INTERVAL) {
+                for child in children.iter_mut() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                return Err(error)
+            }
+        }
+    }
+    return Ok(status)
+}
+
+fn output_value(output: Output) -> RuntimeVal {
+    return variant(
+        "Output",
+        &["stdout", "stderr", "status"],
+        vec![RuntimeVal::from(output.stdout), RuntimeVal::from(output.stderr), RuntimeVal::from(output.status)]
+    )
+}
+
+// `$(command line)`: the command's stdout without its trailing newlines; failing is an error,
+// since a script that captures output rarely means to carry on without it
+pub fn capture(line: &str) -> EvalResult {
+    let output = run_pipeline(&parse_command_line(line)?)?;
+    if output.status != 0 {
+        let mut message = format!("Command '{}' exited with status {}", line, output.status);
+        if !output.stderr.trim().is_empty() {
+            message = format!("{}: {}", message, output.stderr.trim());
+        }
+        return Err(ErrorVal::make_error("ProcessError", message).into())
+    }
+    return Ok(RuntimeVal::from(String::from(output.stdout.trim_end_matches(['\n', '\r']))))
+}
+
+// exec(program), exec(program, arguments) or exec(command); a non-zero status is not an error here
+pub fn native_exec(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let stages = match arguments.as_slice() {
+        [command] if command_stages(command)?.is_some() => command_stages(command)?.unwrap(),
+        [program] => vec![vec![program.get_string_value()?]],
+        [program, args] => {
+            let mut words = vec![program.get_string_value()?];
+            for arg in args.get_list_value()?.items.borrow().iter() {
+                words.push(arg.get_string_value()?);
+            }
+            vec![words]
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("exec expects 1 or 2 argument(s) but received {}", arguments.len())
+            ).into())
+        },
+    };
+    return Ok(output_value(run_pipeline(&stages)?))
+}
+
+pub fn native_run(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("run", &arguments, 1)?;
+    return command_value(parse_command_line(&arguments[0].get_string_value()?)?)
+}
+
+// the variable's value, or null when it is unset
+pub fn native_getenv(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("getenv", &arguments, 1)?;
+    let name = arguments[0].get_string_value()?;
+    require_io(&format!("read environment variable '{}'", name))?;
+    return Ok(RuntimeVal::from(std::env::var(&name).ok()))
+}
+
+// setting a variable to null removes it; child processes see the change
+pub fn native_setenv(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("setenv", &arguments, 2)?;
+    let name = arguments[0].get_string_value()?;
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(ErrorVal::make_error("ValueError", format!("Invalid environment variable name '{}'", name)).into())
+    }
+    require_io(&format!("set environment variable '{}'", name))?;
+    match &arguments[1] {
+        RuntimeVal::NullVal(_) => std::env::remove_var(&name),
+        value => {
+            let value = value.get_string_value()?;
+            if value.contains('\0') {
+                return Err(ErrorVal::make_error("ValueError", format!("The value for '{}' contains a NUL character", name)).into())
+            }
+            std::env::set_var(&name, value)
+        },
+    }
+    return Ok(NullVal::make_null().to_runtime_val())
+}
+
+// ends the whole evaluation, past any try or finally; the host decides what the status means
+pub fn native_exit(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let status = match arguments.as_slice() {
+        [] => 0,
+        [status] => status.get_number_value()?,
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("exit expects 0 or 1 argument(s) but received {}", arguments.len())
+            ).into())
+        },
+    };
+    let status = i32::try_from(status).map_err(|_| {
+        ErrorVal::make_error("ValueError", format!("Exit status {} is out of range", status))
+    })?;
+    return Err(Interrupt::Exit(status))
+}
@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::lists::list_result;
+use crate::modules::with_session;
+use crate::values::{ErrorVal, ModuleVal, NativeFnVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+// xoshiro256** seeded through splitmix64; the same seed gives the same sequence on every platform
+#[derive(Debug)]
+pub struct Generator {
+    state: [u64; 4],
+}
+
+// seeded from the clock until a script calls random.seed
+impl Default for Generator {
+    fn default() -> Self {
+        return Generator::seeded(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64))
+    }
+}
+
+impl Generator {
+    fn seeded(seed: u64) -> Generator {
+        let mut mix = seed;
+        let mut state = [0; 4];
+        for word in state.iter_mut() {
+            mix = mix.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = mix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+        return Generator { state }
+    }
+
+    fn next(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let shifted = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= shifted;
+        self.state[3] = self.state[3].rotate_left(45);
+        return result
+    }
+
+    // uniform in 0..span, rejecting the top values that would favour the low end
+    fn below(&mut self, span: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % span;
+        loop {
+            let value = self.next();
+            if value < zone {
+                return value % span
+            }
+        }
+    }
+}
+
+// each engine draws from its own generator, so seeding one leaves the others alone
+fn with_generator<T>(draw: impl FnOnce(&mut Generator) -> T) -> T {
+    return with_session(|session| draw(&mut session.random))
+}
+
+// an index below len, which must not be 0
+fn index_below(len: usize) -> usize {
+    return with_generator(|generator| generator.below(len as u64)) as usize
+}
+
+pub fn random_module() -> ModuleVal {
+    let functions: [(&str, Native); 6] = [
+        ("seed", native_seed),
+        ("int", native_int),
+        ("float", native_float),
+        ("choice", native_choice),
+        ("shuffle", native_shuffle),
+        ("sample", native_sample),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("random"),
+        exports: Rc::new(exports),
+    }
+}
+
+fn native_seed(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.seed", &arguments, 1)?;
+    let seed = arguments[0].get_number_value()?;
+    // a negative seed would otherwise wrap onto the stream of a large positive one
+    if seed < 0 {
+        return Err(ErrorVal::make_error("ValueError", format!("random.seed needs a seed of at least 0, not {}", seed)).into())
+    }
+    with_generator(|generator| *generator = Generator::seeded(seed as u64));
+    return Ok(RuntimeVal::from(()))
+}
+
+// both bounds are included, so random.int(1, 6) rolls a die
+fn native_int(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.int", &arguments, 2)?;
+    let (lo, hi) = (arguments[0].get_number_value()?, arguments[1].get_number_value()?);
+    if lo > hi {
+        return Err(ErrorVal::make_error("ValueError", format!("random.int needs lo <= hi, not {} > {}", lo, hi)).into())
+    }
+    let span = hi.abs_diff(lo).wrapping_add(1);
+    // a span of 0 means the whole range of integers wrapped around
+    let offset = with_generator(|generator| if span == 0 { generator.next() } else { generator.below(span) });
+    return Ok(RuntimeVal::from(lo.wrapping_add_unsigned(offset)))
+}
+
+// uniform in [0, 1), from the top 53 bits
+fn native_float(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.float", &arguments, 0)?;
+    let bits = with_generator(|generator| generator.next() >> 11);
+    return Ok(RuntimeVal::from(bits as f64 / (1u64 << 53) as f64))
+}
+
+fn native_choice(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.choice", &arguments, 1)?;
+    let items = arguments[0].get_list_value()?.items.borrow().clone();
+    if items.is_empty() {
+        return Err(ErrorVal::make_error("ValueError", String::from("Cannot choose from an empty list")).into())
+    }
+    return Ok(items[index_below(items.len())].clone())
+}
+
+// shuffles the list in place, Fisher-Yates style
+fn native_shuffle(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.shuffle", &arguments, 1)?;
+    let list = arguments[0].get_list_value()?;
+    let mut items = list.items.borrow_mut();
+    for last in (1..items.len()).rev() {
+        items.swap(last, index_below(last + 1));
+    }
+    return Ok(RuntimeVal::from(()))
+}
+
+// count distinct positions of the list, in the order they were drawn; the list is left alone
+fn native_sample(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("random.sample", &arguments, 2)?;
+    let mut items = arguments[0].get_list_value()?.items.borrow().clone();
+    let count = arguments[1].get_number_value()?;
+    if count < 0 || count as u64 > items.len() as u64 {
+        return Err(ErrorVal::make_error(
+            "ValueError",
+            format!("Cannot sample {} items from a list of {}", count, items.len())
+        ).into())
+    }
+    let count = count as usize;
+    for first in 0..count {
+        let chosen = first + index_below(items.len() - first);
+        items.swap(first, chosen);
+    }
+    items.truncate(count);
+    return list_result(items)
+}
@@ -0,0 +1,217 @@
+use std::path::PathBuf;
+use std::time::Instant;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use shtark::ast::{Program, Stmt};
+use shtark::{parse_source, tokenize, Engine, EngineError, RuntimeVal};
+use crate::report_uncaught;
+
+const HELP: &str = "\
+Enter Shtark code to run it; input continues on a '...' line until brackets are balanced.
+Commands:
+    :help           show this message
+    :env            list the bindings made in this session
+    :ast <code>     print the syntax tree of <code>
+    :tokens <code>  print the tokens of <code>
+    :load <file>    run a file in this session
+    :reset          forget every binding made in this session
+    :time <code>    run <code> and report how long it took
+    exit            leave the REPL (so does Ctrl-D)";
+
+enum Input {
+    Complete(Program, Vec<String>),
+    Incomplete,
+    Invalid(String),
+}
+
+// tells input that merely stops early apart from input that is wrong
+fn parse(source: &str) -> Input {
+    match parse_source(source) {
+        Ok((program, warnings)) => return Input::Complete(program, warnings),
+        Err(error) if error.incomplete => return Input::Incomplete,
+        Err(error) => return Input::Invalid(error.message),
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("SHTARK_HISTORY") {
+        return Some(PathBuf::from(path))
+    }
+    return std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".shtark_history"))
+}
+
+struct Repl {
+    engine: Engine,
+    exit: Option<i32>, // set once a script calls exit(), which ends the session
+    use_vm: bool,
+    opt_level: u8,
+}
+
+impl Repl {
+    // an engine with the flags the REPL was started with, also used again by :reset
+    fn new_engine(use_vm: bool, opt_level: u8) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_use_vm(use_vm);
+        engine.set_opt_level(opt_level);
+        return engine
+    }
+
+    fn report(&mut self, error: EngineError) {
+        match error {
+            EngineError::Runtime(error) => report_uncaught(error),
+            EngineError::Exit(status) => self.exit = Some(status),
+            error => println!("{}", error),
+        }
+    }
+
+    fn print_warnings(&mut self) {
+        for warning in self.engine.take_warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+
+    fn run(&mut self, program: Program, warnings: Vec<String>) {
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        // a declaration's value is what the user just typed, so only expressions are echoed
+        let declares = matches!(
+            program.body.last(),
+            None | Some(Stmt::VarDeclaration(_) | Stmt::FunctionDeclaration(_) | Stmt::ClassDeclaration(_)
+                | Stmt::EnumDeclaration(_) | Stmt::ImportDeclaration(_) | Stmt::ExportDeclaration(_))
+        );
+        let value = match self.engine.run(program) {
+            Ok(value) => value,
+            Err(error) => return self.report(error),
+        };
+        if !declares && !matches!(value, RuntimeVal::NullVal(_)) {
+            println!("{}", value.repr());
+        }
+    }
+
+    fn eval(&mut self, source: &str) {
+        match parse(source) {
+            Input::Complete(program, warnings) => self.run(program, warnings),
+            Input::Incomplete => println!("Incomplete input."),
+            Input::Invalid(message) => println!("{}", message),
+        }
+    }
+
+    fn print_env(&self) {
+        let bindings = self.engine.bindings();
+        if bindings.is_empty() {
+            println!("No bindings yet.");
+        }
+        for (name, value, constant) in bindings {
+            let keyword = if constant { "const" } else { "let" };
+            println!("{} {} = {}", keyword, name, value.repr());
+        }
+    }
+
+    fn command(&mut self, command: &str) {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "help" => println!("{}", HELP),
+            "env" => self.print_env(),
+            "ast" => {
+                match parse(argument) {
+                    Input::Complete(program, _) => println!("{:#?}", program),
+                    Input::Incomplete => println!("Incomplete input."),
+                    Input::Invalid(message) => println!("{}", message),
+                }
+            },
+            "tokens" => {
+                match tokenize(String::from(argument)) {
+                    Ok(tokens) => {
+                        for token in tokens {
+                            println!("{:?} {:?}", token.ttype, token.tvalue);
+                        }
+                    },
+                    Err(error) => println!("{}", error.message),
+                }
+            },
+            "load" => {
+                let result = self.engine.eval_file(argument);
+                self.print_warnings();
+                match result {
+                    Ok(_) => {},
+                    Err(EngineError::Io(error)) => println!("Cannot read {}: {}", argument, error),
+                    Err(error) => self.report(error),
+                }
+            },
+            "reset" => {
+                self.engine = Repl::new_engine(self.use_vm, self.opt_level);
+                println!("Session reset.");
+            },
+            "time" => {
+                let start = Instant::now();
+                self.eval(argument);
+                println!("Took {:?}", start.elapsed());
+            },
+            _ => println!("Unknown command :{}. Type :help for a list of commands.", name),
+        }
+    }
+}
+
+pub fn run(use_vm: bool, opt_level: u8) {
+    let mut editor = DefaultEditor::new().expect("Cannot set up the line editor");
+    let history = history_path();
+    if let Some(ref path) = history {
+        // a missing history file just means a first session
+        let _ = editor.load_history(path);
+    }
+    let mut repl = Repl { engine: Repl::new_engine(use_vm, opt_level), exit: None, use_vm, opt_level };
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { ">>>" } else { "..." };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C abandons the pending input, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            },
+            Err(_) => break,
+        };
+        if buffer.is_empty() {
+            let input = line.trim();
+            if input == "exit" {
+                break;
+            }
+            if input.is_empty() {
+                continue;
+            }
+            if let Some(command) = input.strip_prefix(':') {
+                let _ = editor.add_history_entry(input);
+                repl.command(command);
+                if repl.exit.is_some() {
+                    break;
+                }
+                continue;
+            }
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        match parse(&buffer) {
+            Input::Incomplete => continue,
+            Input::Invalid(message) => println!("{}", message),
+            Input::Complete(program, warnings) => repl.run(program, warnings),
+        }
+        let _ = editor.add_history_entry(buffer.trim_end());
+        buffer.clear();
+        if repl.exit.is_some() {
+            break;
+        }
+    }
+    if let Some(ref path) = history {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("Cannot save history to {}: {}", path.display(), error);
+        }
+    }
+    if let Some(status) = repl.exit {
+        std::process::exit(status);
+    }
+}
@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::ast::{Expr, Identifier, Pattern, Program, Stmt};
+use crate::environment::Environment;
+use crate::limits::grow_stack;
+use crate::values::ErrorVal;
+
+// mirrors one runtime environment: names are listed in the order their slots get filled
+struct Scope {
+    names: Vec<String>,
+    constants: Vec<bool>,
+    live: usize, // how many names have been declared at the current point of the walk
+    function: usize, // nesting level of the function the scope belongs to
+}
+
+struct Resolver {
+    scopes: Vec<Scope>,
+    function: usize,
+    errors: Vec<ErrorVal>,
+}
+
+// resolves every identifier in `program` to a (depth, slot) pair relative to `env`, returning
+// the names `env` will hold once the program has run, in slot order
+pub fn resolve(program: &mut Program, env: &Rc<RefCell<Environment>>) -> Result<Vec<(String, bool)>, ErrorVal> {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        function: 0,
+        errors: Vec::new(),
+    };
+    let mut chain = vec![Rc::clone(env)];
+    loop {
+        let parent = chain.last().unwrap().borrow().parent();
+        match parent {
+            Some(parent) => chain.push(parent),
+            None => break,
+        }
+    }
+    for env in chain.iter().rev() {
+        resolver.push_scope();
+        for declared in env.borrow().declared() {
+            match declared {
+                Some((name, constant)) => resolver.declare_live(name, constant),
+                None => resolver.skip_slot(),
+            }
+        }
+    }
+    resolver.resolve_body(&mut program.body);
+    if let Some(error) = resolver.errors.into_iter().next() {
+        return Err(error)
+    }
+    let scope = resolver.scopes.pop().unwrap();
+    return Ok(scope.names.into_iter().zip(scope.constants).collect())
+}
+
+// the names a statement declares in its own scope, in declaration order
+fn declarations(statement: &Stmt) -> Vec<(String, bool)> {
+    match statement {
+        Stmt::VarDeclaration(declaration) => return vec![(declaration.identifier.clone(), declaration.constant)],
+        Stmt::FunctionDeclaration(declaration) => return vec![(declaration.name.clone(), false)],
+        Stmt::ClassDeclaration(declaration) => return vec![(declaration.name.clone(), false)],
+        Stmt::EnumDeclaration(declaration) => return vec![(declaration.name.clone(), true)],
+        Stmt::ImportDeclaration(declaration) => {
+            let mut names: Vec<(String, bool)> = declaration.specifiers.iter().map(|(_, local)| (local.clone(), true)).collect();
+            if let Some(ref namespace) = declaration.namespace {
+                names.push((namespace.clone(), true));
+            }
+            return names
+        },
+        Stmt::ExportDeclaration(declaration) => return declarations(&declaration.declaration),
+        _ => return Vec::new(),
+    }
+}
+
+fn pattern_bindings(pattern: &Pattern, bindings: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) => bindings.push(name.clone()),
+        Pattern::Variant { fields, .. } => {
+            for field in fields {
+                pattern_bindings(field, bindings);
+            }
+        },
+        Pattern::Wildcard | Pattern::Literal(_) => {},
+    }
+}
+
+impl Resolver {
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope {
+            names: Vec::new(),
+            constants: Vec::new(),
+            live: 0,
+            function: self.function,
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: String, constant: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.names.contains(&name) {
+            self.errors.push(ErrorVal::make_error(
+                "NameError",
+                format!("Cannot declare variable {}. It has already been initialized.", name)
+            ));
+            return
+        }
+        scope.names.push(name);
+        scope.constants.push(constant);
+    }
+
+    fn declare_live(&mut self, name: String, constant: bool) {
+        self.declare(name, constant);
+        let scope = self.scopes.last_mut().unwrap();
+        scope.live = scope.names.len();
+    }
+
+    // a slot whose declaration was never reached still takes up its number; no identifier is
+    // empty, so nothing resolves to it
+    fn skip_slot(&mut self) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.names.push(String::new());
+        scope.constants.push(false);
+        scope.live = scope.names.len();
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<(usize, usize)> {
+        let mut declared_later = false;
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            // enclosing functions' scopes are complete by the time this body can run
+            let visible = if scope.function < self.function { scope.names.len() } else { scope.live };
+            if let Some(slot) = scope.names[..visible].iter().position(|declared| declared == name) {
+                return Some((depth, slot))
+            }
+            declared_later = declared_later || scope.names.iter().any(|declared| declared == name);
+        }
+        if declared_later {
+            self.errors.push(ErrorVal::make_error(
+                "NameError",
+                format!("Cannot access '{}' before its declaration.", name)
+            ));
+        }
+        return None
+    }
+
+    fn resolve_identifier(&mut self, identifier: &mut Identifier) {
+        identifier.slot = self.lookup(&identifier.symbol);
+    }
+
+    fn resolve_body(&mut self, body: &mut [Stmt]) {
+        for statement in body.iter() {
+            for (name, constant) in declarations(statement) {
+                self.declare(name, constant);
+            }
+        }
+        for statement in body.iter_mut() {
+            self.resolve_stmt(statement);
+            let declared = declarations(statement).len();
+            let scope = self.scopes.last_mut().unwrap();
+            scope.live = (scope.live + declared).min(scope.names.len());
+        }
+    }
+
+    fn resolve_scoped_body(&mut self, body: &mut [Stmt], bindings: Vec<String>) {
+        self.push_scope();
+        for name in bindings {
+            self.declare_live(name, false);
+        }
+        self.resolve_body(body);
+        self.pop_scope();
+    }
+
+    // methods run in a scope holding `this` and `super` between the class and the parameters
+    fn resolve_function(&mut self, parameters: &[String], body: &mut [Stmt], method: Option<bool>) {
+        self.function += 1;
+        if let Some(has_super) = method {
+            self.push_scope();
+            self.declare_live(String::from("this"), true);
+            if has_super {
+                self.declare_live(String::from("super"), true);
+            }
+        }
+        self.resolve_scoped_body(body, parameters.to_vec());
+        if method.is_some() {
+            self.pop_scope();
+        }
+        self.function -= 1;
+    }
+
+    fn resolve_stmt(&mut self, statement: &mut Stmt) {
+        grow_stack(|| self.resolve_stmt_node(statement))
+    }
+
+    fn resolve_stmt_node(&mut self, statement: &mut Stmt) {
+        match statement {
+            Stmt::Program(program) => self.resolve_body(&mut program.body),
+            Stmt::VarDeclaration(declaration) => {
+                if let Some(ref mut value) = declaration.value {
+                    self.resolve_expr(value);
+                }
+            },
+            Stmt::FunctionDeclaration(declaration) => {
+                self.resolve_function(&declaration.parameters, &mut declaration.body, None);
+            },
+            Stmt::ClassDeclaration(declaration) => {
+                if let Some(ref superclass) = declaration.superclass {
+                    self.lookup(superclass);
+                }
+                let has_super = declaration.superclass.is_some();
+                for method in declaration.methods.iter_mut().chain(declaration.static_methods.iter_mut()) {
+                    self.resolve_function(&method.parameters, &mut method.body, Some(has_super));
+                }
+            },
+            Stmt::EnumDeclaration(_) | Stmt::ImportDeclaration(_) | Stmt::Comment(_) => {},
+            Stmt::ExportDeclaration(declaration) => self.resolve_stmt(&mut declaration.declaration),
+            Stmt::ReturnStmt(stmt) => {
+                if let Some(ref mut argument) = stmt.argument {
+                    self.resolve_expr(argument);
+                }
+            },
+            Stmt::ThrowStmt(stmt) => self.resolve_expr(&mut stmt.argument),
+            Stmt::TryStmt(stmt) => {
+                self.resolve_scoped_body(&mut stmt.block, Vec::new());
+                if let Some(ref mut handler) = stmt.handler {
+                    self.resolve_scoped_body(handler, stmt.param.iter().cloned().collect());
+                }
+                if let Some(ref mut finalizer) = stmt.finalizer {
+                    self.resolve_scoped_body(finalizer, Vec::new());
+                }
+            },
+            Stmt::ForStmt(stmt) => {
+                self.resolve_expr(&mut stmt.iterable);
+                self.resolve_scoped_body(&mut stmt.body, vec![stmt.variable.clone()]);
+            },
+            Stmt::YieldStmt(stmt) => self.resolve_expr(&mut stmt.argument),
+            Stmt::Expr(expr) => self.resolve_expr(expr),
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        grow_stack(|| self.resolve_expr_node(expr))
+    }
+
+    fn resolve_expr_node(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::NumericLiteral(_) | Expr::FloatLiteral(_) | Expr::NullLiteral(_) | Expr::StringLiteral(_) | Expr::CaptureExpr(_) => {},
+            Expr::Identifier(identifier) => self.resolve_identifier(identifier),
+            Expr::BinaryExpr(binary_expr) => {
+                self.resolve_expr(&mut binary_expr.left);
+                self.resolve_expr(&mut binary_expr.right);
+            },
+            Expr::AssignmentExpr(assignment_expr) => {
+                if let Expr::Identifier(ref mut identifier) = assignment_expr.assigne {
+                    self.resolve_identifier(identifier);
+                    if let Some((depth, slot)) = identifier.slot {
+                        if self.scopes[self.scopes.len() - 1 - depth].constants[slot] {
+                            self.errors.push(ErrorVal::make_error(
+                                "TypeError",
+                                format!("Cannot reassign to variable {} as it is a constant.", identifier.symbol)
+                            ));
+                        }
+                    }
+                } else {
+                    self.resolve_expr(&mut assignment_expr.assigne);
+                }
+                self.resolve_expr(&mut assignment_expr.value);
+            },
+            Expr::MemberExpr(member_expr) => self.resolve_expr(&mut member_expr.object),
+            Expr::IndexExpr(index_expr) => {
+                self.resolve_expr(&mut index_expr.object);
+                self.resolve_expr(&mut index_expr.index);
+            },
+            Expr::ListExpr(list_expr) => {
+                for element in list_expr.elements.iter_mut() {
+                    self.resolve_expr(element);
+                }
+            },
+            Expr::CallExpr(call_expr) => {
+                self.resolve_expr(&mut call_expr.callee);
+                for argument in call_expr.arguments.iter_mut() {
+                    self.resolve_expr(argument);
+                }
+            },
+            Expr::NewExpr(new_expr) => {
+                self.resolve_expr(&mut new_expr.callee);
+                for argument in new_expr.arguments.iter_mut() {
+                    self.resolve_expr(argument);
+                }
+            },
+            Expr::FunctionExpr(function_expr) => {
+                self.resolve_function(&function_expr.parameters, &mut function_expr.body, None);
+            },
+            Expr::PropagateExpr(propagate_expr) => self.resolve_expr(&mut propagate_expr.argument),
+            Expr::MatchExpr(match_expr) => {
+                self.resolve_expr(&mut match_expr.subject);
+                for arm in match_expr.arms.iter_mut() {
+                    let mut bindings = Vec::new();
+                    pattern_bindings(&arm.pattern, &mut bindings);
+                    self.resolve_scoped_body(&mut arm.body, bindings);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::builtins::expect_args;
+use crate::interpreter::EvalResult;
+use crate::lexer::parse_number;
+use crate::limits;
+use crate::lists::list_result;
+use crate::printer::escape_string;
+use crate::values::{BooleanVal, ErrorVal, ResultVal, RuntimeVal, StringVal};
+
+fn value_error(message: String) -> ErrorVal {
+    return ErrorVal::make_error("ValueError", message)
+}
+
+fn string_result(value: String) -> EvalResult {
+    limits::allocate_string(value.len())?;
+    return Ok(StringVal::make_string(value).to_runtime_val())
+}
+
+// for methods whose result can dwarf their input: charged before it is built, so a huge one is refused
+// rather than allocated
+fn sized_string_result(length: usize, build: impl FnOnce() -> String) -> EvalResult {
+    limits::allocate_string(length)?;
+    return Ok(StringVal::make_string(build()).to_runtime_val())
+}
+
+fn bool_result(value: bool) -> EvalResult {
+    return Ok(BooleanVal::make_bool(value).to_runtime_val())
+}
+
+// searching for or splitting on "" has no sensible answer, so it is refused up front
+fn non_empty(method: &str, argument: &RuntimeVal) -> Result<String, ErrorVal> {
+    let value = argument.get_string_value()?;
+    if value.is_empty() {
+        return Err(value_error(format!("{} needs a non-empty string", method)))
+    }
+    return Ok(value)
+}
+
+// lengths, positions and widths all count Unicode scalar values rather than UTF-8 bytes
+pub fn call_string_method(string: String, method: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    match method {
+        "len" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(RuntimeVal::from(string.chars().count() as i64))
+        },
+        "split" => {
+            expect_args(method, &arguments, 1)?;
+            let separator = non_empty(method, &arguments[0])?;
+            return list_result(string.split(separator.as_str()).map(RuntimeVal::from).collect())
+        },
+        "join" => {
+            // the string is the separator, as in ", ".join(names); items print as `print` shows them
+            expect_args(method, &arguments, 1)?;
+            let items: Vec<String> = arguments[0].get_list_value()?.items.borrow().iter().map(RuntimeVal::to_string).collect();
+            return string_result(items.join(&string))
+        },
+        "trim" => {
+            expect_args(method, &arguments, 0)?;
+            return string_result(String::from(string.trim()))
+        },
+        "starts_with" => {
+            expect_args(method, &arguments, 1)?;
+            return bool_result(string.starts_with(arguments[0].get_string_value()?.as_str()))
+        },
+        "ends_with" => {
+            expect_args(method, &arguments, 1)?;
+            return bool_result(string.ends_with(arguments[0].get_string_value()?.as_str()))
+        },
+        "contains" => {
+            expect_args(method, &arguments, 1)?;
+            return bool_result(string.contains(arguments[0].get_string_value()?.as_str()))
+        },
+        "replace" => {
+            expect_args(method, &arguments, 2)?;
+            let from = non_empty(method, &arguments[0])?;
+            let to = arguments[1].get_string_value()?;
+            let length = string.len() + string.matches(from.as_str()).count().saturating_mul(to.len().saturating_sub(from.len()));
+            return sized_string_result(length, || string.replace(from.as_str(), &to))
+        },
+        "to_upper" => {
+            expect_args(method, &arguments, 0)?;
+            return string_result(string.to_uppercase())
+        },
+        "to_lower" => {
+            expect_args(method, &arguments, 0)?;
+            return string_result(string.to_lowercase())
+        },
+        "repeat" => {
+            expect_args(method, &arguments, 1)?;
+            let count = arguments[0].get_number_value()?;
+            let count = usize::try_from(count).map_err(|_| value_error(format!("Cannot repeat a string {} times", count)))?;
+            let length = match string.len().checked_mul(count) {
+                Some(length) if length <= isize::MAX as usize => length,
+                _ => return Err(ErrorVal::make_error("OverflowError", format!("Repeating a string {} times makes it too long", count)).into()),
+            };
+            return sized_string_result(length, || string.repeat(count))
+        },
+        "pad_start" => return pad_start(string, arguments),
+        "chars" => {
+            expect_args(method, &arguments, 0)?;
+            return list_result(string.chars().map(|c| RuntimeVal::from(String::from(c))).collect())
+        },
+        "bytes" => {
+            expect_args(method, &arguments, 0)?;
+            return list_result(string.bytes().map(|byte| RuntimeVal::from(i64::from(byte))).collect())
+        },
+        "parse_int" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(parse_int(&string).to_runtime_val())
+        },
+        "parse_float" => {
+            expect_args(method, &arguments, 0)?;
+            let parsed = match string.trim().parse::<f64>() {
+                Ok(value) => ResultVal::make_ok(RuntimeVal::from(value)),
+                Err(_) => ResultVal::make_err(RuntimeVal::from(format!("Cannot parse {} as a float", escape_string(&string)))),
+            };
+            return Ok(parsed.to_runtime_val())
+        },
+        "format" => return format(&string, arguments),
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("String has no method '{}'", method)
+            ).into())
+        },
+    }
+}
+
+// pads on the left to `width` characters, cycling through `fill` (a space by default)
+fn pad_start(string: String, arguments: Vec<RuntimeVal>) -> EvalResult {
+    if arguments.is_empty() || arguments.len() > 2 {
+        return Err(ErrorVal::make_error(
+            "TypeError",
+            format!("pad_start expects 1 or 2 argument(s) but received {}", arguments.len())
+        ).into())
+    }
+    let width = arguments[0].get_number_value()?;
+    let fill = match arguments.get(1) {
+        Some(fill) => non_empty("pad_start", fill)?,
+        None => String::from(" "),
+    };
+    let missing = usize::try_from(width).unwrap_or(0).saturating_sub(string.chars().count());
+    let length = string.len().saturating_add(missing.saturating_mul(fill.chars().map(char::len_utf8).max().unwrap_or(1)));
+    return sized_string_result(length, || {
+        let mut padded: String = fill.chars().cycle().take(missing).collect();
+        padded.push_str(&string);
+        return padded
+    })
+}
+
+// accepts what an integer literal accepts, such as 0xff or 1_000, after an optional sign
+fn parse_int(string: &str) -> ResultVal {
+    let trimmed = string.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let parsed = parse_number(digits).ok().and_then(|value| if negative { value.checked_neg() } else { Some(value) });
+    match parsed {
+        Some(value) => return ResultVal::make_ok(RuntimeVal::from(value)),
+        None => return ResultVal::make_err(RuntimeVal::from(format!("Cannot parse {} as an integer", escape_string(string)))),
+    }
+}
+
+// fills each `{}` with the next argument as `print` shows it; `{{` and `}}` stand for braces
+fn format(template: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    let mut out = String::new();
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                out.push(c);
+                chars.next();
+            },
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(argument) = arguments.get(placeholders) {
+                    out.push_str(&argument.to_string());
+                }
+                placeholders += 1;
+            },
+            ('{', _) | ('}', _) => {
+                return Err(value_error(format!("Unmatched '{}' in format string; write '{}{}' for a literal brace", c, c, c)).into())
+            },
+            _ => out.push(c),
+        }
+    }
+    if placeholders != arguments.len() {
+        return Err(value_error(format!("Format string has {} placeholder(s) but received {} argument(s)", placeholders, arguments.len())).into())
+    }
+    return string_result(out)
+}
@@ -0,0 +1,756 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::ast::BinaryOperator;
+use crate::builtins::expect_args;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::limits;
+use crate::values::{DateTimeVal, DurationVal, ErrorVal, InstantVal, ModuleVal, NativeFnVal, RuntimeVal, ValueType};
+
+type Native = fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+// datetimes are UTC until given another offset; durations are built from a count of some unit
+pub fn time_module() -> ModuleVal {
+    let functions: [(&str, Native); 13] = [
+        ("now", native_now),
+        ("instant", native_instant),
+        ("datetime", native_datetime),
+        ("from_unix", native_from_unix),
+        ("parse", native_parse),
+        ("nanoseconds", native_nanoseconds),
+        ("microseconds", native_microseconds),
+        ("milliseconds", native_milliseconds),
+        ("seconds", native_seconds),
+        ("minutes", native_minutes),
+        ("hours", native_hours),
+        ("days", native_days),
+        ("sleep", native_sleep),
+    ];
+    let mut exports: HashMap<String, RuntimeVal> = HashMap::new();
+    for (name, function) in functions {
+        exports.insert(String::from(name), NativeFnVal::make_native_fn(name, function).to_runtime_val());
+    }
+    return ModuleVal {
+        value_type: ValueType::Module,
+        path: String::from("time"),
+        exports: Rc::new(exports),
+    }
+}
+
+fn value_error(message: String) -> ErrorVal {
+    return ErrorVal::make_error("ValueError", message)
+}
+
+fn overflow_error(message: &str) -> ErrorVal {
+    return ErrorVal::make_error("OverflowError", String::from(message))
+}
+
+// days since 1970-01-01 in the proleptic Gregorian calendar, after Howard Hinnant's algorithm
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((i64::from(month) + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    return era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => return 29,
+        2 => return 28,
+        4 | 6 | 9 | 11 => return 30,
+        _ => return 31,
+    }
+}
+
+// a datetime split into calendar fields at its own offset
+struct Fields {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    nanos: u32,
+    offset: i32,
+}
+
+impl Fields {
+    fn of(datetime: &DateTimeVal) -> Fields {
+        let local = datetime.seconds + i64::from(datetime.offset);
+        let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+        let second_of_day = local.rem_euclid(SECONDS_PER_DAY) as u32;
+        return Fields {
+            year,
+            month,
+            day,
+            hour: second_of_day / 3600,
+            minute: second_of_day / 60 % 60,
+            second: second_of_day % 60,
+            nanos: datetime.nanos,
+            offset: datetime.offset,
+        }
+    }
+
+    fn to_datetime(&self) -> Result<DateTimeVal, ErrorVal> {
+        if !(0..=9999).contains(&self.year) || !(1..=12).contains(&self.month) || self.day == 0 || self.day > days_in_month(self.year, self.month) {
+            return Err(value_error(format!("Invalid date {:04}-{:02}-{:02}", self.year, self.month, self.day)))
+        }
+        if self.hour > 23 || self.minute > 59 || self.second > 59 {
+            return Err(value_error(format!("Invalid time {:02}:{:02}:{:02}", self.hour, self.minute, self.second)))
+        }
+        let local = days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + i64::from(self.hour * 3600 + self.minute * 60 + self.second);
+        return checked_datetime(local - i64::from(self.offset), self.nanos, self.offset)
+    }
+
+    fn weekday(&self) -> usize {
+        return (days_from_civil(self.year, self.month, self.day) + 3).rem_euclid(7) as usize
+    }
+
+    fn day_of_year(&self) -> i64 {
+        return days_from_civil(self.year, self.month, self.day) - days_from_civil(self.year, 1, 1) + 1
+    }
+}
+
+// keeps every datetime within the years 0 to 9999 at its own offset, so four digits always show the year
+fn checked_datetime(seconds: i64, nanos: u32, offset: i32) -> Result<DateTimeVal, ErrorVal> {
+    let local = seconds.checked_add(i64::from(offset));
+    let first = days_from_civil(0, 1, 1) * SECONDS_PER_DAY;
+    let last = days_from_civil(10_000, 1, 1) * SECONDS_PER_DAY - 1;
+    match local {
+        Some(local) if (first..=last).contains(&local) => return Ok(DateTimeVal::make_datetime(seconds, nanos, offset)),
+        _ => return Err(overflow_error("DateTime is out of range: years run from 0 to 9999")),
+    }
+}
+
+fn total_nanos(datetime: &DateTimeVal) -> i128 {
+    return i128::from(datetime.seconds) * i128::from(NANOS_PER_SECOND) + i128::from(datetime.nanos)
+}
+
+fn shift(datetime: &DateTimeVal, nanos: i128) -> Result<DateTimeVal, ErrorVal> {
+    let total = total_nanos(datetime) + nanos;
+    let seconds = i64::try_from(total.div_euclid(i128::from(NANOS_PER_SECOND)))
+        .map_err(|_| overflow_error("DateTime is out of range: years run from 0 to 9999"))?;
+    return checked_datetime(seconds, total.rem_euclid(i128::from(NANOS_PER_SECOND)) as u32, datetime.offset)
+}
+
+fn duration_result(nanos: Option<i128>) -> EvalResult {
+    match nanos.and_then(|nanos| i64::try_from(nanos).ok()) {
+        Some(nanos) => return Ok(DurationVal::make_duration(nanos).to_runtime_val()),
+        None => return Err(overflow_error("Duration is out of range: durations span about 292 years either way").into()),
+    }
+}
+
+fn format_offset(offset: i32, colon: bool) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.unsigned_abs() / 60;
+    let separator = if colon { ":" } else { "" };
+    return format!("{}{:02}{}{:02}", sign, minutes / 60, separator, minutes % 60)
+}
+
+// whole units, then as few fraction digits as the remainder needs
+fn decimal(value: u64, unit: u64) -> String {
+    let fraction = value % unit;
+    if fraction == 0 {
+        return (value / unit).to_string()
+    }
+    let width = unit.ilog10() as usize;
+    let digits = format!("{:0width$}", fraction, width = width);
+    return format!("{}.{}", value / unit, digits.trim_end_matches('0'))
+}
+
+// `2024-03-01T12:30:00Z`, with a fraction only when there is one and in groups of three digits
+pub fn format_rfc3339(datetime: &DateTimeVal) -> String {
+    let fields = Fields::of(datetime);
+    let mut text = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        fields.year, fields.month, fields.day, fields.hour, fields.minute, fields.second
+    );
+    if fields.nanos != 0 {
+        let digits = format!("{:09}", fields.nanos);
+        let width = digits.trim_end_matches('0').len().div_ceil(3) * 3;
+        text.push('.');
+        text.push_str(&digits[..width]);
+    }
+    if fields.offset == 0 {
+        text.push('Z');
+    } else {
+        text.push_str(&format_offset(fields.offset, true));
+    }
+    return text
+}
+
+// `1h30m`, `2.5s`, `-750ms`: the largest units that fit, like Go prints its durations
+pub fn format_duration(nanos: i64) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    if nanos == 0 {
+        return String::from("0s")
+    } else if nanos < 1_000 {
+        return format!("{}{}ns", sign, nanos)
+    } else if nanos < 1_000_000 {
+        return format!("{}{}us", sign, decimal(nanos, 1_000))
+    } else if nanos < NANOS_PER_SECOND as u64 {
+        return format!("{}{}ms", sign, decimal(nanos, 1_000_000))
+    }
+    let hours = nanos / (3600 * NANOS_PER_SECOND as u64);
+    let minutes = nanos / (60 * NANOS_PER_SECOND as u64) % 60;
+    let mut text = String::from(sign);
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if hours > 0 || minutes > 0 {
+        text.push_str(&format!("{}m", minutes));
+    }
+    text.push_str(&format!("{}s", decimal(nanos % (60 * NANOS_PER_SECOND as u64), NANOS_PER_SECOND as u64)));
+    return text
+}
+
+// the strftime directives: %Y %m %d %H %M %S, %f for microseconds, %j for the day of the year,
+// %a %A %b %B for English names, %z and %:z for the offset, and %% for a percent sign
+pub fn format_pattern(datetime: &DateTimeVal, pattern: &str) -> Result<String, ErrorVal> {
+    let fields = Fields::of(datetime);
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => text.push_str(&format!("{:04}", fields.year)),
+            Some('m') => text.push_str(&format!("{:02}", fields.month)),
+            Some('d') => text.push_str(&format!("{:02}", fields.day)),
+            Some('H') => text.push_str(&format!("{:02}", fields.hour)),
+            Some('M') => text.push_str(&format!("{:02}", fields.minute)),
+            Some('S') => text.push_str(&format!("{:02}", fields.second)),
+            Some('f') => text.push_str(&format!("{:06}", fields.nanos / 1_000)),
+            Some('j') => text.push_str(&format!("{:03}", fields.day_of_year())),
+            Some('a') => text.push_str(&WEEKDAYS[fields.weekday()][..3]),
+            Some('A') => text.push_str(WEEKDAYS[fields.weekday()]),
+            Some('b') => text.push_str(&MONTHS[fields.month as usize - 1][..3]),
+            Some('B') => text.push_str(MONTHS[fields.month as usize - 1]),
+            Some('z') => text.push_str(&format_offset(fields.offset, false)),
+            Some(':') if chars.next() == Some('z') => text.push_str(&format_offset(fields.offset, true)),
+            Some('%') => text.push('%'),
+            Some(other) => return Err(value_error(format!("Unknown format directive '%{}' in '{}'", other, pattern))),
+            None => return Err(value_error(format!("The pattern '{}' ends in a lone '%'", pattern))),
+        }
+    }
+    return Ok(text)
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Cursor {
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: &[char]) -> bool {
+        if self.peek().is_some_and(|c| expected.contains(&c)) {
+            self.position += 1;
+            return true
+        }
+        return false
+    }
+
+    fn number(&mut self, min: usize, max: usize) -> Option<u32> {
+        let start = self.position;
+        while self.position - start < max && self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position - start < min {
+            return None
+        }
+        return self.chars[start..self.position].iter().collect::<String>().parse().ok()
+    }
+
+    // up to nine digits after the point are kept; later ones are below a nanosecond
+    fn fraction(&mut self) -> Option<u32> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position == start {
+            return None
+        }
+        let digits: String = self.chars[start..self.position].iter().take(9).collect();
+        return format!("{:0<9}", digits).parse().ok()
+    }
+
+    // `Z`, `+hh:mm` or `+hhmm`
+    fn offset(&mut self) -> Option<i32> {
+        if self.eat(&['Z', 'z']) {
+            return Some(0)
+        }
+        let sign = match self.peek() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return None,
+        };
+        self.position += 1;
+        let hours = self.number(2, 2)?;
+        self.eat(&[':']);
+        let minutes = self.number(2, 2)?;
+        if hours > 23 || minutes > 59 {
+            return None
+        }
+        return Some(sign * (hours * 3600 + minutes * 60) as i32)
+    }
+
+    // the index of the name that comes next, ignoring case
+    fn name(&mut self, names: &[&str], short: bool) -> Option<usize> {
+        let rest: String = self.chars[self.position..].iter().collect::<String>().to_lowercase();
+        for (index, name) in names.iter().enumerate() {
+            let name = if short { &name[..3] } else { name };
+            if rest.starts_with(&name.to_lowercase()) {
+                self.position += name.len();
+                return Some(index)
+            }
+        }
+        return None
+    }
+}
+
+fn empty_fields() -> Fields {
+    return Fields { year: 1970, month: 1, day: 1, hour: 0, minute: 0, second: 0, nanos: 0, offset: 0 }
+}
+
+pub fn parse_rfc3339(text: &str) -> Result<DateTimeVal, ErrorVal> {
+    let mut cursor = Cursor { chars: text.chars().collect(), position: 0 };
+    let mut fields = empty_fields();
+    let parsed = (|| -> Option<()> {
+        fields.year = i64::from(cursor.number(4, 4)?);
+        cursor.eat(&['-']).then_some(())?;
+        fields.month = cursor.number(2, 2)?;
+        cursor.eat(&['-']).then_some(())?;
+        fields.day = cursor.number(2, 2)?;
+        cursor.eat(&['T', 't', ' ']).then_some(())?;
+        fields.hour = cursor.number(2, 2)?;
+        cursor.eat(&[':']).then_some(())?;
+        fields.minute = cursor.number(2, 2)?;
+        cursor.eat(&[':']).then_some(())?;
+        fields.second = cursor.number(2, 2)?;
+        if cursor.eat(&['.']) {
+            fields.nanos = cursor.fraction()?;
+        }
+        fields.offset = cursor.offset()?;
+        return (cursor.position == cursor.chars.len()).then_some(())
+    })();
+    if parsed.is_none() {
+        return Err(value_error(format!("Cannot parse '{}' as an RFC 3339 date and time", text)))
+    }
+    return fields.to_datetime()
+}
+
+// the directives of format_pattern except %j; fields the pattern leaves out default to
+// 1970-01-01T00:00:00Z
+pub fn parse_pattern(text: &str, pattern: &str) -> Result<DateTimeVal, ErrorVal> {
+    let mut cursor = Cursor { chars: text.chars().collect(), position: 0 };
+    let mut fields = empty_fields();
+    let mismatch = || value_error(format!("Cannot parse '{}' with the pattern '{}'", text, pattern));
+    let mut directives = pattern.chars();
+    while let Some(c) = directives.next() {
+        if c != '%' {
+            if !cursor.eat(&[c]) {
+                return Err(mismatch())
+            }
+            continue;
+        }
+        let matched = match directives.next() {
+            Some('Y') => cursor.number(4, 4).map(|year| fields.year = i64::from(year)),
+            Some('m') => cursor.number(1, 2).map(|month| fields.month = month),
+            Some('d') => cursor.number(1, 2).map(|day| fields.day = day),
+            Some('H') => cursor.number(1, 2).map(|hour| fields.hour = hour),
+            Some('M') => cursor.number(1, 2).map(|minute| fields.minute = minute),
+            Some('S') => cursor.number(1, 2).map(|second| fields.second = second),
+            Some('f') => cursor.fraction().map(|nanos| fields.nanos = nanos),
+            Some('a') => cursor.name(&WEEKDAYS, true).map(|_| ()),
+            Some('A') => cursor.name(&WEEKDAYS, false).map(|_| ()),
+            Some('b') => cursor.name(&MONTHS, true).map(|month| fields.month = month as u32 + 1),
+            Some('B') => cursor.name(&MONTHS, false).map(|month| fields.month = month as u32 + 1),
+            Some('z') => cursor.offset().map(|offset| fields.offset = offset),
+            Some(':') if directives.next() == Some('z') => cursor.offset().map(|offset| fields.offset = offset),
+            Some('%') => cursor.eat(&['%']).then_some(()),
+            Some(other) => return Err(value_error(format!("Unknown parse directive '%{}' in '{}'", other, pattern))),
+            None => return Err(value_error(format!("The pattern '{}' ends in a lone '%'", pattern))),
+        };
+        if matched.is_none() {
+            return Err(mismatch())
+        }
+    }
+    if cursor.position != cursor.chars.len() {
+        return Err(mismatch())
+    }
+    return fields.to_datetime()
+}
+
+// `+` and `-` move datetimes and instants by durations and measure the gap between two of them;
+// durations also add up and scale by numbers
+pub fn time_arithmetic(lhs: RuntimeVal, rhs: RuntimeVal, operator: BinaryOperator) -> EvalResult {
+    match (&lhs, &rhs, operator) {
+        (RuntimeVal::DateTimeVal(datetime), RuntimeVal::DurationVal(duration), BinaryOperator::Add)
+        | (RuntimeVal::DurationVal(duration), RuntimeVal::DateTimeVal(datetime), BinaryOperator::Add) => {
+            return Ok(shift(datetime, i128::from(duration.nanos))?.to_runtime_val())
+        },
+        (RuntimeVal::DateTimeVal(datetime), RuntimeVal::DurationVal(duration), BinaryOperator::Subtract) => {
+            return Ok(shift(datetime, -i128::from(duration.nanos))?.to_runtime_val())
+        },
+        (RuntimeVal::DateTimeVal(later), RuntimeVal::DateTimeVal(earlier), BinaryOperator::Subtract) => {
+            return duration_result(Some(total_nanos(later) - total_nanos(earlier)))
+        },
+        (RuntimeVal::DurationVal(a), RuntimeVal::DurationVal(b), BinaryOperator::Add) => return duration_result(Some(i128::from(a.nanos) + i128::from(b.nanos))),
+        (RuntimeVal::DurationVal(a), RuntimeVal::DurationVal(b), BinaryOperator::Subtract) => return duration_result(Some(i128::from(a.nanos) - i128::from(b.nanos))),
+        (RuntimeVal::DurationVal(a), RuntimeVal::DurationVal(b), BinaryOperator::Divide) => {
+            if b.nanos == 0 {
+                return Err(ErrorVal::make_error("ZeroDivisionError", String::from("Division by zero.")).into())
+            }
+            return Ok(RuntimeVal::from(a.nanos as f64 / b.nanos as f64))
+        },
+        (RuntimeVal::DurationVal(duration), RuntimeVal::NumberVal(_) | RuntimeVal::FloatVal(_), BinaryOperator::Multiply | BinaryOperator::Divide)
+        | (RuntimeVal::NumberVal(_) | RuntimeVal::FloatVal(_), RuntimeVal::DurationVal(duration), BinaryOperator::Multiply) => {
+            let factor = if let RuntimeVal::DurationVal(_) = lhs { &rhs } else { &lhs };
+            if let RuntimeVal::NumberVal(factor) = factor {
+                if operator == BinaryOperator::Multiply {
+                    return duration_result(Some(i128::from(duration.nanos) * i128::from(factor.value)))
+                } else if factor.value != 0 {
+                    return duration_result(Some(i128::from(duration.nanos) / i128::from(factor.value)))
+                }
+            }
+            let factor = factor.get_float_value()?;
+            if operator == BinaryOperator::Divide && factor == 0.0 {
+                return Err(ErrorVal::make_error("ZeroDivisionError", String::from("Division by zero.")).into())
+            }
+            let scaled = if operator == BinaryOperator::Multiply { duration.nanos as f64 * factor } else { duration.nanos as f64 / factor };
+            return duration_from_float(scaled)
+        },
+        (RuntimeVal::InstantVal(instant), RuntimeVal::DurationVal(duration), BinaryOperator::Add | BinaryOperator::Subtract) => {
+            let forward = (duration.nanos >= 0) == (operator == BinaryOperator::Add);
+            let span = std::time::Duration::from_nanos(duration.nanos.unsigned_abs());
+            let moved = if forward { instant.instant.checked_add(span) } else { instant.instant.checked_sub(span) };
+            match moved {
+                Some(moved) => return Ok(InstantVal::make_instant(moved).to_runtime_val()),
+                None => return Err(overflow_error("Instant is out of range").into()),
+            }
+        },
+        (RuntimeVal::InstantVal(later), RuntimeVal::InstantVal(earlier), BinaryOperator::Subtract) => {
+            return duration_result(Some(signed_gap(later.instant, earlier.instant)))
+        },
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("Unsupported operand types for {}: {:?} and {:?}", operator, lhs.get_value_type(), rhs.get_value_type())
+            ).into())
+        },
+    }
+}
+
+fn signed_gap(later: Instant, earlier: Instant) -> i128 {
+    if later >= earlier {
+        return (later - earlier).as_nanos() as i128
+    }
+    return -((earlier - later).as_nanos() as i128)
+}
+
+fn duration_from_float(nanos: f64) -> EvalResult {
+    if !nanos.is_finite() || nanos.abs() >= i64::MAX as f64 {
+        return duration_result(None)
+    }
+    return Ok(DurationVal::make_duration(nanos.round() as i64).to_runtime_val())
+}
+
+// how two time values of the same kind order, for sort and the compare methods
+pub fn compare_times(lhs: &RuntimeVal, rhs: &RuntimeVal) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (RuntimeVal::DurationVal(lhs), RuntimeVal::DurationVal(rhs)) => return Some(lhs.nanos.cmp(&rhs.nanos)),
+        (RuntimeVal::DateTimeVal(lhs), RuntimeVal::DateTimeVal(rhs)) => return Some(total_nanos(lhs).cmp(&total_nanos(rhs))),
+        (RuntimeVal::InstantVal(lhs), RuntimeVal::InstantVal(rhs)) => return Some(lhs.instant.cmp(&rhs.instant)),
+        _ => return None,
+    }
+}
+
+fn ordering(object: &RuntimeVal, method: &str, other: &RuntimeVal) -> Result<Ordering, ErrorVal> {
+    return compare_times(object, other).ok_or_else(|| {
+        ErrorVal::make_error(
+            "TypeError",
+            format!("{} expects a {:?} but received {:?}", method, object.get_value_type(), other.get_value_type())
+        )
+    })
+}
+
+pub fn call_time_method(object: RuntimeVal, method: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    match method {
+        // -1, 0 or 1 as the value is before, at or after the other
+        "compare" => {
+            expect_args(method, &arguments, 1)?;
+            return Ok(RuntimeVal::from(ordering(&object, method, &arguments[0])? as i64))
+        },
+        "is_before" => {
+            expect_args(method, &arguments, 1)?;
+            return Ok(RuntimeVal::from(ordering(&object, method, &arguments[0])? == Ordering::Less))
+        },
+        "is_after" => {
+            expect_args(method, &arguments, 1)?;
+            return Ok(RuntimeVal::from(ordering(&object, method, &arguments[0])? == Ordering::Greater))
+        },
+        _ => {},
+    }
+    match object {
+        RuntimeVal::DateTimeVal(datetime) => return call_datetime_method(datetime, method, arguments),
+        RuntimeVal::DurationVal(duration) => return call_duration_method(duration, method, arguments),
+        RuntimeVal::InstantVal(instant) => return call_instant_method(instant, method, arguments),
+        _ => unreachable!("only time values have time methods"),
+    }
+}
+
+fn no_method(type_name: &str, method: &str) -> EvalResult {
+    return Err(ErrorVal::make_error("TypeError", format!("{} has no method '{}'", type_name, method)).into())
+}
+
+fn call_datetime_method(datetime: DateTimeVal, method: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    let fields = Fields::of(&datetime);
+    let field = match method {
+        "year" => Some(fields.year),
+        "month" => Some(i64::from(fields.month)),
+        "day" => Some(i64::from(fields.day)),
+        "hour" => Some(i64::from(fields.hour)),
+        "minute" => Some(i64::from(fields.minute)),
+        "second" => Some(i64::from(fields.second)),
+        "nanosecond" => Some(i64::from(fields.nanos)),
+        // 1 for Monday through 7 for Sunday, as in ISO 8601
+        "weekday" => Some(fields.weekday() as i64 + 1),
+        "day_of_year" => Some(fields.day_of_year()),
+        "unix" => Some(datetime.seconds),
+        "unix_millis" => Some(datetime.seconds * 1_000 + i64::from(datetime.nanos / 1_000_000)),
+        _ => None,
+    };
+    if let Some(field) = field {
+        expect_args(method, &arguments, 0)?;
+        return Ok(RuntimeVal::from(field))
+    }
+    match method {
+        "offset" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(DurationVal::make_duration(i64::from(datetime.offset) * NANOS_PER_SECOND).to_runtime_val())
+        },
+        "format" => {
+            expect_args(method, &arguments, 1)?;
+            return Ok(RuntimeVal::from(format_pattern(&datetime, &arguments[0].get_string_value()?)?))
+        },
+        "to_rfc3339" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(RuntimeVal::from(format_rfc3339(&datetime)))
+        },
+        "to_utc" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(DateTimeVal::make_datetime(datetime.seconds, datetime.nanos, 0).to_runtime_val())
+        },
+        // the same moment seen from another offset, given as a duration of whole minutes
+        "with_offset" => {
+            expect_args(method, &arguments, 1)?;
+            let offset = match &arguments[0] {
+                RuntimeVal::DurationVal(offset) => offset.nanos,
+                other => {
+                    return Err(ErrorVal::make_error(
+                        "TypeError",
+                        format!("with_offset expects a Duration but received {:?}", other.get_value_type())
+                    ).into())
+                },
+            };
+            if offset % (60 * NANOS_PER_SECOND) != 0 || offset.abs() >= SECONDS_PER_DAY * NANOS_PER_SECOND {
+                return Err(value_error(format!("An offset must be whole minutes less than a day either way, not {}", format_duration(offset))).into())
+            }
+            return Ok(checked_datetime(datetime.seconds, datetime.nanos, (offset / NANOS_PER_SECOND) as i32)?.to_runtime_val())
+        },
+        _ => return no_method("DateTime", method),
+    }
+}
+
+fn call_duration_method(duration: DurationVal, method: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    // whole units, truncated toward zero
+    let unit = match method {
+        "nanoseconds" => Some(1),
+        "microseconds" => Some(1_000),
+        "milliseconds" => Some(1_000_000),
+        "seconds" => Some(NANOS_PER_SECOND),
+        "minutes" => Some(60 * NANOS_PER_SECOND),
+        "hours" => Some(3600 * NANOS_PER_SECOND),
+        "days" => Some(SECONDS_PER_DAY * NANOS_PER_SECOND),
+        _ => None,
+    };
+    if let Some(unit) = unit {
+        expect_args(method, &arguments, 0)?;
+        return Ok(RuntimeVal::from(duration.nanos / unit))
+    }
+    match method {
+        "as_seconds" => {
+            expect_args(method, &arguments, 0)?;
+            return Ok(RuntimeVal::from(duration.nanos as f64 / NANOS_PER_SECOND as f64))
+        },
+        "abs" => {
+            expect_args(method, &arguments, 0)?;
+            return duration_result(Some(i128::from(duration.nanos).abs()))
+        },
+        _ => return no_method("Duration", method),
+    }
+}
+
+fn call_instant_method(instant: InstantVal, method: &str, arguments: Vec<RuntimeVal>) -> EvalResult {
+    match method {
+        "elapsed" => {
+            expect_args(method, &arguments, 0)?;
+            return duration_result(Some(signed_gap(Instant::now(), instant.instant)))
+        },
+        _ => return no_method("Instant", method),
+    }
+}
+
+fn native_now(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("time.now", &arguments, 0)?;
+    let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i128,
+        Err(error) => -(error.duration().as_nanos() as i128),
+    };
+    return Ok(shift(&DateTimeVal::make_datetime(0, 0, 0), nanos)?.to_runtime_val())
+}
+
+fn native_instant(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("time.instant", &arguments, 0)?;
+    return Ok(InstantVal::make_instant(Instant::now()).to_runtime_val())
+}
+
+// time.datetime(year, month, day) with an optional hour, minute and second, in UTC
+fn native_datetime(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    if !(3..=6).contains(&arguments.len()) {
+        return Err(ErrorVal::make_error(
+            "TypeError",
+            format!("time.datetime expects 3 to 6 argument(s) but received {}", arguments.len())
+        ).into())
+    }
+    let mut parts = Vec::new();
+    for argument in &arguments {
+        parts.push(argument.get_number_value()?);
+    }
+    parts.resize(6, 0);
+    let field = |value: i64| u32::try_from(value).unwrap_or(u32::MAX);
+    let fields = Fields {
+        year: parts[0],
+        month: field(parts[1]),
+        day: field(parts[2]),
+        hour: field(parts[3]),
+        minute: field(parts[4]),
+        second: field(parts[5]),
+        nanos: 0,
+        offset: 0,
+    };
+    return Ok(fields.to_datetime()?.to_runtime_val())
+}
+
+// whole or fractional seconds since the Unix epoch
+fn native_from_unix(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("time.from_unix", &arguments, 1)?;
+    let nanos = match &arguments[0] {
+        RuntimeVal::NumberVal(seconds) => i128::from(seconds.value) * i128::from(NANOS_PER_SECOND),
+        other => {
+            let nanos = other.get_float_value()? * NANOS_PER_SECOND as f64;
+            if !nanos.is_finite() || nanos.abs() > 1e30 {
+                return Err(overflow_error("DateTime is out of range: years run from 0 to 9999").into())
+            }
+            nanos.round() as i128
+        },
+    };
+    return Ok(shift(&DateTimeVal::make_datetime(0, 0, 0), nanos)?.to_runtime_val())
+}
+
+// time.parse(text) reads RFC 3339; time.parse(text, pattern) uses strftime directives
+fn native_parse(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    match arguments.as_slice() {
+        [text] => return Ok(parse_rfc3339(&text.get_string_value()?)?.to_runtime_val()),
+        [text, pattern] => return Ok(parse_pattern(&text.get_string_value()?, &pattern.get_string_value()?)?.to_runtime_val()),
+        _ => {
+            return Err(ErrorVal::make_error(
+                "TypeError",
+                format!("time.parse expects 1 or 2 argument(s) but received {}", arguments.len())
+            ).into())
+        },
+    }
+}
+
+// an integer or float count of the unit
+fn duration_of(name: &str, arguments: Vec<RuntimeVal>, unit: i64) -> EvalResult {
+    expect_args(name, &arguments, 1)?;
+    match &arguments[0] {
+        RuntimeVal::NumberVal(count) => return duration_result(Some(i128::from(count.value) * i128::from(unit))),
+        other => return duration_from_float(other.get_float_value()? * unit as f64),
+    }
+}
+
+fn native_nanoseconds(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.nanoseconds", arguments, 1)
+}
+
+fn native_microseconds(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.microseconds", arguments, 1_000)
+}
+
+fn native_milliseconds(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.milliseconds", arguments, 1_000_000)
+}
+
+fn native_seconds(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.seconds", arguments, NANOS_PER_SECOND)
+}
+
+fn native_minutes(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.minutes", arguments, 60 * NANOS_PER_SECOND)
+}
+
+fn native_hours(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.hours", arguments, 3600 * NANOS_PER_SECOND)
+}
+
+fn native_days(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    return duration_of("time.days", arguments, SECONDS_PER_DAY * NANOS_PER_SECOND)
+}
+
+// sleep(ms) takes milliseconds as a number, or a Duration; timeouts and interrupts still apply
+pub fn native_sleep(arguments: Vec<RuntimeVal>, _env: &Rc<RefCell<Environment>>) -> EvalResult {
+    expect_args("sleep", &arguments, 1)?;
+    let nanos = match &arguments[0] {
+        RuntimeVal::DurationVal(duration) => duration.nanos,
+        RuntimeVal::NumberVal(millis) => millis.value.saturating_mul(1_000_000),
+        other => (other.get_float_value()? * 1e6) as i64,
+    };
+    if nanos < 0 {
+        return Err(value_error(format!("sleep needs a duration of at least 0, not {}", format_duration(nanos))).into())
+    }
+    limits::sleep(std::time::Duration::from_nanos(nanos as u64))?;
+    return Ok(RuntimeVal::from(()))
+}
@@ -1,8 +1,36 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Instant;
+use crate::ast::Stmt;
+use crate::compiler::Chunk;
+use crate::environment::Environment;
+use crate::interpreter::EvalResult;
+use crate::iterators::Source;
+use crate::printer::escape_string;
+use crate::time::{format_duration, format_rfc3339};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ValueType {
     Null,
     Number,
+    Float,
     Boolean,
+    String,
+    List,
+    Iterator,
+    Duration,
+    DateTime,
+    Instant,
+    Error,
+    Result,
+    Function,
+    Class,
+    Instance,
+    Enum,
+    Variant,
+    Module,
 }
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Value {
@@ -14,7 +42,24 @@ pub enum Value {
 pub enum RuntimeVal {
     NullVal(NullVal),
     NumberVal(NumberVal),
+    FloatVal(FloatVal),
     BooleanVal(BooleanVal),
+    StringVal(StringVal),
+    ListVal(ListVal),
+    IteratorVal(IteratorVal),
+    DurationVal(DurationVal),
+    DateTimeVal(DateTimeVal),
+    InstantVal(InstantVal),
+    ErrorVal(ErrorVal),
+    ResultVal(ResultVal),
+    FunctionVal(FunctionVal),
+    NativeFnVal(NativeFnVal),
+    ClassVal(ClassVal),
+    InstanceVal(InstanceVal),
+    EnumVal(EnumVal),
+    VariantVal(VariantVal),
+    VariantCtorVal(VariantCtorVal),
+    ModuleVal(ModuleVal),
 }
 
 impl RuntimeVal {
@@ -22,46 +67,201 @@ impl RuntimeVal {
         match self {
             RuntimeVal::NullVal(_) => ValueType::Null,
             RuntimeVal::NumberVal(_) => ValueType::Number,
+            RuntimeVal::FloatVal(_) => ValueType::Float,
             RuntimeVal::BooleanVal(_) => ValueType::Boolean,
+            RuntimeVal::StringVal(_) => ValueType::String,
+            RuntimeVal::ListVal(_) => ValueType::List,
+            RuntimeVal::IteratorVal(_) => ValueType::Iterator,
+            RuntimeVal::DurationVal(_) => ValueType::Duration,
+            RuntimeVal::DateTimeVal(_) => ValueType::DateTime,
+            RuntimeVal::InstantVal(_) => ValueType::Instant,
+            RuntimeVal::ErrorVal(_) => ValueType::Error,
+            RuntimeVal::ResultVal(_) => ValueType::Result,
+            RuntimeVal::FunctionVal(_) => ValueType::Function,
+            RuntimeVal::NativeFnVal(_) => ValueType::Function,
+            RuntimeVal::ClassVal(_) => ValueType::Class,
+            RuntimeVal::InstanceVal(_) => ValueType::Instance,
+            RuntimeVal::EnumVal(_) => ValueType::Enum,
+            RuntimeVal::VariantVal(_) => ValueType::Variant,
+            RuntimeVal::VariantCtorVal(_) => ValueType::Function,
+            RuntimeVal::ModuleVal(_) => ValueType::Module,
         }
     }
-    pub fn get_number_value(&self) -> i64 {
+    fn type_error(&self, expected: &str) -> ErrorVal {
+        return ErrorVal::make_error(
+            "TypeError",
+            format!("Expected a {} but found {:?}", expected, self.get_value_type())
+        )
+    }
+    pub fn get_number_value(&self) -> Result<i64, ErrorVal> {
         if let RuntimeVal::NumberVal(number_val) = self {
-            return number_val.value
+            return Ok(number_val.value)
         } else {
-            panic!("Not a NumberVal");
+            return Err(self.type_error("Number"))
+        }
+    }
+    // integers widen, so anything that takes a float also takes an integer
+    pub fn get_float_value(&self) -> Result<f64, ErrorVal> {
+        match self {
+            RuntimeVal::NumberVal(number_val) => return Ok(number_val.value as f64),
+            RuntimeVal::FloatVal(float_val) => return Ok(float_val.value),
+            _ => return Err(self.type_error("Number or Float")),
         }
     }
-    pub fn get_null_value(&self) -> Value {
+    pub fn get_null_value(&self) -> Result<Value, ErrorVal> {
         if let RuntimeVal::NullVal(null_val) = self {
-            return null_val.value.clone()
+            return Ok(null_val.value)
         } else {
-            panic!("Not a NullVall")
+            return Err(self.type_error("Null"))
         }
     }
-    pub fn get_bool_val(&self) -> bool {
+    pub fn get_bool_val(&self) -> Result<bool, ErrorVal> {
         if let RuntimeVal::BooleanVal(bool_val) = self {
-            return bool_val.value.clone()
+            return Ok(bool_val.value)
         } else {
-            panic!("Not a BooleanVal")
+            return Err(self.type_error("Boolean"))
         }
     }
-    pub fn to_number_val(&self) -> NumberVal {
-        return NumberVal {
-            value_type: ValueType::Number,
-            value: self.get_number_value(),
+    pub fn get_string_value(&self) -> Result<String, ErrorVal> {
+        if let RuntimeVal::StringVal(string_val) = self {
+            return Ok(string_val.value.clone())
+        } else {
+            return Err(self.type_error("String"))
         }
     }
-    pub fn to_null_val(&self) -> NullVal {
-        return NullVal {
-            value_type: ValueType::Null,
-            value: self.get_null_value(),
+    pub fn get_list_value(&self) -> Result<ListVal, ErrorVal> {
+        if let RuntimeVal::ListVal(list_val) = self {
+            return Ok(list_val.clone())
+        } else {
+            return Err(self.type_error("List"))
         }
     }
-    pub fn to_boolean_val(&self) -> BooleanVal {
-        return BooleanVal {
+    pub fn to_number_val(&self) -> Result<NumberVal, ErrorVal> {
+        return Ok(NumberVal {
+            value_type: ValueType::Number,
+            value: self.get_number_value()?,
+        })
+    }
+    pub fn to_null_val(&self) -> Result<NullVal, ErrorVal> {
+        return Ok(NullVal {
+            value_type: ValueType::Null,
+            value: self.get_null_value()?,
+        })
+    }
+    pub fn to_boolean_val(&self) -> Result<BooleanVal, ErrorVal> {
+        return Ok(BooleanVal {
             value_type: ValueType::Boolean,
-            value: self.get_bool_val(),
+            value: self.get_bool_val()?,
+        })
+    }
+    pub fn to_string_val(&self) -> Result<StringVal, ErrorVal> {
+        return Ok(StringVal {
+            value_type: ValueType::String,
+            value: self.get_string_value()?,
+        })
+    }
+}
+
+// instances and lists already being written, so a value pointing back at its owner ends the walk
+type Seen = Vec<*const ()>;
+
+impl RuntimeVal {
+    // the REPL echo form: strings are quoted so `"1"` and `1` look different, and nested values
+    // are written the same way
+    pub fn repr(&self) -> String {
+        let mut out = String::new();
+        self.write_repr(&mut out, &mut Vec::new());
+        return out
+    }
+
+    fn write_repr(&self, out: &mut String, seen: &mut Seen) {
+        match self {
+            RuntimeVal::StringVal(string_val) => out.push_str(&escape_string(&string_val.value)),
+            RuntimeVal::ResultVal(result) => {
+                out.push_str(if result.is_ok { "Ok(" } else { "Err(" });
+                result.value.write_repr(out, seen);
+                out.push(')');
+            },
+            RuntimeVal::VariantVal(variant) => {
+                out.push_str(&format!("{}.{}", variant.enum_name, variant.variant));
+                if !variant.fields.is_empty() {
+                    out.push('(');
+                    for (index, field) in variant.fields.iter().enumerate() {
+                        if index > 0 {
+                            out.push_str(", ");
+                        }
+                        field.write_repr(out, seen);
+                    }
+                    out.push(')');
+                }
+            },
+            RuntimeVal::ListVal(list) => {
+                let pointer = Rc::as_ptr(&list.items) as *const ();
+                if seen.contains(&pointer) {
+                    out.push_str("[...]");
+                    return
+                }
+                seen.push(pointer);
+                out.push('[');
+                for (index, item) in list.items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_repr(out, seen);
+                }
+                out.push(']');
+                seen.pop();
+            },
+            RuntimeVal::InstanceVal(instance) => {
+                let pointer = Rc::as_ptr(&instance.fields) as *const ();
+                if seen.contains(&pointer) {
+                    out.push_str(&format!("<cycle {}>", instance.class.name));
+                    return
+                }
+                seen.push(pointer);
+                let fields = instance.fields.borrow();
+                let mut names: Vec<&String> = fields.keys().collect();
+                names.sort();
+                out.push_str(&instance.class.name);
+                out.push_str(" {");
+                for (index, name) in names.into_iter().enumerate() {
+                    out.push_str(if index > 0 { ", " } else { " " });
+                    out.push_str(name);
+                    out.push_str(": ");
+                    fields[name].write_repr(out, seen);
+                }
+                out.push_str(if fields.is_empty() { "}" } else { " }" });
+                seen.pop();
+            },
+            _ => out.push_str(&self.to_string()),
+        }
+    }
+}
+
+// what `print` shows: strings as their bare contents, everything else as its repr
+impl fmt::Display for RuntimeVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeVal::NullVal(_) => return write!(f, "null"),
+            RuntimeVal::NumberVal(number_val) => return write!(f, "{}", number_val.value),
+            // Debug keeps the '.0' that tells 1.0 from 1
+            RuntimeVal::FloatVal(float_val) => return write!(f, "{:?}", float_val.value),
+            RuntimeVal::BooleanVal(bool_val) => return write!(f, "{}", bool_val.value),
+            RuntimeVal::StringVal(string_val) => return write!(f, "{}", string_val.value),
+            RuntimeVal::ErrorVal(error) => return write!(f, "{}: {}", error.kind, error.message),
+            RuntimeVal::FunctionVal(function) => return write!(f, "<fn {}>", function.name),
+            RuntimeVal::NativeFnVal(native_fn) => return write!(f, "<native fn {}>", native_fn.name),
+            RuntimeVal::IteratorVal(iterator) => return write!(f, "<{}>", iterator.label),
+            RuntimeVal::DurationVal(duration) => return write!(f, "{}", format_duration(duration.nanos)),
+            RuntimeVal::DateTimeVal(datetime) => return write!(f, "{}", format_rfc3339(datetime)),
+            RuntimeVal::InstantVal(_) => return write!(f, "<instant>"),
+            RuntimeVal::ClassVal(class) => return write!(f, "<class {}>", class.name),
+            RuntimeVal::EnumVal(enum_val) => return write!(f, "<enum {}>", enum_val.name),
+            RuntimeVal::VariantCtorVal(ctor) => return write!(f, "<variant {}.{}>", ctor.enum_name, ctor.variant),
+            RuntimeVal::ModuleVal(module) => return write!(f, "<module {}>", module.path),
+            RuntimeVal::ResultVal(_) | RuntimeVal::VariantVal(_) | RuntimeVal::InstanceVal(_) | RuntimeVal::ListVal(_) => {
+                return write!(f, "{}", self.repr())
+            },
         }
     }
 }
@@ -117,4 +317,544 @@ impl NumberVal {
             value
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FloatVal {
+    pub value_type: ValueType,
+    pub value: f64,
+}
+
+impl FloatVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::FloatVal(self)
+    }
+    pub fn make_float(value: f64) -> FloatVal {
+        return FloatVal {
+            value_type: ValueType::Float,
+            value
+        }
+    }
+}
+
+// a signed span of time, to the nanosecond
+#[derive(Debug, PartialEq, Clone)]
+pub struct DurationVal {
+    pub value_type: ValueType,
+    pub nanos: i64,
+}
+
+impl DurationVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::DurationVal(self)
+    }
+    pub fn make_duration(nanos: i64) -> DurationVal {
+        return DurationVal {
+            value_type: ValueType::Duration,
+            nanos,
+        }
+    }
+}
+
+// a moment in time, seen from a fixed offset east of UTC
+#[derive(Debug, Clone)]
+pub struct DateTimeVal {
+    pub value_type: ValueType,
+    pub seconds: i64, // since the Unix epoch, in UTC
+    pub nanos: u32,
+    pub offset: i32, // in seconds; only changes how the moment is shown and split into fields
+}
+
+// the same moment is equal whatever offset it is shown at
+impl PartialEq for DateTimeVal {
+    fn eq(&self, other: &Self) -> bool {
+        return (self.seconds, self.nanos) == (other.seconds, other.nanos)
+    }
+}
+
+impl DateTimeVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::DateTimeVal(self)
+    }
+    pub fn make_datetime(seconds: i64, nanos: u32, offset: i32) -> DateTimeVal {
+        return DateTimeVal {
+            value_type: ValueType::DateTime,
+            seconds,
+            nanos,
+            offset,
+        }
+    }
+}
+
+// a reading of the monotonic clock, only good for measuring elapsed time
+#[derive(Debug, PartialEq, Clone)]
+pub struct InstantVal {
+    pub value_type: ValueType,
+    pub instant: Instant,
+}
+
+impl InstantVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::InstantVal(self)
+    }
+    pub fn make_instant(instant: Instant) -> InstantVal {
+        return InstantVal {
+            value_type: ValueType::Instant,
+            instant,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StringVal {
+    pub value_type: ValueType,
+    pub value: String,
+}
+
+impl StringVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::StringVal(self)
+    }
+    pub fn make_string(value: String) -> StringVal {
+        return StringVal {
+            value_type: ValueType::String,
+            value
+        }
+    }
+}
+
+// shared and mutable, so every binding of a list sees a push made through any other
+#[derive(Debug, Clone)]
+pub struct ListVal {
+    pub value_type: ValueType,
+    pub items: Rc<RefCell<Vec<RuntimeVal>>>,
+}
+
+thread_local! {
+    // the pairs of lists being compared further up, since PartialEq has no room to pass them down
+    static COMPARING: RefCell<Vec<(*const (), *const ())>> = const { RefCell::new(Vec::new()) };
+}
+
+// lists compare by contents; meeting a pair already being compared means both sides have come
+// round a cycle together, so that branch counts as equal and the remaining items decide
+impl PartialEq for ListVal {
+    fn eq(&self, other: &Self) -> bool {
+        if Rc::ptr_eq(&self.items, &other.items) {
+            return true
+        }
+        let pair = (Rc::as_ptr(&self.items) as *const (), Rc::as_ptr(&other.items) as *const ());
+        if COMPARING.with(|comparing| comparing.borrow().contains(&pair)) {
+            return true
+        }
+        COMPARING.with(|comparing| comparing.borrow_mut().push(pair));
+        let equal = *self.items.borrow() == *other.items.borrow();
+        COMPARING.with(|comparing| comparing.borrow_mut().pop());
+        return equal
+    }
+}
+
+impl ListVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::ListVal(self)
+    }
+    pub fn make_list(items: Vec<RuntimeVal>) -> ListVal {
+        return ListVal {
+            value_type: ValueType::List,
+            items: Rc::new(RefCell::new(items)),
+        }
+    }
+}
+
+// a lazy sequence; copies share one position, so advancing any of them advances all
+#[derive(Clone)]
+pub struct IteratorVal {
+    pub value_type: ValueType,
+    pub label: String, // how it prints: "iterator", or "generator" and the function's name
+    pub source: Rc<RefCell<Source>>,
+}
+
+// a suspended generator holds a whole VM frame, which is too much to show
+impl fmt::Debug for IteratorVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_struct("IteratorVal")
+            .field("value_type", &self.value_type)
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+impl PartialEq for IteratorVal {
+    fn eq(&self, other: &Self) -> bool {
+        return Rc::ptr_eq(&self.source, &other.source)
+    }
+}
+
+impl IteratorVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::IteratorVal(self)
+    }
+    pub fn make_iterator(label: String, source: Source) -> IteratorVal {
+        return IteratorVal {
+            value_type: ValueType::Iterator,
+            label,
+            source: Rc::new(RefCell::new(source)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ErrorVal {
+    pub value_type: ValueType,
+    pub kind: String,
+    pub message: String,
+    pub stack: Vec<String>, // innermost frame first, filled in when thrown
+}
+
+impl ErrorVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::ErrorVal(self)
+    }
+    pub fn make_error(kind: &str, message: String) -> ErrorVal {
+        return ErrorVal {
+            value_type: ValueType::Error,
+            kind: String::from(kind),
+            message,
+            stack: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResultVal {
+    pub value_type: ValueType,
+    pub is_ok: bool,
+    pub value: Box<RuntimeVal>,
+}
+
+impl ResultVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::ResultVal(self)
+    }
+    pub fn make_ok(value: RuntimeVal) -> ResultVal {
+        return ResultVal {
+            value_type: ValueType::Result,
+            is_ok: true,
+            value: Box::new(value),
+        }
+    }
+    pub fn make_err(value: RuntimeVal) -> ResultVal {
+        return ResultVal {
+            value_type: ValueType::Result,
+            is_ok: false,
+            value: Box::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FunctionBody {
+    Ast(Rc<Vec<Stmt>>), // shared, so copies of one function value can be told from another with the same code
+    Bytecode(Rc<Chunk>),
+    Generator(Rc<Chunk>), // always bytecode, since only a VM frame can be suspended and resumed
+}
+
+#[derive(Clone)]
+pub struct FunctionVal {
+    pub value_type: ValueType,
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: FunctionBody,
+    pub declaration_env: Rc<RefCell<Environment>>,
+}
+
+// the declaration environment usually contains the function itself, so neither
+// impl may look inside it
+impl fmt::Debug for FunctionVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_struct("FunctionVal")
+            .field("value_type", &self.value_type)
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .finish()
+    }
+}
+
+// a function is equal only to itself: the same code closed over the same scope, however alike
+// two functions look
+impl PartialEq for FunctionVal {
+    fn eq(&self, other: &Self) -> bool {
+        let same_code = match (&self.body, &other.body) {
+            (FunctionBody::Ast(lhs), FunctionBody::Ast(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (FunctionBody::Bytecode(lhs), FunctionBody::Bytecode(rhs))
+            | (FunctionBody::Generator(lhs), FunctionBody::Generator(rhs)) => Rc::ptr_eq(lhs, rhs),
+            _ => false,
+        };
+        return same_code && Rc::ptr_eq(&self.declaration_env, &other.declaration_env)
+    }
+}
+
+impl FunctionVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::FunctionVal(self)
+    }
+}
+
+// an Rc'd closure rather than a fn pointer, so embedders can register functions that capture state
+pub type NativeFn = Rc<dyn Fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult>;
+
+#[derive(Clone)]
+pub struct NativeFnVal {
+    pub value_type: ValueType,
+    pub name: String,
+    pub call: NativeFn,
+}
+
+impl fmt::Debug for NativeFnVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_struct("NativeFnVal")
+            .field("value_type", &self.value_type)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+// two natives may share a name, as when a host registers a function again, so only the same
+// closure is equal to itself
+impl PartialEq for NativeFnVal {
+    fn eq(&self, other: &Self) -> bool {
+        return Rc::ptr_eq(&self.call, &other.call)
+    }
+}
+
+impl NativeFnVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::NativeFnVal(self)
+    }
+    pub fn make_native_fn(name: &str, call: impl Fn(Vec<RuntimeVal>, &Rc<RefCell<Environment>>) -> EvalResult + 'static) -> NativeFnVal {
+        return NativeFnVal {
+            value_type: ValueType::Function,
+            name: String::from(name),
+            call: Rc::new(call),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassVal {
+    pub value_type: ValueType,
+    pub name: String,
+    pub superclass: Option<Box<ClassVal>>,
+    pub methods: Rc<HashMap<String, FunctionVal>>,
+    pub static_methods: Rc<HashMap<String, FunctionVal>>,
+}
+
+impl PartialEq for ClassVal {
+    fn eq(&self, other: &Self) -> bool {
+        return Rc::ptr_eq(&self.methods, &other.methods)
+    }
+}
+
+impl ClassVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::ClassVal(self)
+    }
+    // returns the method together with the class that defines it, so `super` can be bound
+    pub fn find_method(&self, name: &str) -> Option<(FunctionVal, ClassVal)> {
+        if let Some(method) = self.methods.get(name) {
+            return Some((method.clone(), self.clone()))
+        }
+        match self.superclass {
+            Some(ref superclass) => return superclass.find_method(name),
+            None => return None,
+        }
+    }
+    pub fn find_static_method(&self, name: &str) -> Option<(FunctionVal, ClassVal)> {
+        if let Some(method) = self.static_methods.get(name) {
+            return Some((method.clone(), self.clone()))
+        }
+        match self.superclass {
+            Some(ref superclass) => return superclass.find_static_method(name),
+            None => return None,
+        }
+    }
+    pub fn is_subclass_of(&self, other: &ClassVal) -> bool {
+        if self == other {
+            return true
+        }
+        match self.superclass {
+            Some(ref superclass) => return superclass.is_subclass_of(other),
+            None => return false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceVal {
+    pub value_type: ValueType,
+    pub class: ClassVal,
+    pub fields: Rc<RefCell<HashMap<String, RuntimeVal>>>,
+}
+
+impl PartialEq for InstanceVal {
+    fn eq(&self, other: &Self) -> bool {
+        return Rc::ptr_eq(&self.fields, &other.fields)
+    }
+}
+
+impl InstanceVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::InstanceVal(self)
+    }
+    pub fn make_instance(class: ClassVal) -> InstanceVal {
+        return InstanceVal {
+            value_type: ValueType::Instance,
+            class,
+            fields: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumVal {
+    pub value_type: ValueType,
+    pub name: String,
+    pub variants: Vec<(String, Vec<String>)>, // variant name and its field names
+}
+
+impl EnumVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::EnumVal(self)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct VariantVal {
+    pub value_type: ValueType,
+    pub enum_name: String,
+    pub variant: String,
+    pub field_names: Vec<String>,
+    pub fields: Vec<RuntimeVal>,
+}
+
+impl fmt::Debug for VariantVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple(&format!("{}.{}", self.enum_name, self.variant));
+        for field in &self.fields {
+            tuple.field(field);
+        }
+        return tuple.finish()
+    }
+}
+
+impl VariantVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::VariantVal(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VariantCtorVal {
+    pub value_type: ValueType,
+    pub enum_name: String,
+    pub variant: String,
+    pub field_names: Vec<String>,
+}
+
+impl VariantCtorVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::VariantCtorVal(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ModuleVal {
+    pub value_type: ValueType,
+    pub path: String,
+    pub exports: Rc<HashMap<String, RuntimeVal>>,
+}
+
+impl ModuleVal {
+    pub fn to_runtime_val(self) -> RuntimeVal {
+        return RuntimeVal::ModuleVal(self)
+    }
+}
+
+// conversions for embedders moving values between Rust and scripts
+impl From<i64> for RuntimeVal {
+    fn from(value: i64) -> Self {
+        return NumberVal::make_number(value).to_runtime_val()
+    }
+}
+
+impl From<f64> for RuntimeVal {
+    fn from(value: f64) -> Self {
+        return FloatVal::make_float(value).to_runtime_val()
+    }
+}
+
+impl From<bool> for RuntimeVal {
+    fn from(value: bool) -> Self {
+        return BooleanVal::make_bool(value).to_runtime_val()
+    }
+}
+
+impl From<String> for RuntimeVal {
+    fn from(value: String) -> Self {
+        return StringVal::make_string(value).to_runtime_val()
+    }
+}
+
+impl From<&str> for RuntimeVal {
+    fn from(value: &str) -> Self {
+        return StringVal::make_string(String::from(value)).to_runtime_val()
+    }
+}
+
+impl From<()> for RuntimeVal {
+    fn from(_: ()) -> Self {
+        return NullVal::make_null().to_runtime_val()
+    }
+}
+
+impl<T: Into<RuntimeVal>> From<Vec<T>> for RuntimeVal {
+    fn from(items: Vec<T>) -> Self {
+        return ListVal::make_list(items.into_iter().map(Into::into).collect()).to_runtime_val()
+    }
+}
+
+impl<T: Into<RuntimeVal>> From<Option<T>> for RuntimeVal {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => return value.into(),
+            None => return NullVal::make_null().to_runtime_val(),
+        }
+    }
+}
+
+impl TryFrom<RuntimeVal> for i64 {
+    type Error = ErrorVal;
+    fn try_from(value: RuntimeVal) -> Result<Self, Self::Error> {
+        return value.get_number_value()
+    }
+}
+
+impl TryFrom<RuntimeVal> for f64 {
+    type Error = ErrorVal;
+    fn try_from(value: RuntimeVal) -> Result<Self, Self::Error> {
+        return value.get_float_value()
+    }
+}
+
+impl TryFrom<RuntimeVal> for bool {
+    type Error = ErrorVal;
+    fn try_from(value: RuntimeVal) -> Result<Self, Self::Error> {
+        return value.get_bool_val()
+    }
+}
+
+impl TryFrom<RuntimeVal> for String {
+    type Error = ErrorVal;
+    fn try_from(value: RuntimeVal) -> Result<Self, Self::Error> {
+        return value.get_string_value()
+    }
+}
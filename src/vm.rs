@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::ast::Program;
+use crate::compiler::{compile_program, Chunk, OpCode};
+use crate::environment::Environment;
+use crate::limits;
+use crate::iterators::{iterate, next_item};
+use crate::process::capture;
+use crate::interpreter::{apply_binary_operator, call_function, call_method, construct, eval_import_declaration, eval_super_call, get_index, get_property, make_class, match_pattern, pop_frame, push_frame, set_index, set_property, throw_value, EvalResult, Interrupt};
+use crate::values::{ErrorVal, FunctionBody, FunctionVal, ListVal, NullVal, RuntimeVal, ValueType};
+
+#[derive(Debug)]
+struct Handler {
+    ip: usize,
+    stack_height: usize,
+    env: Rc<RefCell<Environment>>,
+}
+
+// why a frame stopped running; only generator chunks ever yield
+pub enum Exit {
+    Returned(RuntimeVal),
+    Yielded(RuntimeVal),
+}
+
+// the state of one running chunk; calls into other compiled functions get their own frame
+pub struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    stack: Vec<RuntimeVal>,
+    env: Rc<RefCell<Environment>>,
+    handlers: Vec<Handler>,
+}
+
+pub fn run_program(program: Program, env: &Rc<RefCell<Environment>>) -> EvalResult {
+    let chunk = Rc::new(compile_program(program));
+    push_frame(String::from("<program>"))?;
+    let result = Frame::new(chunk, Rc::clone(env)).run();
+    pop_frame();
+    return result
+}
+
+pub fn call_compiled(name: String, chunk: Rc<Chunk>, scope: Rc<RefCell<Environment>>) -> EvalResult {
+    push_frame(name)?;
+    let result = limits::grow_stack(|| Frame::new(chunk, scope).run());
+    pop_frame();
+    return result
+}
+
+impl Frame {
+    pub fn new(chunk: Rc<Chunk>, env: Rc<RefCell<Environment>>) -> Self {
+        return Frame {
+            chunk,
+            ip: 0,
+            stack: Vec::new(),
+            env,
+            handlers: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> EvalResult {
+        match self.resume()? {
+            Exit::Returned(value) => return Ok(value),
+            Exit::Yielded(_) => unreachable!("only generator chunks yield, and they are resumed instead"),
+        }
+    }
+
+    // runs until the chunk returns or yields; a yielded frame picks up where it left off next time
+    pub fn resume(&mut self) -> Result<Exit, Interrupt> {
+        loop {
+            match self.step() {
+                Ok(None) => {},
+                Ok(Some(exit)) => return Ok(exit),
+                Err(Interrupt::Throw(error)) => {
+                    let handler = match self.handlers.pop() {
+                        Some(handler) => handler,
+                        None => return Err(Interrupt::Throw(error)),
+                    };
+                    self.stack.truncate(handler.stack_height);
+                    self.stack.push(error.to_runtime_val());
+                    self.env = handler.env;
+                    self.ip = handler.ip;
+                },
+                Err(Interrupt::Return(value)) => return Ok(Exit::Returned(value)),
+                Err(exit) => return Err(exit),
+            }
+        }
+    }
+
+    fn pop(&mut self) -> RuntimeVal {
+        return self.stack.pop().expect("VM stack underflow")
+    }
+
+    fn pop_arguments(&mut self, argc: usize) -> Vec<RuntimeVal> {
+        return self.stack.split_off(self.stack.len() - argc)
+    }
+
+    fn make_function(&self, index: usize) -> FunctionVal {
+        let proto = &self.chunk.functions[index];
+        return FunctionVal {
+            value_type: ValueType::Function,
+            name: proto.name.clone(),
+            parameters: proto.parameters.clone(),
+            body: match proto.generator {
+                true => FunctionBody::Generator(Rc::clone(&proto.chunk)),
+                false => FunctionBody::Bytecode(Rc::clone(&proto.chunk)),
+            },
+            declaration_env: Rc::clone(&self.env),
+        }
+    }
+
+    // executes one instruction, reporting how the chunk stopped once it returns or yields
+    fn step(&mut self) -> Result<Option<Exit>, Interrupt> {
+        limits::tick()?;
+        let chunk = Rc::clone(&self.chunk);
+        let op = &chunk.code[self.ip];
+        self.ip += 1;
+        match op {
+            OpCode::Constant(index) => self.stack.push(chunk.constants[*index].clone()),
+            OpCode::Null => self.stack.push(NullVal::make_null().to_runtime_val()),
+            OpCode::Pop => { self.pop(); },
+            OpCode::DefineVar(name, constant) => {
+                let value = self.stack.last().expect("VM stack underflow").clone();
+                self.env.borrow_mut().declare_var(chunk.names[*name].clone(), value, *constant)?;
+            },
+            OpCode::LoadVar(name) => {
+                let value = self.env.borrow().lookup_var(chunk.names[*name].clone())?;
+                self.stack.push(value);
+            },
+            OpCode::StoreVar(name) => {
+                let value = self.pop();
+                let value = self.env.borrow_mut().assign_var(chunk.names[*name].clone(), value)?;
+                self.stack.push(value);
+            },
+            OpCode::LoadSlot(depth, slot, name) => {
+                let value = self.env.borrow().lookup_at(*depth, *slot, &chunk.names[*name])?;
+                self.stack.push(value);
+            },
+            OpCode::StoreSlot(depth, slot, name) => {
+                let value = self.pop();
+                let value = self.env.borrow_mut().assign_at(*depth, *slot, &chunk.names[*name], value)?;
+                self.stack.push(value);
+            },
+            OpCode::PushScope => {
+                self.env = Rc::new(RefCell::new(Environment::new(Some(Rc::clone(&self.env)))));
+            },
+            OpCode::PopScope => {
+                let parent = self.env.borrow().parent().expect("VM scope underflow");
+                self.env = parent;
+            },
+            OpCode::Capture(command) => self.stack.push(capture(&chunk.names[*command])?),
+            OpCode::Binary(operator) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.stack.push(apply_binary_operator(lhs, rhs, *operator)?);
+            },
+            OpCode::GetProperty(name) => {
+                let object = self.pop();
+                self.stack.push(get_property(object, &chunk.names[*name])?);
+            },
+            OpCode::SetProperty(name) => {
+                let value = self.pop();
+                let object = self.pop();
+                self.stack.push(set_property(object, chunk.names[*name].clone(), value)?);
+            },
+            OpCode::GetIndex => {
+                let index = self.pop();
+                let object = self.pop();
+                self.stack.push(get_index(object, index)?);
+            },
+            OpCode::SetIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                let object = self.pop();
+                self.stack.push(set_index(object, index, value)?);
+            },
+            OpCode::MakeList(count) => {
+                limits::allocate_list(*count)?;
+                let items = self.pop_arguments(*count);
+                self.stack.push(ListVal::make_list(items).to_runtime_val());
+            },
+            OpCode::Call(argc) => {
+                let arguments = self.pop_arguments(*argc);
+                let callee = self.pop();
+                self.stack.push(call_function(callee, arguments, &self.env)?);
+            },
+            OpCode::CallMethod(name, argc) => {
+                let arguments = self.pop_arguments(*argc);
+                let object = self.pop();
+                self.stack.push(call_method(object, &chunk.names[*name], arguments, &self.env)?);
+            },
+            OpCode::SuperCall(name, argc) => {
+                let arguments = self.pop_arguments(*argc);
+                let method = name.map(|name| chunk.names[name].clone());
+                self.stack.push(eval_super_call(method, arguments, &self.env)?);
+            },
+            OpCode::New(argc) => {
+                let arguments = self.pop_arguments(*argc);
+                let callee = self.pop();
+                self.stack.push(construct(callee, arguments, &self.env)?);
+            },
+            OpCode::MakeFunction(index) => {
+                limits::allocate()?;
+                let function = self.make_function(*index);
+                self.stack.push(function.to_runtime_val());
+            },
+            OpCode::MakeClass(index) => {
+                let proto = &chunk.classes[*index];
+                let methods = proto.methods.iter().map(|method| self.make_function(*method)).collect();
+                let static_methods = proto.static_methods.iter().map(|method| self.make_function(*method)).collect();
+                let class = make_class(proto.name.clone(), proto.superclass.clone(), methods, static_methods, &self.env)?;
+                self.stack.push(class.to_runtime_val());
+            },
+            OpCode::Import(index) => {
+                self.stack.push(eval_import_declaration(&chunk.imports[*index], &self.env)?);
+            },
+            OpCode::Throw => {
+                let value = self.pop();
+                return Err(throw_value(value))
+            },
+            OpCode::SetupTry(target) => {
+                self.handlers.push(Handler {
+                    ip: *target,
+                    stack_height: self.stack.len(),
+                    env: Rc::clone(&self.env),
+                });
+            },
+            OpCode::PopTry => { self.handlers.pop(); },
+            OpCode::Return => return Ok(Some(Exit::Returned(self.pop()))),
+            OpCode::JumpIfOkUnwrap(target) => {
+                match self.pop() {
+                    RuntimeVal::ResultVal(result) if result.is_ok => {
+                        self.stack.push(*result.value);
+                        self.ip = *target;
+                    },
+                    RuntimeVal::ResultVal(result) => self.stack.push(result.to_runtime_val()),
+                    value => {
+                        return Err(ErrorVal::make_error(
+                            "TypeError",
+                            format!("The '?' operator expects a Result, found {:?}", value.get_value_type())
+                        ).into())
+                    },
+                }
+            },
+            OpCode::MatchPattern(pattern, fail) => {
+                let subject = self.stack.last().expect("VM stack underflow").clone();
+                let mut bindings = Vec::new();
+                if match_pattern(&chunk.patterns[*pattern], &subject, &mut bindings, &self.env)? {
+                    self.pop();
+                    let scope = Rc::new(RefCell::new(Environment::new(Some(Rc::clone(&self.env)))));
+                    for (name, value) in bindings {
+                        scope.borrow_mut().declare_var(name, value, false)?;
+                    }
+                    self.env = scope;
+                } else {
+                    self.ip = *fail;
+                }
+            },
+            OpCode::MatchFail => {
+                let subject = self.pop();
+                return Err(ErrorVal::make_error(
+                    "MatchError",
+                    format!("No match arm matched a value of type {:?}", subject.get_value_type())
+                ).into())
+            },
+            OpCode::Jump(target) => self.ip = *target,
+            OpCode::GetIter => {
+                let iterable = self.pop();
+                self.stack.push(iterate(iterable)?.to_runtime_val());
+            },
+            OpCode::IterNext(done) => {
+                let iterator = match self.stack.last() {
+                    Some(RuntimeVal::IteratorVal(iterator)) => iterator.clone(),
+                    _ => panic!("VM expected an iterator on top of the stack"),
+                };
+                match next_item(&iterator, &self.env)? {
+                    Some(item) => self.stack.push(item),
+                    None => {
+                        self.pop();
+                        self.ip = *done;
+                    },
+                }
+            },
+            OpCode::Yield => {
+                // the yield statement itself evaluates to null once the generator resumes
+                let value = self.pop();
+                self.stack.push(NullVal::make_null().to_runtime_val());
+                return Ok(Some(Exit::Yielded(value)))
+            },
+        }
+        return Ok(None)
+    }
+}
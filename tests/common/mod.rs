@@ -0,0 +1,64 @@
+// helpers shared by the integration tests; each test binary uses its own subset of them
+#![allow(dead_code, clippy::needless_return)]
+use shtark::{Engine, EngineError, RuntimeVal};
+
+// which engine a test runs its scripts on; the default is the tree walker
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mode {
+    pub use_vm: bool,
+}
+
+impl Mode {
+    pub fn engine(self) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_use_vm(self.use_vm);
+        return engine
+    }
+
+    pub fn eval(self, source: &str) -> RuntimeVal {
+        return self.engine().eval(source).unwrap()
+    }
+
+    pub fn repr(self, source: &str) -> String {
+        return self.eval(source).repr()
+    }
+
+    pub fn error(self, source: &str) -> (String, String) {
+        return error_in(&mut self.engine(), source)
+    }
+}
+
+pub fn eval(source: &str) -> RuntimeVal {
+    return Mode::default().eval(source)
+}
+
+pub fn repr(source: &str) -> String {
+    return Mode::default().repr(source)
+}
+
+// the kind and message of the runtime error that `source` stops with
+pub fn error(source: &str) -> (String, String) {
+    return Mode::default().error(source)
+}
+
+pub fn error_in(engine: &mut Engine, source: &str) -> (String, String) {
+    match engine.eval(source) {
+        Err(EngineError::Runtime(error)) => return (error.kind, error.message),
+        other => panic!("expected {} to fail, got {:?}", source, other),
+    }
+}
+
+pub fn error_kind(result: Result<RuntimeVal, EngineError>) -> String {
+    match result {
+        Err(EngineError::Runtime(error)) => return error.kind,
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+// the tree walker and the VM share the builtins but not the code that calls them, so every
+// test that runs scripts through both must see the same results
+pub fn on_both_engines(test: impl Fn(Mode)) {
+    for use_vm in [false, true] {
+        test(Mode { use_vm });
+    }
+}
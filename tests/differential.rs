@@ -0,0 +1,49 @@
+#![allow(clippy::needless_return)]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn run(program: &Path, flags: &[&str]) -> Output {
+    return Command::new(env!("CARGO_BIN_EXE_shtark")).args(flags).arg(program).output().expect("failed to run shtark")
+}
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("missing tests/programs")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "shtark"))
+        .collect();
+    programs.sort();
+    return programs
+}
+
+#[test]
+fn programs_print_what_they_should_on_every_engine() {
+    let programs = programs();
+    assert!(!programs.is_empty(), "no test programs found");
+    for program in programs {
+        let interpreted = run(&program, &[]);
+        // agreeing is not enough if every engine is wrong, so the interpreter is checked against
+        // the output recorded next to the program first
+        let expected = program.with_extension("out");
+        let expected = fs::read_to_string(&expected).unwrap_or_else(|_| panic!("missing {}", expected.display()));
+        assert_eq!(String::from_utf8_lossy(&interpreted.stdout), expected, "unexpected stdout from {}", program.display());
+        for flags in [&["--vm"][..], &["--opt-level", "2"][..], &["--vm", "--opt-level", "2"][..]] {
+            let other = run(&program, flags);
+            assert_eq!(
+                String::from_utf8_lossy(&interpreted.stdout),
+                String::from_utf8_lossy(&other.stdout),
+                "stdout differs for {} with {:?}", program.display(), flags
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&interpreted.stderr),
+                String::from_utf8_lossy(&other.stderr),
+                "stderr differs for {} with {:?}", program.display(), flags
+            );
+            assert_eq!(interpreted.status.code(), other.status.code(), "exit status differs for {} with {:?}", program.display(), flags);
+        }
+        // a panic would agree with itself, so make sure each program actually ran
+        assert!(interpreted.status.code().is_some_and(|code| code <= 1), "{} crashed", program.display());
+    }
+}
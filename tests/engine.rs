@@ -0,0 +1,150 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::cell::Cell;
+use std::fs;
+use std::rc::Rc;
+use shtark::ErrorVal;
+use shtark::{Engine, EngineError, RuntimeVal};
+use common::on_both_engines;
+
+#[test]
+fn globals_persist_between_evaluations() {
+    let mut engine = Engine::new();
+    engine.eval("let total = 40;").unwrap();
+    let value = engine.eval("total = total + 2; total").unwrap();
+    assert_eq!(i64::try_from(value).unwrap(), 42);
+    assert_eq!(engine.get_global("total"), Some(RuntimeVal::from(42)));
+    assert_eq!(engine.get_global("missing"), None);
+}
+
+#[test]
+fn host_values_cross_into_scripts() {
+    let mut engine = Engine::new();
+    engine.set_global("name", "shtark");
+    engine.set_global("limit", 3);
+    engine.set_global("verbose", true);
+    engine.set_global("nothing", ());
+    let value = engine.eval("match verbose { true => name, _ => nothing }").unwrap();
+    assert_eq!(String::try_from(value).unwrap(), "shtark");
+    // setting a global again replaces it, even after scripts have looked it up
+    engine.set_global("limit", 4);
+    assert_eq!(i64::try_from(engine.eval("limit").unwrap()).unwrap(), 4);
+    assert!(i64::try_from(RuntimeVal::from("four")).is_err());
+}
+
+#[test]
+fn registered_functions_can_capture_state() {
+    let mut engine = Engine::new();
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    engine.register_fn("add", move |arguments| {
+        counter.set(counter.get() + 1);
+        let mut sum = 0;
+        for argument in arguments {
+            sum += i64::try_from(argument)?;
+        }
+        return Ok(RuntimeVal::from(sum))
+    });
+    assert_eq!(engine.eval("add(1, 2, add(3, 4))").unwrap(), RuntimeVal::from(10));
+    assert_eq!(calls.get(), 2);
+    match engine.eval("add(1, \"two\")") {
+        Err(EngineError::Runtime(error)) => assert_eq!(error.kind, "TypeError"),
+        other => panic!("expected a TypeError, got {:?}", other),
+    }
+}
+
+#[test]
+fn registered_functions_are_only_equal_to_themselves() {
+    let mut engine = Engine::new();
+    engine.register_fn("answer", |_| Ok(RuntimeVal::from(1)));
+    let first = engine.get_global("answer").unwrap();
+    engine.register_fn("answer", |_| Ok(RuntimeVal::from(2)));
+    engine.set_global("first", first);
+    assert_eq!(engine.eval("[first == answer, answer == answer, print == print, first(), answer()]").unwrap().repr(), "[false, true, true, 1, 2]");
+}
+
+#[test]
+fn errors_are_returned_instead_of_exiting() {
+    let mut engine = Engine::new();
+    match engine.eval("let x 5;") {
+        Err(EngineError::Syntax(error)) => {
+            assert!(!error.incomplete, "{}", error);
+            assert_eq!(error.message, "Expected 'equals' token following identifier in var declaration. Found '5'.");
+        },
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+    match engine.eval("fn f() {") {
+        Err(EngineError::Syntax(error)) => assert!(error.incomplete, "{}", error),
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+    assert!(matches!(engine.eval("\"open"), Err(EngineError::Syntax(_))));
+    assert!(matches!(engine.eval("#"), Err(EngineError::Syntax(_))));
+    match engine.eval("throw \"boom\";") {
+        Err(EngineError::Runtime(ErrorVal { message, .. })) => assert_eq!(message, "boom"),
+        other => panic!("expected an uncaught error, got {:?}", other),
+    }
+    assert!(matches!(engine.eval_file("/nonexistent/file.shtark"), Err(EngineError::Io(_))));
+    // the engine is still usable afterwards
+    assert_eq!(engine.eval("1 + 1").unwrap(), RuntimeVal::from(2));
+}
+
+#[test]
+fn files_import_relative_to_themselves() {
+    let dir = std::env::temp_dir().join(format!("shtark-engine-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("lib.shtark"), "export const answer = 42;\n").unwrap();
+    fs::write(dir.join("main.shtark"), "import { answer } from \"lib.shtark\";\nconst doubled = answer * 2;\n").unwrap();
+    let mut engine = Engine::new();
+    engine.eval_file(dir.join("main.shtark")).unwrap();
+    assert_eq!(engine.get_global("doubled"), Some(RuntimeVal::from(84)));
+    let names: Vec<String> = engine.bindings().into_iter().map(|(name, _, _)| name).collect();
+    assert_eq!(names, vec!["answer", "doubled"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+
+#[test]
+fn each_engine_imports_its_own_modules() {
+    let dir = std::env::temp_dir().join(format!("shtark-engine-modules-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("log.shtark"), "export const entries = [];\n").unwrap();
+    let import = format!("import {{ entries }} from \"{}\";\n", dir.join("log.shtark").display());
+    let mut a = Engine::new();
+    a.eval(&format!("{}entries.push(\"from engine a\");", import)).unwrap();
+    let mut b = Engine::new();
+    b.set_use_vm(true);
+    assert_eq!(b.eval(&format!("{}entries", import)).unwrap().repr(), "[]");
+    // within one engine the module is evaluated once and shared
+    let again = import.replace("{ entries }", "{ entries as again }");
+    assert_eq!(a.eval(&format!("{}again", again)).unwrap().repr(), "[\"from engine a\"]");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn warnings_from_imported_modules_are_returned() {
+    let dir = std::env::temp_dir().join(format!("shtark-engine-warnings-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let module = dir.join("shapes.shtark");
+    fs::write(&module, "enum Shape { Dot, Line }\nexport fn name(s) { return match s { Shape.Dot => \"dot\" }; }\n").unwrap();
+    let mut engine = Engine::new();
+    engine.eval(&format!("import {{ name }} from \"{}\";", module.display())).unwrap();
+    assert_eq!(engine.take_warnings(), vec![format!("{}: Non-exhaustive match over enum Shape, missing: Line", module.canonicalize().unwrap().display())]);
+    assert!(engine.take_warnings().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_failed_evaluation_leaves_its_unreached_globals_undeclared() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        assert!(engine.eval("let early = fn() { return late; };\nthrow \"stop\";\nlet late = 1;").is_err());
+        engine.eval("let other = 2;").unwrap();
+        let before = "Cannot access 'late' before its declaration.";
+        match engine.eval("early()") {
+            Err(EngineError::Runtime(error)) => assert_eq!((error.kind.as_str(), error.message.as_str()), ("NameError", before)),
+            other => panic!("expected a NameError, got {:?}", other),
+        }
+        // the name itself is free to declare again
+        assert_eq!(engine.eval("let late = 3; late + other").unwrap(), RuntimeVal::from(5));
+    });
+}
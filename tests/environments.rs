@@ -0,0 +1,95 @@
+#![allow(clippy::needless_return)]
+use std::fs;
+use std::process::Command;
+
+// runs `source` on both engines and returns the printed lines, which must agree
+fn run(name: &str, source: &str) -> Vec<String> {
+    let path = std::env::temp_dir().join(format!("shtark-environments-{}-{}.shtark", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    let mut outputs = Vec::new();
+    for flags in [&[][..], &["--vm"][..]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_shtark")).args(flags).arg(&path).output().unwrap();
+        assert!(output.status.success(), "{} failed: {}", name, String::from_utf8_lossy(&output.stdout));
+        outputs.push(String::from_utf8(output.stdout).unwrap());
+    }
+    fs::remove_file(&path).unwrap();
+    assert_eq!(outputs[0], outputs[1], "engines disagree on {}", name);
+    return outputs[0].lines().map(String::from).collect()
+}
+
+fn number(value: i64) -> String {
+    return value.to_string()
+}
+
+#[test]
+fn initializer_side_effects_reach_the_scope() {
+    let lines = run("initializer", "
+        let b = 0;
+        let a = b = 5;
+        print(a);
+        print(b);
+    ");
+    assert_eq!(lines, vec![number(5), number(5)]);
+}
+
+#[test]
+fn closures_share_the_scope_they_close_over() {
+    let lines = run("shared", "
+        fn make() {
+            let count = 0;
+            const bump = fn() { count = count + 1 };
+            const read = fn() { count };
+            bump();
+            bump();
+            return read;
+        }
+        const read = make();
+        print(read());
+    ");
+    assert_eq!(lines, vec![number(2)]);
+}
+
+#[test]
+fn closures_see_later_assignments() {
+    let lines = run("later", "
+        let value = 1;
+        const get = fn() { value };
+        value = 2;
+        print(get());
+    ");
+    assert_eq!(lines, vec![number(2)]);
+}
+
+#[test]
+fn child_scopes_assign_to_the_enclosing_variable() {
+    let lines = run("child", "
+        let total = 1;
+        try {
+            total = total + 1;
+        } finally {
+            total = total * 10;
+        }
+        match total {
+            n => { total = n + 1 },
+        };
+        print(total);
+    ");
+    assert_eq!(lines, vec![number(21)]);
+}
+
+#[test]
+fn each_call_gets_a_fresh_scope() {
+    let lines = run("fresh", "
+        fn counter() {
+            let count = 0;
+            return fn() { count = count + 1 };
+        }
+        const first = counter();
+        const second = counter();
+        first();
+        first();
+        print(first());
+        print(second());
+    ");
+    assert_eq!(lines, vec![number(3), number(1)]);
+}
@@ -0,0 +1,213 @@
+#![allow(clippy::needless_return)]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fmt(args: &[&str], path: &Path) -> Output {
+    return Command::new(env!("CARGO_BIN_EXE_shtark")).arg("fmt").args(args).arg(path).output().unwrap()
+}
+
+fn temp_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("shtark-format-{}-{}.shtark", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    return path
+}
+
+// a tiny linear congruential generator, so failures reproduce from the seed alone
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        return (self.0 >> 33) % n
+    }
+
+    fn pick<'a>(&mut self, options: &[&'a str]) -> &'a str {
+        return options[self.below(options.len() as u64) as usize]
+    }
+}
+
+const NAMES: &[&str] = &["a", "b", "count", "value2", "make_pair", "_tmp", "Shape"];
+const OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "==", "!=", "instanceof", "|"];
+
+// random source text over the whole grammar, with redundant parentheses sprinkled in
+fn expr(rng: &mut Rng, depth: u32) -> String {
+    let choice = if depth == 0 { rng.below(4) } else { rng.below(15) };
+    let text = match choice {
+        0 => String::from(rng.pick(&["0", "42", "1_000", "0xFF", "0o17", "0b1010", "1.5", "2.5e-3", "1E10"])),
+        1 => String::from(rng.pick(&["\"text\"", "\"tab\\tand \\\"quote\\\"\\n\"", "null", "this", "$(ls -l \"a b\" | wc)"])),
+        2 | 3 => String::from(rng.pick(NAMES)),
+        4 | 5 => format!("{} {} {}", expr(rng, depth - 1), rng.pick(OPERATORS), expr(rng, depth - 1)),
+        6 => format!("{} = {}", rng.pick(NAMES), expr(rng, depth - 1)),
+        7 => format!("{}.{}", expr(rng, depth - 1), rng.pick(NAMES)),
+        8 => format!("{}({}, {})", expr(rng, depth - 1), expr(rng, depth - 1), expr(rng, depth - 1)),
+        9 => format!("new {}({})", rng.pick(NAMES), expr(rng, depth - 1)),
+        10 => format!("fn({}) {{ {} }}", rng.pick(NAMES), block(rng, depth - 1)),
+        11 => format!("{}?", expr(rng, depth - 1)),
+        12 => format!("[{}, {}]", expr(rng, depth - 1), expr(rng, depth - 1)),
+        13 => format!("{}[{}]", expr(rng, depth - 1), expr(rng, depth - 1)),
+        _ => format!(
+            "match {} {{ 1 => {}, \"s\" => {{ {} }} Shape.Circle(r, _) => r, {} => {} }}",
+            expr(rng, depth - 1), expr(rng, depth - 1), block(rng, depth - 1), rng.pick(NAMES), expr(rng, depth - 1)
+        ),
+    };
+    if rng.below(4) == 0 {
+        return format!("({})", text)
+    }
+    return text
+}
+
+fn stmt(rng: &mut Rng, depth: u32) -> String {
+    let comment = rng.pick(&["", "", "", "// note\n", "/* block /* nested */ */ ", "/// doc\n///\n"]);
+    return format!("{}{}", comment, bare_stmt(rng, depth))
+}
+
+fn bare_stmt(rng: &mut Rng, depth: u32) -> String {
+    match rng.below(11) {
+        0 => return format!("let {} = {};", rng.pick(NAMES), expr(rng, depth)),
+        1 => return format!("const {} = {};", rng.pick(NAMES), expr(rng, depth)),
+        2 => return format!("fn {}(a, b) {{ {} }}", rng.pick(NAMES), block(rng, depth.saturating_sub(1))),
+        3 => return format!("return {};", expr(rng, depth)),
+        4 => return format!("throw {};", expr(rng, depth)),
+        5 => return format!("try {{ {} }} catch (e) {{ {} }} finally {{ {} }}", block(rng, depth.saturating_sub(1)), block(rng, 0), block(rng, 0)),
+        6 => return format!("class {} extends Base {{ constructor(a) {{ {} }} static make() {{ }} }}", rng.pick(NAMES), block(rng, depth.saturating_sub(1))),
+        7 => return format!("for {} in {} {{ {} }}", rng.pick(NAMES), expr(rng, depth), block(rng, depth.saturating_sub(1))),
+        8 => return format!("fn {}() {{ yield {}; {} }}", rng.pick(NAMES), expr(rng, depth), block(rng, depth.saturating_sub(1))),
+        _ => return format!("{};", expr(rng, depth)),
+    }
+}
+
+fn block(rng: &mut Rng, depth: u32) -> String {
+    let count = rng.below(3);
+    return (0..count).map(|_| stmt(rng, depth)).collect::<Vec<_>>().join(" ")
+}
+
+fn program(rng: &mut Rng) -> String {
+    let mut source = String::from("#!/usr/bin/env shtark\nimport { a as b, c } from \"lib\";\nenum Shape { Circle(r, s), Dot }\n");
+    for _ in 0..8 {
+        source.push_str(&stmt(rng, 3));
+        source.push('\n');
+    }
+    source.push_str("export const done = 1;\n");
+    return source
+}
+
+#[test]
+fn printing_preserves_the_syntax_tree() {
+    // fmt itself reparses its output and refuses to write anything that changes the tree
+    for seed in 0..200 {
+        let source = program(&mut Rng(seed));
+        let path = temp_file(&format!("random-{}", seed), &source);
+        let output = fmt(&[], &path);
+        assert!(output.status.success(), "seed {}: {}\n{}", seed, String::from_utf8_lossy(&output.stdout), source);
+        let check = fmt(&["--check"], &path);
+        assert!(check.status.success(), "seed {} is not idempotent:\n{}", seed, fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn parentheses_are_kept_only_where_needed() {
+    let path = temp_file("parens", "let a = ((1 + 2)) * (3 - (4 - 5)) + (6 * 7);\n(b = (c = 1));\n(new (f())(1)).x;\n");
+    assert!(fmt(&[], &path).status.success());
+    let formatted = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(formatted, "let a = (1 + 2) * (3 - (4 - 5)) + 6 * 7;\nb = c = 1;\nnew (f())(1).x;\n");
+}
+
+#[test]
+fn check_reports_files_that_would_change() {
+    let path = temp_file("check", "let   a=1;print(a)\n");
+    let output = fmt(&["--check"], &path);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Would reformat"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "let   a=1;print(a)\n");
+    assert!(fmt(&[], &path).status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "let a = 1;\nprint(a);\n");
+    assert!(fmt(&["--check"], &path).status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn comments_and_doc_comments_are_kept() {
+    let source = "/// Adds.\n///\n///   indented\nexport fn add(a, b) {\n    /* sum */\n    return a + b;\n    // done\n}\n\nclass A {\n    /// Builds an A.\n    constructor() {}\n}\n\n/// not attached\nadd(1, 2);\n";
+    let path = temp_file("comments", source);
+    let output = fmt(&["--check"], &path);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn comments_between_members_and_variants_are_kept() {
+    let source = "class A {\n  // builds one\n  constructor() {}\n  /* helpers */ /// Says hi.\n  hi() { return 1; }\n  // the end\n}\nclass B {\n    // nothing yet\n}\nenum Shape { // round\n  Circle(r),\n  /* flat */ Dot,\n  // more to come\n}\n";
+    let path = temp_file("members", source);
+    let output = fmt(&[], &path);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    let formatted = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(formatted, "class A {\n    // builds one\n    constructor() {}\n\n    /* helpers */\n    /// Says hi.\n    hi() {\n        return 1;\n    }\n    // the end\n}\n\nclass B {\n    // nothing yet\n}\n\nenum Shape {\n    // round\n    Circle(r),\n    /* flat */\n    Dot,\n    // more to come\n}\n");
+}
+
+#[test]
+fn comments_inside_expressions_are_refused() {
+    let path = temp_file("lost", "print(1 /* one */);\n");
+    let output = fmt(&[], &path);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("cannot be kept"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "print(1 /* one */);\n");
+    fs::remove_file(&path).unwrap();
+}
+
+// copies `from` into `to`, subdirectories included
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &target);
+        } else {
+            fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+#[test]
+fn example_programs_keep_their_behaviour() {
+    // formatted copies live in a copy of the whole directory, so relative imports still resolve
+    // and other tests scanning tests/programs never see them
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    let copies = std::env::temp_dir().join(format!("shtark-format-programs-{}", std::process::id()));
+    copy_dir(&programs, &copies);
+    for entry in fs::read_dir(&programs).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("shtark") {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let copy = copies.join(path.file_name().unwrap());
+        assert!(fmt(&[], &copy).status.success(), "cannot format {}", name);
+        let original = Command::new(env!("CARGO_BIN_EXE_shtark")).arg(&path).output().unwrap();
+        let formatted = Command::new(env!("CARGO_BIN_EXE_shtark")).arg(&copy).output().unwrap();
+        assert_eq!(original.status.code(), formatted.status.code(), "{}", name);
+        assert_eq!(String::from_utf8_lossy(&original.stdout), String::from_utf8_lossy(&formatted.stdout), "{}", name);
+    }
+    fs::remove_dir_all(&copies).unwrap();
+}
+
+#[test]
+fn a_file_that_does_not_parse_does_not_stop_the_batch() {
+    let bad = temp_file("bad", "let x = (1;\n");
+    let lexing = temp_file("unlexable", "let s = \"open\n");
+    let good = temp_file("good", "let   a=1;\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_shtark")).arg("fmt").arg(&bad).arg(&lexing).arg(&good).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains(&format!("Cannot format {}", bad.display())), "{}", stdout);
+    assert!(stdout.contains(&format!("Cannot format {}", lexing.display())), "{}", stdout);
+    assert_eq!(fs::read_to_string(&bad).unwrap(), "let x = (1;\n");
+    assert_eq!(fs::read_to_string(&good).unwrap(), "let a = 1;\n");
+    for path in [bad, lexing, good] {
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::fs;
+use std::path::PathBuf;
+use shtark::Limits;
+use shtark::{Engine, RuntimeVal};
+use common::{error_in, on_both_engines};
+
+// a scratch directory per test, so tests running in parallel never share files
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shtark-fs-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    return dir
+}
+
+#[test]
+fn files_can_be_written_read_and_removed() {
+    on_both_engines(|mode| {
+        let dir = scratch("files");
+        let mut engine = mode.engine();
+        engine.set_global("dir", dir.to_string_lossy().into_owned());
+        let source = "let file = path.join(dir, \"notes.txt\");\n\
+            let before = fs.exists(file);\n\
+            fs.write(file, \"one\\n\");\n\
+            fs.append(file, \"two\\n\");\n\
+            let contents = fs.read_to_string(file);\n\
+            fs.mkdir(path.join(dir, \"sub\", \"deeper\"));\n\
+            let listed = fs.list_dir(dir);\n\
+            fs.remove(file);\n\
+            [before, contents, listed, fs.exists(file)]";
+        assert_eq!(engine.eval(source).unwrap().repr(), "[false, \"one\\ntwo\\n\", [\"notes.txt\", \"sub\"], false]");
+        fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn lines_are_read_lazily_without_their_endings() {
+    on_both_engines(|mode| {
+        let dir = scratch("lines");
+        fs::write(dir.join("data.txt"), "alpha\r\nbeta\n\ngamma").unwrap();
+        let mut engine = mode.engine();
+        engine.set_global("file", dir.join("data.txt").to_string_lossy().into_owned());
+        assert_eq!(engine.eval("fs.lines(file).collect()").unwrap().repr(), "[\"alpha\", \"beta\", \"\", \"gamma\"]");
+        let source = "let found = [];\n\
+            for line in fs.lines(file).filter(fn(l) { l.len() == 4 }) { found.push(line); }\n\
+            found";
+        assert_eq!(engine.eval(source).unwrap().repr(), "[\"beta\"]");
+        fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn failures_are_io_errors_naming_the_path() {
+    on_both_engines(|mode| {
+        let dir = scratch("failures");
+        let missing = dir.join("missing.txt").to_string_lossy().into_owned();
+        let mut engine = mode.engine();
+        engine.set_global("missing", missing.clone());
+        let (kind, message) = error_in(&mut engine, "fs.read_to_string(missing)");
+        assert_eq!(kind, "IOError");
+        assert!(message.starts_with(&format!("Cannot read '{}': ", missing)), "{}", message);
+        assert_eq!(error_in(&mut engine, "fs.lines(missing)").0, "IOError");
+        assert_eq!(error_in(&mut engine, "fs.remove(missing)").0, "IOError");
+        assert_eq!(error_in(&mut engine, "fs.list_dir(missing)").0, "IOError");
+        // a non-empty directory is left alone
+        fs::write(dir.join("kept.txt"), "").unwrap();
+        engine.set_global("dir", dir.to_string_lossy().into_owned());
+        assert_eq!(error_in(&mut engine, "fs.remove(dir)").0, "IOError");
+        // scripts can recover
+        assert_eq!(engine.eval("try { fs.read_to_string(missing); } catch (e) { \"fallback\"; }").unwrap(), RuntimeVal::from("fallback"));
+        assert_eq!(error_in(&mut engine, "fs.write(missing)").0, "TypeError");
+        fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn paths_are_joined_and_split() {
+    let mut engine = Engine::new();
+    let source = "[path.basename(\"a/b/report.tar.gz\"), path.extension(\"a/b/report.tar.gz\"), \
+        path.extension(\"Makefile\"), path.basename(\"/\"), path.join(\"a\", \"b\", \"c.txt\"), path.join(\"a\", \"/root\")]";
+    assert_eq!(engine.eval(source).unwrap().repr(), "[\"report.tar.gz\", \"gz\", \"\", \"\", \"a/b/c.txt\", \"/root\"]");
+    assert_eq!(error_in(&mut engine, "path.join()").1, "path.join expects at least 1 argument");
+    assert_eq!(engine.eval("import { basename } from \"path\";\nbasename(\"x/y\")").unwrap(), RuntimeVal::from("y"));
+}
+
+#[test]
+fn the_modules_only_exist_while_io_is_allowed() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        engine.set_limits(Limits { allow_io: false, ..Limits::default() });
+        assert_eq!(error_in(&mut engine, "fs.exists(\".\")").0, "NameError");
+        assert_eq!(error_in(&mut engine, "path.join(\"a\")").0, "NameError");
+        assert_eq!(error_in(&mut engine, "import { exists } from \"fs\";").0, "PermissionError");
+        // other builtins keep working with the modules gone
+        assert_eq!(engine.eval("range(3).collect()").unwrap().repr(), "[0, 1, 2]");
+        engine.set_limits(Limits::default());
+        assert_eq!(engine.eval("fs.exists(\"/definitely/not/here\")").unwrap(), RuntimeVal::from(false));
+    });
+}
@@ -0,0 +1,126 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::time::Duration;
+use shtark::Limits;
+use shtark::{Engine, EngineError};
+use common::{error, on_both_engines, repr};
+
+#[test]
+fn generators_resume_where_they_left_off() {
+    on_both_engines(|mode| {
+        let source = "let log = [];\n\
+            fn numbers(limit) {\n\
+                log.push(\"start\");\n\
+                for n in range(limit) {\n\
+                    log.push(n);\n\
+                    yield n * 10;\n\
+                }\n\
+                log.push(\"end\");\n\
+            }\n\
+            let g = numbers(2);\n\
+            let before = log.len();\n\
+            [before, g.next(), log, g.next(), g.next(), g.next(), log]";
+        assert_eq!(
+            mode.repr(source),
+            "[0, 0, [\"start\", 0, 1, \"end\"], 10, Iter.Done, Iter.Done, [\"start\", 0, 1, \"end\"]]"
+        );
+        assert_eq!(mode.repr("fn pairs() { yield 1; return 5; yield 2; }\npairs().collect()"), "[1]");
+    });
+}
+
+#[test]
+fn adapters_are_lazy_over_endless_sources() {
+    on_both_engines(|mode| {
+        let cases = [
+            ("range(0, null).map(fn(x) { x * x }).filter(fn(x) { x % 2 == 1 }).take(3).collect()", "[1, 9, 25]"),
+            ("fn ones() { for _ in range(0, null) { yield 1; } }\nones().skip(5).take(2).collect()", "[1, 1]"),
+            ("range(3).chain(\"ab\").collect()", "[0, 1, 2, \"a\", \"b\"]"),
+            ("range(5, 0, 0 - 2).collect()", "[5, 3, 1]"),
+            ("range(0).collect()", "[]"),
+            ("let calls = [];\nlet it = range(0, null).map(fn(x) { calls.push(x); x }).take(2);\n[calls.len(), it.collect(), calls]", "[0, [0, 1], [0, 1]]"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(mode.repr(source), expected, "{}", source);
+        }
+    });
+}
+
+#[test]
+fn for_loops_walk_every_iterable() {
+    on_both_engines(|mode| {
+        let cases = [
+            ("let out = [];\nfor x in [1, 2] { out.push(x); }\nfor c in \"ïx\" { out.push(c); }\nout", "[1, 2, \"ï\", \"x\"]"),
+            ("let fs = [];\nfor i in range(3) { fs.push(fn() { i }); }\nfs.map(fn(f) { f() })", "[0, 1, 2]"),
+            ("fn first_even(xs) { for x in xs { match x % 2 { 0 => { return x; }, _ => null } } return null; }\nfirst_even([3, 5, 8, 9])", "8"),
+            (
+                "class Countdown {\n\
+                    constructor(n) { this.n = n; }\n\
+                    next() { match this.n { 0 => Iter.Done, _ => { this.n = this.n - 1; this.n + 1 } } }\n\
+                }\n\
+                iter(new Countdown(3)).collect()",
+                "[3, 2, 1]",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(mode.repr(source), expected, "{}", source);
+        }
+    });
+}
+
+#[test]
+fn misuse_raises_errors() {
+    let cases = [
+        ("for x in 5 { x; }", "TypeError", "Cannot iterate over a value of type Number"),
+        ("range(1, 5, 0)", "ValueError", "range step cannot be 0"),
+        ("range(0, null, 9223372036854775807).skip(1).collect()", "OverflowError", "An endless range ran past the largest integer"),
+        ("iter([1]).take(0 - 1)", "ValueError", "take needs a count of at least 0, not -1"),
+        ("iter([1]).filter(fn(x) { x }).next()", "TypeError", "The function given to filter must return a Boolean, not Number"),
+        ("let g = null;\nfn me() { yield g.next(); }\ng = me();\ng.next()", "ValueError", "Cannot advance the generator me from inside its own next step"),
+        ("iter([]).size()", "TypeError", "Iterator has no method 'size'"),
+    ];
+    for (source, kind, message) in cases {
+        assert_eq!(error(source), (String::from(kind), String::from(message)), "{}", source);
+    }
+}
+
+#[test]
+fn a_failed_generator_stays_finished() {
+    assert_eq!(repr("fn bad() { yield 1; throw \"no\"; }\nlet g = bad();\nlet first = g.next();\ntry { g.next(); } catch (e) { null; }\n[first, g.next()]"), "[1, Iter.Done]");
+}
+
+#[test]
+fn yield_outside_a_function_is_a_syntax_error() {
+    match Engine::new().eval("yield 1;") {
+        Err(EngineError::Syntax(error)) => assert_eq!(error.message, "Yield statements are only allowed inside a function body."),
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+}
+
+#[test]
+fn endless_loops_are_still_bounded_by_limits() {
+    // collecting and filtering drain their source natively, without running any script steps
+    let sources = [
+        "for x in range(0, null) { x; }",
+        "range(0, null).collect()",
+        "range(0, null).map(fn(x) { x * 2 }).collect()",
+        "range(0, null).filter(fn(x) { x == 0 - 1 }).next()",
+        "range(0, null).skip(9000000000000000000).next()",
+        "let total = 0; for x in range(0, null).map(fn(x) { x * 2 }) { total = total + x; }",
+    ];
+    let budgets = [
+        (Limits { timeout: Some(Duration::from_millis(50)), ..Default::default() }, "TimeoutError"),
+        (Limits { fuel: Some(10_000), ..Default::default() }, "FuelError"),
+    ];
+    on_both_engines(|mode| {
+        for (limits, kind) in &budgets {
+            for source in sources {
+                let mut engine = mode.engine();
+                engine.set_limits(limits.clone());
+                match engine.eval(source) {
+                    Err(EngineError::Runtime(error)) => assert_eq!(error.kind, *kind, "{}", source),
+                    other => panic!("expected a {} from {}, got {:?}", kind, source, other),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,69 @@
+#![allow(clippy::needless_return)]
+mod common;
+use common::{error, repr};
+
+#[test]
+fn documents_parse_into_values() {
+    let source = "let doc = json.parse(\"{\\\"name\\\": \\\"shtark\\\", \\\"tags\\\": [1, 2.5, true, null], \\\"first-name\\\": {}}\");\n\
+        [doc.name, doc.tags, doc[\"first-name\"], doc instanceof Object]";
+    assert_eq!(repr(source), "[\"shtark\", [1, 2.5, true, null], Object {}, true]");
+    assert_eq!(repr("json.parse(\" [ ] \")"), "[]");
+    assert_eq!(repr("json.parse(\"\\\"tab\\\\tquote\\\\\\\"\\\\u00e9\\\\ud83d\\\\ude00\\\"\")"), "\"tab\\tquote\\\"é😀\"");
+    // repeated keys keep the last value
+    assert_eq!(repr("json.parse(\"{\\\"a\\\": 1, \\\"a\\\": 2}\").a"), "2");
+}
+
+#[test]
+fn integers_and_floats_keep_their_type() {
+    assert_eq!(repr("[json.parse(\"1\"), json.parse(\"1.0\"), json.parse(\"-0\"), json.parse(\"2e3\"), json.parse(\"9223372036854775808\")]"), "[1, 1.0, 0, 2000.0, 9.223372036854776e18]");
+    assert_eq!(repr("json.stringify([1, 1.0, 0.1, 1e300])"), "\"[1,1.0,0.1,1e300]\"");
+    assert_eq!(repr("json.parse(json.stringify([3, 3.0]))"), "[3, 3.0]");
+}
+
+#[test]
+fn values_stringify_compactly_or_indented() {
+    let source = "let o = new Object();\n\
+        o.b = [1, \"two\\n\", null];\n\
+        o.a = true;\n\
+        o[\"c d\"] = new Object();\n\
+        [json.stringify(o), json.stringify(o, 2)]";
+    assert_eq!(repr(source), "[\"{\\\"a\\\":true,\\\"b\\\":[1,\\\"two\\\\n\\\",null],\\\"c d\\\":{}}\", \"{\\n  \\\"a\\\": true,\\n  \\\"b\\\": [\\n    1,\\n    \\\"two\\\\n\\\",\\n    null\\n  ],\\n  \\\"c d\\\": {}\\n}\"]");
+    assert_eq!(repr("json.stringify([[]], 0)"), "\"[[]]\"");
+    assert_eq!(repr("json.stringify(\"\\t\", null)"), "\"\\\"\\\\t\\\"\"");
+    // instances of script classes are objects too
+    assert_eq!(repr("class P { constructor(x) { this.x = x; } }\njson.stringify(new P(1))"), "\"{\\\"x\\\":1}\"");
+}
+
+#[test]
+fn unserialisable_values_are_refused() {
+    assert_eq!(error("json.stringify([fn(x) { x }])"), (String::from("TypeError"), String::from("Cannot convert a value of type Function to JSON")));
+    assert_eq!(error("json.stringify(Ok(1))").0, "TypeError");
+    assert_eq!(error("json.stringify([1e300 * 1e300])"), (String::from("ValueError"), String::from("Cannot convert inf to JSON")));
+    assert_eq!(error("let l = [1];\nl.push(l);\njson.stringify(l)"), (String::from("ValueError"), String::from("Cannot convert a cyclic structure to JSON")));
+    assert_eq!(error("let o = new Object();\no.self = o;\njson.stringify(o)").1, "Cannot convert a cyclic structure to JSON");
+    // a value shared between two places is not a cycle
+    assert_eq!(repr("let l = [1];\njson.stringify([l, l])"), "\"[[1],[1]]\"");
+    assert_eq!(error("json.stringify(1, 11)").1, "json.stringify needs an indent from 0 to 10, not 11");
+}
+
+#[test]
+fn malformed_documents_report_where() {
+    for (text, message) in [
+        ("[1, 2,]", "Invalid JSON at line 1 column 7: unexpected character ']'"),
+        ("{\\\"a\\\" 1}", "Invalid JSON at line 1 column 6: expected ':' after an object key"),
+        ("[\\n01]", "Invalid JSON at line 2 column 3: numbers cannot have leading zeros"),
+        ("tru", "Invalid JSON at line 1 column 4: expected 'true'"),
+        ("1 2", "Invalid JSON at line 1 column 3: unexpected text after the value"),
+        ("\\\"open", "Invalid JSON at line 1 column 6: unterminated string"),
+        ("", "Invalid JSON at line 1 column 1: unexpected end of input"),
+    ] {
+        assert_eq!(error(&format!("json.parse(\"{}\")", text)), (String::from("ValueError"), String::from(message)), "{}", text);
+    }
+    let deep = format!("json.parse(\"{}\")", "[".repeat(600));
+    assert_eq!(error(&deep).1, "Invalid JSON at line 1 column 514: nested more than 512 levels deep");
+}
+
+#[test]
+fn json_is_importable() {
+    assert_eq!(repr("import { parse, stringify } from \"json\";\nstringify(parse(\"[1,{}]\"))"), "\"[1,{}]\"");
+}
@@ -0,0 +1,201 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+use shtark::Limits;
+use shtark::{Engine, EngineError, RuntimeVal};
+use common::{error_kind, on_both_engines, Mode};
+
+const FIB: &str = "fn fib(n) { return match n { 0 => 0, 1 => 1, _ => fib(n - 1) + fib(n - 2) }; }\n";
+const COUNTDOWN: &str = "fn down(n) { return match n { 0 => 0, _ => down(n - 1) }; }\n";
+
+fn engine(mode: Mode, limits: Limits) -> Engine {
+    let mut engine = mode.engine();
+    engine.set_limits(limits);
+    return engine
+}
+
+#[test]
+fn fuel_bounds_the_number_of_steps() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { fuel: Some(2_000), ..Limits::default() });
+        engine.eval(COUNTDOWN).unwrap();
+        assert_eq!(engine.eval("down(10)").unwrap(), RuntimeVal::from(0));
+        assert_eq!(error_kind(engine.eval("down(1000)")), "FuelError");
+        // catching the error does not buy more fuel
+        assert_eq!(error_kind(engine.eval("try { down(1000); } catch (e) { down(1); }")), "FuelError");
+        // every evaluation starts with a full tank
+        assert_eq!(engine.eval("down(10)").unwrap(), RuntimeVal::from(0));
+    });
+}
+
+#[test]
+fn timeouts_stop_long_evaluations() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { timeout: Some(Duration::from_millis(50)), ..Limits::default() });
+        let start = Instant::now();
+        assert_eq!(error_kind(engine.eval(&format!("{}fib(40)", FIB))), "TimeoutError");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    });
+}
+
+#[test]
+fn hosts_can_interrupt_from_another_thread() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        let interrupt = engine.interrupt_handle();
+        let setter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(error_kind(engine.eval(&format!("{}fib(40)", FIB))), "InterruptError");
+        setter.join().unwrap();
+        // the flag is cleared once the interrupted evaluation returns
+        assert_eq!(engine.eval("1 + 1").unwrap(), RuntimeVal::from(2));
+    });
+}
+
+#[test]
+fn an_interrupt_set_before_evaluating_stops_the_next_evaluation() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        engine.interrupt_handle().store(true, Ordering::Relaxed);
+        assert_eq!(error_kind(engine.eval("let x = 1;")), "InterruptError");
+        assert_eq!(engine.get_global("x"), None);
+        assert_eq!(engine.eval("1 + 1").unwrap(), RuntimeVal::from(2));
+    });
+}
+
+#[test]
+fn call_depth_is_bounded() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { max_call_depth: 50, ..Limits::default() });
+        engine.eval(COUNTDOWN).unwrap();
+        assert_eq!(engine.eval("down(40)").unwrap(), RuntimeVal::from(0));
+        assert_eq!(error_kind(engine.eval("down(60)")), "RecursionError");
+        let caught = engine.eval("try { down(60); } catch (e) { e.kind }").unwrap();
+        assert_eq!(String::try_from(caught).unwrap(), "RecursionError");
+    });
+}
+
+#[test]
+fn default_depth_does_not_overflow_the_native_stack() {
+    // test threads have small stacks, so this only passes if deep calls grow the stack
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        engine.eval(COUNTDOWN).unwrap();
+        assert_eq!(engine.eval("down(1900)").unwrap(), RuntimeVal::from(0));
+        assert_eq!(error_kind(engine.eval("down(5000)")), "RecursionError");
+    });
+}
+
+#[test]
+fn object_count_is_bounded() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { max_objects: Some(20), ..Limits::default() });
+        engine.eval("class Box { constructor(v) { this.v = v; } }\nenum Pair { Of(a, b) }").unwrap();
+        engine.eval("fn boxes(n) { return match n { 0 => 0, _ => { new Box(n); boxes(n - 1) } }; }").unwrap();
+        assert_eq!(engine.eval("boxes(10)").unwrap(), RuntimeVal::from(0));
+        assert_eq!(error_kind(engine.eval("boxes(30)")), "MemoryError");
+        assert_eq!(error_kind(engine.eval("fn pairs(n) { Pair.Of(n, n); return match n { 0 => 0, _ => pairs(n - 1) }; }\npairs(30)")), "MemoryError");
+        assert_eq!(error_kind(engine.eval("fn closures(n) { let f = fn() { n }; return match n { 0 => 0, _ => closures(n - 1) }; }\nclosures(30)")), "MemoryError");
+    });
+}
+
+#[test]
+fn long_lists_and_strings_count_against_the_memory_limit() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits::sandboxed(10_000, Duration::from_millis(50), 100));
+        let start = Instant::now();
+        assert_eq!(error_kind(engine.eval("range(0, 30000000).collect()")), "MemoryError");
+        assert_eq!(error_kind(engine.eval("\"ab\".repeat(300000000)")), "MemoryError");
+        assert_eq!(error_kind(engine.eval("\"x\".pad_start(300000000)")), "MemoryError");
+        assert_eq!(error_kind(engine.eval("let xs = []; for x in range(0, 200) { xs.push(x); }")), "MemoryError");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        // short ones are cheap
+        assert_eq!(engine.eval("\"ab\".repeat(100).len()").unwrap(), RuntimeVal::from(200));
+    });
+}
+
+#[test]
+fn list_methods_tick_once_per_item() {
+    on_both_engines(|mode| {
+        // chars() builds its list without running a step, so the fuel goes on the method itself;
+        // sorting and deduplicating tick once per comparison
+        let mut engine = engine(mode, Limits { fuel: Some(1_000), ..Limits::default() });
+        for method in ["sort()", "unique()", "map(fn(x) { x })", "group_by(fn(x) { x })"] {
+            assert_eq!(error_kind(engine.eval(&format!("\"abc\".repeat(2000).chars().{}", method))), "FuelError", "{}", method);
+        }
+    });
+}
+
+#[test]
+fn timeouts_and_interrupts_stop_running_commands() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { timeout: Some(Duration::from_millis(100)), ..Limits::default() });
+        let start = Instant::now();
+        assert_eq!(error_kind(engine.eval("exec(\"sleep\", [\"5\"])")), "TimeoutError");
+        assert_eq!(error_kind(engine.eval("$(sleep 5 | cat)")), "TimeoutError");
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        let mut engine = mode.engine();
+        let interrupt = engine.interrupt_handle();
+        let setter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        assert_eq!(error_kind(engine.eval("exec(\"sleep\", [\"5\"])")), "InterruptError");
+        assert!(start.elapsed() < Duration::from_secs(3));
+        setter.join().unwrap();
+    });
+}
+
+#[test]
+fn io_can_be_switched_off() {
+    on_both_engines(|mode| {
+        let mut engine = engine(mode, Limits { allow_io: false, ..Limits::default() });
+        assert_eq!(error_kind(engine.eval("print(\"leak\");")), "PermissionError");
+        assert_eq!(error_kind(engine.eval("import { answer } from \"lib.shtark\";")), "PermissionError");
+        // pure computation is unaffected
+        assert_eq!(engine.eval("6 * 7").unwrap(), RuntimeVal::from(42));
+    });
+}
+
+#[test]
+fn sandboxed_limits_combine_every_restriction() {
+    let limits = Limits::sandboxed(10_000, Duration::from_secs(1), 100);
+    assert!(!limits.allow_io);
+    let mut engine = engine(Mode::default(), limits);
+    assert_eq!(error_kind(engine.eval(&format!("{}fib(25)", FIB))), "FuelError");
+    assert_eq!(engine.limits().fuel, Some(10_000));
+}
+
+#[test]
+fn deep_nesting_is_a_syntax_error_rather_than_a_stack_overflow() {
+    let sum = vec!["1"; 3000].join(" + ");
+    let too_deep = [
+        format!("print({});", sum),
+        format!("{}1{}", "(".repeat(2000), ")".repeat(2000)),
+        // refused even where it would never run
+        format!("fn never() {{ print({}); }}", sum),
+    ];
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        for source in &too_deep {
+            match engine.eval(source) {
+                Err(EngineError::Syntax(error)) => assert_eq!(error.message, "Nesting is deeper than 512 levels."),
+                other => panic!("expected a syntax error, got {:?}", other),
+            }
+        }
+        // test threads have small stacks, so nesting just inside the limit must not overflow them either
+        assert_eq!(engine.eval(&vec!["1"; 500].join(" + ")).unwrap(), RuntimeVal::from(500));
+        engine.set_opt_level(2);
+        assert_eq!(engine.eval(&format!("let n = 1;\n{}", vec!["n"; 500].join(" + "))).unwrap(), RuntimeVal::from(500));
+        let parens = format!("{}1{}", "(".repeat(500), ")".repeat(500));
+        assert_eq!(engine.eval(&parens).unwrap(), RuntimeVal::from(1));
+        let lists = format!("{}{}", "[".repeat(500), "]".repeat(500));
+        assert_eq!(engine.eval(&format!("{}.len()", lists)).unwrap(), RuntimeVal::from(1));
+    });
+}
@@ -0,0 +1,91 @@
+#![allow(clippy::needless_return)]
+mod common;
+use common::{error, on_both_engines, repr};
+
+#[test]
+fn callbacks_run_on_both_engines() {
+    let source = "let xs = [1, 2, 3, 4, 5, 6];\n\
+        [xs.map(fn(x) { x * x }), xs.filter(fn(x) { x % 2 == 0 }), xs.reduce(fn(a, b) { a + b }), xs.reduce(fn(a, b) { a + b }, 100),\n\
+        xs.find(fn(x) { x % 4 == 0 }), xs.find(fn(x) { x == 9 }), xs.any(fn(x) { x == 3 }), xs.all(fn(x) { x == 3 }),\n\
+        [1, 2].flat_map(fn(x) { [x, x * 10] })]";
+    on_both_engines(|mode| {
+        assert_eq!(
+            mode.repr(source),
+            "[[1, 4, 9, 16, 25, 36], [2, 4, 6], 21, 121, 4, null, true, false, [1, 10, 2, 20]]"
+        );
+    });
+}
+
+#[test]
+fn reshaping_methods() {
+    let cases = [
+        ("[1, 2, 3].zip([\"a\", \"b\"])", "[[1, \"a\"], [2, \"b\"]]"),
+        ("[\"a\", \"b\"].enumerate()", "[[0, \"a\"], [1, \"b\"]]"),
+        ("[1, 2, 3].reverse()", "[3, 2, 1]"),
+        ("[1, 1.0, 2, 1, \"1\"].unique()", "[1, 2, \"1\"]"),
+        ("[1, 2, 3, 4, 5].chunks(2)", "[[1, 2], [3, 4], [5]]"),
+        ("[1, 2, 3, 4].windows(3)", "[[1, 2, 3], [2, 3, 4]]"),
+        ("[1, 2].windows(3)", "[]"),
+        ("[1, 2, 3, 4, 5].group_by(fn(x) { x % 2 })", "[[1, [1, 3, 5]], [0, [2, 4]]]"),
+        ("[].reduce(fn(a, b) { a + b }, 0)", "0"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(repr(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn sorting_is_stable_and_leaves_the_original_alone() {
+    let cases = [
+        ("[3, 1.5, 2, 0 - 1].sort()", "[-1, 1.5, 2, 3]"),
+        ("[\"pear\", \"Apple\", \"fig\"].sort()", "[\"Apple\", \"fig\", \"pear\"]"),
+        ("[[2, 1], [1, 5], [1]].sort()", "[[1], [1, 5], [2, 1]]"),
+        ("[\"bb\", \"a\", \"cc\", \"d\"].sort_by(fn(s) { s.len() })", "[\"a\", \"d\", \"bb\", \"cc\"]"),
+        ("let xs = [2, 1]; let ys = xs.sort(); [xs, ys]", "[[2, 1], [1, 2]]"),
+        ("range(0, 40).collect().sort_by(fn(x) { x % 3 })", "[0, 3, 6, 9, 12, 15, 18, 21, 24, 27, 30, 33, 36, 39, 1, 4, 7, 10, 13, 16, 19, 22, 25, 28, 31, 34, 37, 2, 5, 8, 11, 14, 17, 20, 23, 26, 29, 32, 35, 38]"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(repr(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn bad_callbacks_and_arguments_raise_errors() {
+    let cases = [
+        ("[1].filter(fn(x) { x })", "TypeError", "The function given to filter must return a Boolean, not Number"),
+        ("[1].flat_map(fn(x) { x })", "TypeError", "The function given to flat_map must return a List, not Number"),
+        ("[1, \"a\"].sort()", "TypeError", "Cannot order Number and String"),
+        ("range(0, 40).collect().map(fn(x) { match x { 17 => \"x\", _ => x } }).sort()", "TypeError", "Cannot order String and Number"),
+        ("[].reduce(fn(a, b) { a + b })", "ValueError", "reduce of an empty list needs an initial value"),
+        ("[1].chunks(0)", "ValueError", "chunks needs a size of at least 1, not 0"),
+        ("[1].map()", "TypeError", "map expects 1 argument(s) but received 0"),
+        ("[1].map(fn(x) { 1 / 0 })", "ZeroDivisionError", "Division by zero."),
+        ("[1].sum()", "TypeError", "List has no method 'sum'"),
+    ];
+    for (source, kind, message) in cases {
+        assert_eq!(error(source), (String::from(kind), String::from(message)), "{}", source);
+    }
+}
+
+#[test]
+fn callbacks_see_a_snapshot_of_the_list() {
+    assert_eq!(repr("let xs = [1, 2]; let ys = xs.map(fn(x) { xs.push(x); x }); [xs, ys]"), "[[1, 2, 1, 2], [1, 2]]");
+}
+
+#[test]
+fn functions_are_only_equal_to_themselves() {
+    let source = "let f = fn(x) { x }; let g = fn(x) { x + 1 }; let h = f;\n\
+        [f == g, f == h, fn(x) { x } == fn(x) { x }, [f, g, h].unique().len(), [g, f].map(fn(k) { k(1) })]";
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr(source), "[false, true, false, 2, [2, 1]]");
+    });
+}
+
+#[test]
+fn lists_that_contain_themselves_compare_without_overflowing() {
+    let source = "let a = [1]; a.push(a); let b = [1]; b.push(b); let c = [2]; c.push(c);\n\
+        [a == b, a == c, a == a, a != c, [a, b, c].unique().len(), [c, a].sort() == [a, c], [a, b].sort().len(), [a, c, b].group_by(fn(x) { x }).len()]";
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr(source), "[true, false, true, true, 2, true, 2, 2]");
+    });
+}
@@ -0,0 +1,55 @@
+#![allow(clippy::needless_return)]
+use std::fs;
+use std::process::Command;
+
+// runs `source` and returns its exit code and printed output
+fn run(name: &str, source: &str) -> (Option<i32>, String) {
+    let path = std::env::temp_dir().join(format!("shtark-literals-{}-{}.shtark", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_shtark")).arg(&path).output().unwrap();
+    fs::remove_file(&path).unwrap();
+    return (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn identifiers_take_digits_and_underscores() {
+    let (code, output) = run("identifiers", "let _a1 = 1; let b_2 = _a1 + 1; print(b_2);");
+    assert_eq!(code, Some(0));
+    assert_eq!(output, "2\n");
+}
+
+#[test]
+fn identifiers_follow_unicode_xid_rules() {
+    // a base letter followed by a combining acute accent, which XID_Continue allows
+    let (code, output) = run("combining", "let e\u{301}t\u{e9} = 3; let \u{3c0}_2 = 1; print(e\u{301}t\u{e9} + \u{3c0}_2);");
+    assert_eq!(code, Some(0));
+    assert_eq!(output, "4\n");
+    // numeric symbols and marks are not identifier characters, even after a letter
+    for (name, source, character) in [("superscript", "let x\u{b2} = 1;", '\u{b2}'), ("fraction", "let \u{bd} = 1;", '\u{bd}'), ("mark", "let \u{301}a = 1;", '\u{301}')] {
+        let (code, output) = run(name, source);
+        assert_eq!(code, Some(1), "{}", name);
+        assert!(output.starts_with(&format!("Unrecognized character found: {}", character)), "{}: {}", name, output);
+    }
+}
+
+#[test]
+fn malformed_literals_are_reported_precisely() {
+    let cases = [
+        ("prefix", "0x;", "Missing digits in hexadecimal literal 0x"),
+        ("binary", "0b102;", "Invalid digit '2' in binary literal 0b102"),
+        ("octal", "0o8;", "Invalid digit '8' in octal literal 0o8"),
+        ("suffix", "12ab;", "Invalid digit 'a' in decimal literal 12ab"),
+        ("doubled", "1__000;", "Misplaced '_' in numeric literal 1__000"),
+        ("trailing", "0xff_;", "Misplaced '_' in numeric literal 0xff_"),
+        ("overflow", "9223372036854775808;", "Numeric literal 9223372036854775808 does not fit in a 64-bit integer"),
+        ("fraction", "1.5x;", "Invalid digit 'x' in float literal 1.5x"),
+        ("exponent", "2e+;", "Missing digits in the exponent of float literal 2e+"),
+        ("float separator", "1_.5;", "Misplaced '_' in numeric literal 1_.5"),
+        ("huge", "1e400;", "Float literal 1e400 is too large"),
+    ];
+    for (name, source, message) in cases {
+        let (code, output) = run(name, source);
+        assert_eq!(code, Some(1), "{}", name);
+        assert!(output.starts_with(message), "{}: {}", name, output);
+    }
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::needless_return)]
+mod common;
+use shtark::{Engine, RuntimeVal};
+use common::{error, eval};
+
+#[test]
+fn integer_inputs_give_exact_integer_results() {
+    let cases = [
+        ("math.abs(0 - 5)", 5),
+        ("math.min(4, 0 - 2, 9)", -2),
+        ("math.max(4, 0 - 2, 9)", 9),
+        ("math.clamp(0 - 3, 0, 10)", 0),
+        ("math.pow(3, 4)", 81),
+        ("math.pow(0 - 1, 9223372036854775807)", -1),
+        ("math.isqrt(9223372036854775807)", 3037000499),
+        ("math.isqrt(0)", 0),
+        ("math.floor(7)", 7),
+        ("math.gcd(0 - 48, 36)", 12),
+        ("math.gcd(0, 0)", 0),
+        ("math.lcm(0 - 4, 6)", 12),
+        ("math.lcm(0, 6)", 0),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval(source), RuntimeVal::from(expected), "{}", source);
+    }
+}
+
+#[test]
+fn float_inputs_give_float_results() {
+    let cases = [
+        ("math.abs(0 - 2.5)", 2.5),
+        ("math.max(1, 2.5)", 2.5),
+        ("math.pow(2, 0 - 2)", 0.25),
+        ("math.pow(4, 0.5)", 2.0),
+        ("math.sqrt(2.25)", 1.5),
+        ("math.log(100, 10)", 2.0),
+        ("math.log2(8)", 3.0),
+        ("math.exp(0)", 1.0),
+        ("math.cos(math.PI)", -1.0),
+        ("math.acos(1)", 0.0),
+        ("1 + 0.25", 1.25),
+        ("1.5 * 2", 3.0),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval(source), RuntimeVal::from(expected), "{}", source);
+    }
+    assert_eq!(eval("math.sin(math.PI / 2)"), RuntimeVal::from(1.0));
+    assert_eq!(eval("math.E"), RuntimeVal::from(std::f64::consts::E));
+}
+
+#[test]
+fn rounding_turns_floats_into_integers() {
+    let cases = [
+        ("math.floor(0 - 2.5)", -3),
+        ("math.ceil(0 - 2.5)", -2),
+        ("math.round(2.5)", 3),
+        ("math.round(0 - 2.5)", -3),
+        ("math.round(2.4999)", 2),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval(source), RuntimeVal::from(expected), "{}", source);
+    }
+}
+
+#[test]
+fn invalid_inputs_raise_distinct_errors() {
+    let cases = [
+        ("math.sqrt(0 - 1)", "ValueError", "math.sqrt is undefined for -1"),
+        ("math.log(0)", "ValueError", "math.log is undefined for 0"),
+        ("math.log(8, 1)", "ValueError", "math.log is undefined for 1"),
+        ("math.asin(1.5)", "ValueError", "math.asin is undefined for 1.5"),
+        ("math.isqrt(0 - 4)", "ValueError", "math.isqrt is undefined for -4"),
+        ("math.clamp(5, 10, 0)", "ValueError", "math.clamp lower bound 10 is above upper bound 0"),
+        ("math.pow(2, 63)", "OverflowError", "math.pow(2, 63) does not fit in a 64-bit integer"),
+        ("math.floor(1e300)", "OverflowError", "math.floor result 1e300 does not fit in a 64-bit integer"),
+        ("math.lcm(9223372036854775807, 2)", "OverflowError", "math.lcm(9223372036854775807, 2) does not fit in a 64-bit integer"),
+        ("math.gcd(2.0, 4)", "TypeError", "Expected a Number but found Float"),
+        ("math.sqrt(\"4\")", "TypeError", "Expected a Number or Float but found String"),
+        ("math.min()", "TypeError", "math.min expects at least 1 argument"),
+        ("1.5 / 0", "ZeroDivisionError", "Division by zero."),
+    ];
+    for (source, kind, message) in cases {
+        assert_eq!(error(source), (String::from(kind), String::from(message)), "{}", source);
+    }
+}
+
+#[test]
+fn math_can_be_imported_by_name_without_io() {
+    let mut engine = Engine::new();
+    engine.set_limits(shtark::Limits { allow_io: false, ..Default::default() });
+    let value = engine.eval("import { gcd } from \"math\";\nimport * as m from \"math\";\ngcd(m.isqrt(64), 12)").unwrap();
+    assert_eq!(value, RuntimeVal::from(4));
+}
+
+#[test]
+fn integers_and_floats_compare_by_value() {
+    assert_eq!(eval("1 == 1.0"), RuntimeVal::from(true));
+    assert_eq!(eval("0.1 + 0.2 == 0.3"), RuntimeVal::from(false));
+    assert_eq!(eval("match 3.0 { 3 => \"three\", _ => \"other\" }"), RuntimeVal::from("three"));
+    assert_eq!(eval("2.0").to_string(), "2.0");
+    assert_eq!(eval("1e21").to_string(), "1e21");
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::fs;
+use shtark::Limits;
+use shtark::{EngineError, RuntimeVal};
+use common::on_both_engines;
+
+#[test]
+fn exec_reports_output_and_status() {
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr("exec(\"echo\", [\"hello\", \"world\"])"), "Process.Output(\"hello world\\n\", \"\", 0)");
+        assert_eq!(mode.repr("exec(\"sh\", [\"-c\", \"echo oops 1>&2; exit 3\"])"), "Process.Output(\"\", \"oops\\n\", 3)");
+        assert_eq!(mode.repr("exec(\"true\").status"), "0");
+        let source = "match exec(\"false\") { Process.Output(_, _, status) => status, _ => null }";
+        assert_eq!(mode.repr(source), "1");
+        let (kind, message) = mode.error("exec(\"shtark-no-such-program\")");
+        assert_eq!(kind, "IOError");
+        assert!(message.starts_with("Cannot run 'shtark-no-such-program': "), "{}", message);
+        assert_eq!(mode.error("exec(\"echo\", [1])").0, "TypeError");
+    });
+}
+
+#[test]
+fn captures_evaluate_to_trimmed_stdout() {
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr("$(echo hi)"), "\"hi\"");
+        // quotes group words and may hold parentheses and pipes
+        assert_eq!(mode.repr("$(printf \"%s|%s\" \"a)\" 'b c')"), "\"a)|b c\"");
+        assert_eq!(mode.repr("$(echo shout | tr a-z A-Z).to_lower()"), "\"shout\"");
+        assert_eq!(mode.error("$(sh -c \"echo broken 1>&2; exit 2\")"), (String::from("ProcessError"), String::from("Command 'sh -c \"echo broken 1>&2; exit 2\"' exited with status 2: broken")));
+        assert_eq!(mode.error("run(\"echo 'open\")"), (String::from("ValueError"), String::from("Unterminated quote in command 'echo 'open'")));
+        // a quote left open swallows the closing parenthesis
+        assert!(matches!(mode.engine().eval("$(echo \"open)"), Err(EngineError::Syntax(error)) if error.incomplete));
+    });
+}
+
+#[test]
+fn pipelines_wire_stdout_to_stdin() {
+    on_both_engines(|mode| {
+        let dir = std::env::temp_dir().join(format!("shtark-process-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("words.txt");
+        fs::write(&file, "apple\nfoo bar\nbanana\nfood\n").unwrap();
+        let mut engine = mode.engine();
+        engine.set_global("file", file.to_string_lossy().into_owned());
+        // nothing runs until the pipeline is exec'd
+        assert_eq!(engine.eval("let p = run(\"cat {}\".format(file)) | run(\"grep foo\") | run(\"wc -l\");\np.stages.len()").unwrap(), RuntimeVal::from(3));
+        assert_eq!(engine.eval("exec(p).stdout.trim()").unwrap(), RuntimeVal::from("2"));
+        assert_eq!(engine.eval("exec(run(\"cat {} | grep ban\".format(file))).stdout").unwrap(), RuntimeVal::from("banana\n"));
+        // the status is the last stage's
+        assert_eq!(engine.eval("exec(run(\"false\") | run(\"cat\")).status").unwrap(), RuntimeVal::from(0));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mode.error("run(\"echo a\") | 1"), (String::from("TypeError"), String::from("Unsupported operand types for |: Variant and Number")));
+        assert_eq!(mode.error("run(\"echo a |\")"), (String::from("ValueError"), String::from("Empty command in 'echo a |'")));
+    });
+}
+
+#[test]
+fn environment_variables_can_be_read_and_written() {
+    on_both_engines(|mode| {
+        let source = "setenv(\"SHTARK_PROCESS_TEST\", \"one two\");\n\
+            let seen = [getenv(\"SHTARK_PROCESS_TEST\"), $(sh -c \"echo $SHTARK_PROCESS_TEST\")];\n\
+            setenv(\"SHTARK_PROCESS_TEST\", null);\n\
+            seen.push(getenv(\"SHTARK_PROCESS_TEST\"));\n\
+            seen";
+        assert_eq!(mode.repr(source), "[\"one two\", \"one two\", null]");
+        assert_eq!(mode.error("setenv(\"A=B\", \"x\")").0, "ValueError");
+    });
+}
+
+#[test]
+fn exit_ends_the_evaluation_past_try_and_finally() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        let source = "let log = [];\n\
+            fn leave() { try { exit(7); } catch (e) { log.push(\"caught\"); } finally { log.push(\"finally\"); } }\n\
+            leave();\n\
+            log.push(\"after\");";
+        assert!(matches!(engine.eval(source), Err(EngineError::Exit(7))));
+        assert_eq!(engine.eval("log").unwrap().repr(), "[]");
+        assert!(matches!(engine.eval("exit()"), Err(EngineError::Exit(0))));
+        // the engine stays usable for the host
+        assert_eq!(engine.eval("1 + 1").unwrap(), RuntimeVal::from(2));
+        assert_eq!(mode.error("exit(5_000_000_000)"), (String::from("ValueError"), String::from("Exit status 5000000000 is out of range")));
+    });
+}
+
+#[test]
+fn processes_need_io() {
+    on_both_engines(|mode| {
+        let mut engine = mode.engine();
+        engine.set_limits(Limits { allow_io: false, ..Limits::default() });
+        for source in ["exec(\"echo\")", "$(echo hi)", "getenv(\"HOME\")", "setenv(\"X\", \"y\")", "exec(run(\"echo\"))"] {
+            match engine.eval(source) {
+                Err(EngineError::Runtime(error)) => assert_eq!(error.kind, "PermissionError", "{}", source),
+                other => panic!("expected {} to be refused, got {:?}", source, other),
+            }
+        }
+        // describing a command runs nothing, so it is allowed
+        assert_eq!(engine.eval("run(\"echo a\").stages").unwrap().repr(), "[[\"echo\", \"a\"]]");
+    });
+}
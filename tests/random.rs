@@ -0,0 +1,65 @@
+#![allow(clippy::needless_return)]
+mod common;
+use common::{error, on_both_engines};
+
+#[test]
+fn seeding_reproduces_a_run_exactly() {
+    on_both_engines(|mode| {
+        let source = "random.seed(42);\n\
+            let drawn = [random.int(1, 6), random.int(1, 6), random.float()];\n\
+            let xs = [1, 2, 3, 4, 5];\n\
+            random.shuffle(xs);\n\
+            [drawn, xs, random.choice(xs), random.sample(xs, 3)]";
+        assert_eq!(mode.repr(source), "[[1, 1, 0.6800434110281394], [2, 3, 5, 1, 4], 5, [1, 5, 2]]");
+        let source = "random.seed(7);\n\
+            let first = [random.int(0, 1000000), random.float()];\n\
+            random.seed(7);\n\
+            first == [random.int(0, 1000000), random.float()]";
+        assert_eq!(mode.repr(source), "true");
+    });
+}
+
+#[test]
+fn draws_stay_within_their_bounds() {
+    on_both_engines(|mode| {
+        let source = "random.seed(1);\n\
+            let rolls = range(0, 600).map(fn(_) { return random.int(1, 6); }).collect();\n\
+            let floats = range(0, 100).map(fn(_) { return random.float(); }).collect();\n\
+            [rolls.unique().sort(), floats.all(fn(f) { return math.floor(f) == 0; }), random.int(5, 5)]";
+        assert_eq!(mode.repr(source), "[[1, 2, 3, 4, 5, 6], true, 5]");
+        assert_eq!(mode.repr("random.int(0 - 9223372036854775807 - 1, 9223372036854775807) == null"), "false");
+    });
+}
+
+#[test]
+fn shuffles_and_samples_keep_the_items() {
+    on_both_engines(|mode| {
+        let source = "let xs = range(0, 20).collect();\n\
+            random.shuffle(xs);\n\
+            let picked = random.sample(xs, 20);\n\
+            [xs.sort() == range(0, 20).collect(), picked.unique().len(), random.sample(xs, 0), random.shuffle([])]";
+        assert_eq!(mode.repr(source), "[true, 20, [], null]");
+    });
+}
+
+#[test]
+fn bad_arguments_are_reported() {
+    assert_eq!(error("random.int(6, 1)"), (String::from("ValueError"), String::from("random.int needs lo <= hi, not 6 > 1")));
+    assert_eq!(error("random.choice([])"), (String::from("ValueError"), String::from("Cannot choose from an empty list")));
+    assert_eq!(error("random.sample([1, 2], 3)"), (String::from("ValueError"), String::from("Cannot sample 3 items from a list of 2")));
+    assert_eq!(error("random.shuffle(\"abc\")").0, "TypeError");
+    assert_eq!(error("random.seed(1.5)").0, "TypeError");
+    assert_eq!(error("random.seed(0 - 1)"), (String::from("ValueError"), String::from("random.seed needs a seed of at least 0, not -1")));
+}
+
+#[test]
+fn each_engine_has_its_own_generator() {
+    on_both_engines(|mode| {
+        let (mut seeded, mut other) = (mode.engine(), mode.engine());
+        seeded.eval("random.seed(42);").unwrap();
+        other.eval("random.seed(7);").unwrap();
+        // draws and reseeding in one engine do not move the other's sequence
+        other.eval("random.int(0, 100); random.seed(1);").unwrap();
+        assert_eq!(seeded.eval("[random.int(1, 6), random.int(1, 6)]").unwrap().repr(), "[1, 1]");
+    });
+}
@@ -0,0 +1,74 @@
+#![allow(clippy::needless_return)]
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn history_file(name: &str) -> PathBuf {
+    return std::env::temp_dir().join(format!("shtark-repl-{}-{}.history", name, std::process::id()))
+}
+
+// feeds `input` to an interactive session and returns everything it printed
+fn session(name: &str, input: &str) -> String {
+    let history = history_file(name);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shtark"))
+        .env("SHTARK_HISTORY", &history)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    return String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn unfinished_input_continues_on_the_next_line() {
+    let output = session("continuation", "fn add(a, b) {\n    return a +\n        b;\n}\nprint(add(40, 2));\n\"two\nlines\"\n");
+    assert_eq!(output, "42\n\"two\\nlines\"\n");
+    fs::remove_file(history_file("continuation")).unwrap();
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let output = session("errors", "let x 5;\n#\nprint(1);\n");
+    assert!(output.contains("Expected 'equals' token"), "{}", output);
+    assert!(output.contains("Unrecognized character found: #"), "{}", output);
+    assert!(output.ends_with("\n1\n"), "{}", output);
+    fs::remove_file(history_file("errors")).unwrap();
+}
+
+#[test]
+fn meta_commands() {
+    let script = std::env::temp_dir().join(format!("shtark-repl-load-{}.shtark", std::process::id()));
+    fs::write(&script, "const loaded = 7;\n").unwrap();
+    let input = format!(":load {}\n:env\n:tokens let a1 = 0xF;\n:ast 1 + 2\n:time loaded\n:reset\n:env\n:nope\n", script.display());
+    let output = session("commands", &input);
+    fs::remove_file(&script).unwrap();
+    assert!(output.contains("const loaded = 7\n"), "{}", output);
+    assert!(output.contains("Identifier \"a1\"\n"), "{}", output);
+    assert!(output.contains("Number \"0xF\"\n"), "{}", output);
+    assert!(output.contains("operator: Add"), "{}", output);
+    assert!(output.contains("Took "), "{}", output);
+    assert!(output.contains("Session reset.\nNo bindings yet.\n"), "{}", output);
+    assert!(output.contains("Unknown command :nope."), "{}", output);
+    fs::remove_file(history_file("commands")).unwrap();
+}
+
+#[test]
+fn history_is_saved_between_sessions() {
+    session("history", "let first = 1;\n:env\n");
+    let saved = fs::read_to_string(history_file("history")).unwrap();
+    assert!(saved.contains("let first = 1;"), "{}", saved);
+    assert!(saved.contains(":env"), "{}", saved);
+    fs::remove_file(history_file("history")).unwrap();
+}
+
+#[test]
+fn echo_uses_repr_and_skips_declarations() {
+    let input = "let x = 1;\nfn f() { }\nf()\nx + 2\n\"text\"\nOk(\"x\")\nclass Node { constructor() { this.me = this; } }\nnew Node()\n";
+    let output = session("echo", input);
+    assert_eq!(output, "3\n\"text\"\nOk(\"x\")\nNode { me: <cycle Node> }\n");
+    fs::remove_file(history_file("echo")).unwrap();
+}
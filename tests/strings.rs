@@ -0,0 +1,93 @@
+#![allow(clippy::needless_return)]
+mod common;
+use shtark::{Engine, RuntimeVal};
+use common::{error, eval, repr};
+
+#[test]
+fn lengths_and_indexes_count_characters_not_bytes() {
+    assert_eq!(eval("\"naïve ☃\".len()"), RuntimeVal::from(7));
+    assert_eq!(eval("\"naïve ☃\"[2]"), RuntimeVal::from("ï"));
+    assert_eq!(eval("\"naïve ☃\"[6]"), RuntimeVal::from("☃"));
+    assert_eq!(repr("\"ï☃\".chars()"), "[\"ï\", \"☃\"]");
+    assert_eq!(repr("\"ï☃\".bytes()"), "[195, 175, 226, 152, 131]");
+    assert_eq!(eval("\"7\".pad_start(3, \"ï\")"), RuntimeVal::from("ïï7"));
+    assert_eq!(error("\"ab\"[2]"), (String::from("IndexError"), String::from("Index 2 is out of range for length 2")));
+    assert_eq!(error("\"ab\"[0 - 1]"), (String::from("IndexError"), String::from("Index -1 is out of range for length 2")));
+}
+
+#[test]
+fn text_methods() {
+    let cases = [
+        ("\"a,b,,c\".split(\",\")", "[\"a\", \"b\", \"\", \"c\"]"),
+        ("\"-\".join([\"x\", 1, 2.5, null])", "\"x-1-2.5-null\""),
+        ("\"\".join([])", "\"\""),
+        ("\" \\t padded\\n\".trim()", "\"padded\""),
+        ("\"shtark\".starts_with(\"sh\")", "true"),
+        ("\"shtark\".ends_with(\"sh\")", "false"),
+        ("\"shtark\".contains(\"tar\")", "true"),
+        ("\"a.b.c\".replace(\".\", \"::\")", "\"a::b::c\""),
+        ("\"Straße\".to_upper()", "\"STRASSE\""),
+        ("\"ÀB\".to_lower()", "\"àb\""),
+        ("\"ab\".repeat(0)", "\"\""),
+        ("\"ab\".repeat(3)", "\"ababab\""),
+        ("\"42\".pad_start(5, \"0\")", "\"00042\""),
+        ("\"long\".pad_start(2)", "\"long\""),
+        ("\"x\".pad_start(3)", "\"  x\""),
+        ("\"{} of {}: {{ok}}\".format(1, \"two\")", "\"1 of two: {ok}\""),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(repr(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn parsing_returns_results() {
+    let cases = [
+        ("\" 42 \".parse_int()", "Ok(42)"),
+        ("\"-0x1F\".parse_int()", "Ok(-31)"),
+        ("\"1_000\".parse_int()", "Ok(1000)"),
+        ("\"4.5\".parse_int()", "Err(\"Cannot parse \\\"4.5\\\" as an integer\")"),
+        ("\"99999999999999999999\".parse_int()", "Err(\"Cannot parse \\\"99999999999999999999\\\" as an integer\")"),
+        ("\"2.5e3\".parse_float()", "Ok(2500.0)"),
+        ("\"7\".parse_float()", "Ok(7.0)"),
+        ("\"seven\".parse_float()", "Err(\"Cannot parse \\\"seven\\\" as a float\")"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(repr(source), expected, "{}", source);
+    }
+    assert_eq!(eval("fn f(s) { return Ok(s.parse_int()? * 2); }\nf(\"21\").unwrap()"), RuntimeVal::from(42));
+}
+
+#[test]
+fn misuse_raises_errors() {
+    let cases = [
+        ("\"abc\".split(\"\")", "ValueError", "split needs a non-empty string"),
+        ("\"abc\".replace(\"\", \"x\")", "ValueError", "replace needs a non-empty string"),
+        ("\"abc\".repeat(0 - 1)", "ValueError", "Cannot repeat a string -1 times"),
+        ("\"{}\".format()", "ValueError", "Format string has 1 placeholder(s) but received 0 argument(s)"),
+        ("\"x\".format(1)", "ValueError", "Format string has 0 placeholder(s) but received 1 argument(s)"),
+        ("\"{x}\".format(1)", "ValueError", "Unmatched '{' in format string; write '{{' for a literal brace"),
+        ("\"abc\".shout()", "TypeError", "String has no method 'shout'"),
+        ("\"abc\".pad_start()", "TypeError", "pad_start expects 1 or 2 argument(s) but received 0"),
+        ("\",\".join(\"abc\")", "TypeError", "Expected a List but found String"),
+        ("let s = \"abc\"; s[0] = \"x\";", "TypeError", "Strings are immutable, so their characters cannot be assigned"),
+    ];
+    for (source, kind, message) in cases {
+        assert_eq!(error(source), (String::from(kind), String::from(message)), "{}", source);
+    }
+}
+
+#[test]
+fn lists_are_shared_and_indexable() {
+    let mut engine = Engine::new();
+    engine.eval("let xs = [1, \"two\", [3]];\nlet ys = xs;\nys.push(4);\nxs[0] = 10;").unwrap();
+    assert_eq!(engine.eval("ys").unwrap().repr(), "[10, \"two\", [3], 4]");
+    assert_eq!(engine.eval("xs[2][0]").unwrap(), RuntimeVal::from(3));
+    assert_eq!(engine.eval("xs.len()").unwrap(), RuntimeVal::from(4));
+    assert_eq!(engine.eval("xs.pop()").unwrap(), RuntimeVal::from(4));
+    assert_eq!(engine.eval("[].pop()").unwrap(), RuntimeVal::from(()));
+    assert_eq!(engine.eval("[1, [2]] == [1, [2]]").unwrap(), RuntimeVal::from(true));
+    engine.eval("xs.push(xs);").unwrap();
+    assert_eq!(engine.eval("xs").unwrap().repr(), "[10, \"two\", [3], [...]]");
+    assert_eq!(engine.eval("[1, 2][5]").map_err(|error| error.to_string()).unwrap_err(), "Uncaught IndexError: Index 5 is out of range for length 2");
+}
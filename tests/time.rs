@@ -0,0 +1,94 @@
+#![allow(clippy::needless_return)]
+mod common;
+use std::time::{Duration, Instant};
+use shtark::Limits;
+use shtark::{Engine, EngineError};
+use common::on_both_engines;
+
+#[test]
+fn datetimes_expose_their_calendar_fields() {
+    on_both_engines(|mode| {
+        let source = "let d = time.datetime(2024, 3, 10, 8, 15, 30);\n\
+            [d.year(), d.month(), d.day(), d.hour(), d.minute(), d.second(), d.weekday(), d.day_of_year(), d.unix()]";
+        assert_eq!(mode.repr(source), "[2024, 3, 10, 8, 15, 30, 7, 70, 1710058530]");
+        assert_eq!(mode.repr("time.datetime(1969, 12, 31, 23, 59, 59).unix()"), "-1");
+        assert_eq!(mode.repr("time.from_unix(1.5).unix_millis()"), "1500");
+        assert_eq!(mode.error("time.datetime(2023, 2, 29)"), (String::from("ValueError"), String::from("Invalid date 2023-02-29")));
+        assert_eq!(mode.error("time.datetime(2024, 1, 1, 24)"), (String::from("ValueError"), String::from("Invalid time 24:00:00")));
+        assert_eq!(mode.error("time.datetime(2024, 1)").0, "TypeError");
+    });
+}
+
+#[test]
+fn datetimes_parse_and_format() {
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr("time.parse(\"2024-03-10T08:15:30.25+02:00\")"), "2024-03-10T08:15:30.250+02:00");
+        assert_eq!(mode.repr("time.parse(\"2024-03-10t08:15:30.123456789z\").to_rfc3339()"), "\"2024-03-10T08:15:30.123456789Z\"");
+        assert_eq!(mode.repr("time.parse(\"2024-03-10T08:15:30+02:00\").to_utc()"), "2024-03-10T06:15:30Z");
+        assert_eq!(mode.repr("time.parse(\"Sun, 10 march 2024 7:05 -0130\", \"%a, %d %B %Y %H:%M %z\")"), "2024-03-10T07:05:00-01:30");
+        assert_eq!(mode.repr("time.parse(\"0100%\", \"%Y%%\")"), "0100-01-01T00:00:00Z");
+        let source = "time.datetime(2024, 2, 29, 13, 5).with_offset(time.minutes(330)).format(\"%A %d %b %Y %H:%M:%S.%f %:z day %j\")";
+        assert_eq!(mode.repr(source), "\"Thursday 29 Feb 2024 18:35:00.000000 +05:30 day 060\"");
+        let (kind, message) = mode.error("time.parse(\"2024-03-10 08:15\")");
+        assert_eq!((kind.as_str(), message.as_str()), ("ValueError", "Cannot parse '2024-03-10 08:15' as an RFC 3339 date and time"));
+        assert_eq!(mode.error("time.parse(\"10/03\", \"%d/%m/%Y\")").1, "Cannot parse '10/03' with the pattern '%d/%m/%Y'");
+        assert_eq!(mode.error("time.now().format(\"%Q\")").1, "Unknown format directive '%Q' in '%Q'");
+    });
+}
+
+#[test]
+fn durations_measure_and_print() {
+    on_both_engines(|mode| {
+        let source = "[time.hours(1) + time.minutes(2) + time.seconds(3.5), time.milliseconds(1.5), time.nanoseconds(12), \
+            time.microseconds(7), time.seconds(0), time.minutes(0 - 2) * 3, 2 * time.seconds(1), time.days(1) / 4]";
+        assert_eq!(mode.repr(source), "[1h2m3.5s, 1.5ms, 12ns, 7us, 0s, -6m0s, 2s, 6h0m0s]");
+        let source = "let d = time.minutes(90);\n\
+            [d.hours(), d.minutes(), d.seconds(), d.as_seconds(), d / time.hours(1), (time.seconds(0) - d).abs() == d]";
+        assert_eq!(mode.repr(source), "[1, 90, 5400, 5400.0, 1.5, true]");
+        assert_eq!(mode.repr("[time.seconds(3), time.seconds(1), time.milliseconds(1500)].sort()"), "[1s, 1.5s, 3s]");
+        assert_eq!(mode.error("time.seconds(1) / 0").0, "ZeroDivisionError");
+        assert_eq!(mode.error("time.days(9223372036854775807)").0, "OverflowError");
+        assert_eq!(mode.error("time.seconds(1) + 1").1, "Unsupported operand types for +: Duration and Number");
+    });
+}
+
+#[test]
+fn datetimes_move_by_durations() {
+    on_both_engines(|mode| {
+        assert_eq!(mode.repr("time.datetime(2024, 2, 28, 23, 30) + time.hours(1)"), "2024-02-29T00:30:00Z");
+        assert_eq!(mode.repr("time.days(366) + time.datetime(2023, 3, 1)"), "2024-03-01T00:00:00Z");
+        assert_eq!(mode.repr("time.datetime(2024, 3, 1) - time.datetime(2024, 2, 1)"), "696h0m0s");
+        let source = "let a = time.parse(\"2024-01-01T12:00:00+02:00\");\n\
+            let b = time.parse(\"2024-01-01T10:00:00Z\");\n\
+            [a == b, a.compare(b), a.is_before(b + time.seconds(1)), a.is_after(b), a.format(\"%H\")]";
+        assert_eq!(mode.repr(source), "[true, 0, true, false, \"12\"]");
+        assert_eq!(mode.error("time.datetime(9999, 12, 31) + time.days(1)").0, "OverflowError");
+        assert_eq!(mode.error("time.datetime(2024, 1, 1).with_offset(time.seconds(30))").0, "ValueError");
+        assert_eq!(mode.error("time.datetime(2024, 1, 1).compare(time.seconds(1))").0, "TypeError");
+    });
+}
+
+#[test]
+fn instants_and_sleep_measure_elapsed_time() {
+    on_both_engines(|mode| {
+        let source = "let start = time.instant();\n\
+            sleep(20);\n\
+            sleep(time.milliseconds(5));\n\
+            let later = start + time.seconds(1);\n\
+            [start.elapsed().is_after(time.milliseconds(24)), (later - start) == time.seconds(1), later.is_after(start)]";
+        assert_eq!(mode.repr(source), "[true, true, true]");
+        assert_eq!(mode.error("sleep(0 - 1)").1, "sleep needs a duration of at least 0, not -1ms");
+    });
+}
+
+#[test]
+fn sleeping_respects_the_timeout() {
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { timeout: Some(Duration::from_millis(50)), ..Limits::default() });
+    let started = Instant::now();
+    match engine.eval("sleep(10000)") {
+        Err(EngineError::Runtime(error)) => assert_eq!(error.kind, "TimeoutError"),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert!(started.elapsed() < Duration::from_secs(2));
+}